@@ -1,30 +1,377 @@
+#![allow(clippy::module_inception, clippy::upper_case_acronyms)]
+
 extern crate colored;
 
 mod van;
 use van::*;
 
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::panic;
+use std::thread;
+
+const USAGE: &str = "usage: van <command> [options] [files...]
+
+commands:
+    check     type-check the given files
+    parse     parse the given files and print their ast
+    tokens    print the token stream of the given files
+    run       check and run the given files on the bytecode vm
+    build     check and compile the given files to lua
+    disasm    check and print the bytecode of the given files
+
+options:
+    --ast         print the ast before checking
+    --walk        run with the tree-walking interpreter instead of the vm
+    -o <path>     write build output to <path>
+    -I <dir>      also look for imported modules in <dir>
+    -h, --help    print this message
+
+reads from stdin when no files, or '-', are given. `import foo.bar` loads foo/bar.van from the
+importing file's directory, then from every -I directory in order";
+
+// the tree-walking interpreter goes deeper into rust with every call, so it's given room for as
+// many of them as the vm allows
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Check,
+    Parse,
+    Tokens,
+    Run,
+    Build,
+    Disasm,
+}
+
+impl Command {
+    fn from_str(v: &str) -> Option<Command> {
+        match v {
+            "check"  => Some(Command::Check),
+            "parse"  => Some(Command::Parse),
+            "tokens" => Some(Command::Tokens),
+            "run"    => Some(Command::Run),
+            "build"  => Some(Command::Build),
+            "disasm" => Some(Command::Disasm),
+            _        => None,
+        }
+    }
+}
+
+struct Options {
+    command: Command,
+    ast:     bool,
+    walk:    bool,
+    output:  Option<String>,
+    paths:   Vec<PathBuf>,
+    inputs:  Vec<String>,
+}
+
+struct Source {
+    name: String,
+    text: String,
+}
+
+impl Source {
+    fn read(path: &str) -> Result<Source, String> {
+        let mut text = String::new();
+
+        if path == "-" {
+            io::stdin().read_to_string(&mut text).map_err(|e| format!("couldn't read stdin: {}", e))?;
+        } else {
+            text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        }
+
+        Ok(Source {
+            name: if path == "-" { "<stdin>".to_owned() } else { path.to_owned() },
+            text,
+        })
+    }
+
+    // where imports are looked for, starting next to the file itself
+    fn roots(&self, options: &Options) -> Vec<PathBuf> {
+        let directory = if self.name == "<stdin>" {
+            PathBuf::from(".")
+        } else {
+            Path::new(&self.name).parent().map(|p| p.to_path_buf()).unwrap_or_default()
+        };
+
+        let mut roots = vec![directory];
+        roots.extend(options.paths.iter().cloned());
+
+        roots
+    }
+
+    fn lines(&self) -> Vec<&str> {
+        self.text.lines().collect()
+    }
+
+    fn tokens(&self) -> Result<Vec<Token>, Response> {
+        make_lexer(self.text.chars().collect()).tokenize()
+    }
+
+    fn parse(&self) -> Result<Vec<Statement>, Response> {
+        let traveler   = Traveler::new(self.tokens()?);
+        let mut parser = Parser::new(traveler);
+
+        parser.parse()
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let command = match args.first() {
+        Some(c) => Command::from_str(c).ok_or_else(|| format!("unknown command: {}", c))?,
+        None    => return Err("missing command".to_owned()),
+    };
+
+    let mut options = Options {
+        command,
+        ast:    false,
+        walk:   false,
+        output: None,
+        paths:  Vec::new(),
+        inputs: Vec::new(),
+    };
+
+    let mut args = args[1 ..].iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ast"  => options.ast = true,
+            "--walk" => options.walk = true,
+            "-o"     => match args.next() {
+                Some(path) => options.output = Some(path.clone()),
+                None       => return Err("expected path after -o".to_owned()),
+            },
+
+            "-I" => match args.next() {
+                Some(path) => options.paths.push(PathBuf::from(path)),
+                None       => return Err("expected directory after -I".to_owned()),
+            },
+
+            "-" => options.inputs.push(arg.clone()),
+            a if a.starts_with('-') => return Err(format!("unknown option: {}", a)),
+            _   => options.inputs.push(arg.clone()),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        options.inputs.push("-".to_owned())
+    }
+
+    if options.output.is_some() && options.inputs.len() > 1 {
+        return Err("-o can only be used with a single input".to_owned())
+    }
+
+    Ok(options)
+}
+
+fn fail(message: &str) {
+    Response::error(None, message.to_owned()).display(None)
+}
+
+// reports every response and how many there were, returning whether any of them were errors
+fn report(responses: &[Response], source: &Source) -> bool {
+    let lines = source.lines();
+
+    let mut failed = false;
+
+    for response in responses {
+        response.display(Some(&lines));
+        failed |= response.is_error()
+    }
+
+    if let Some(summary) = Response::summary(responses) {
+        summary.display(None)
+    }
+
+    failed
+}
+
+// loads the modules `ast` imports and checks it against them, giving back the program with
+// overloads and partial applications desugared for the backends
+fn check(loader: &mut Loader, ast: &[Statement], source: &Source) -> Option<Vec<Statement>> {
+    let mut responses = Vec::new();
+
+    // each module's responses are shown against its own file, and counted along with the rest
+    if let Err((response, failures)) = loader.load_imports(ast) {
+        for failure in failures {
+            let module = Source {
+                name: failure.path.display().to_string(),
+                text: failure.text,
+            };
+
+            Response::note(None, format!("in {}", module.name)).display(None);
+            failure.response.display(Some(&module.lines()));
+
+            responses.push(failure.response)
+        }
+
+        if let Some(response) = response {
+            response.display(Some(&source.lines()));
+            responses.push(response)
+        }
+    }
+
+    // the file is checked against whatever modules did load
+    let mut visitor = loader.visitor();
+    let response    = visitor.visit(ast);
+
+    response.display(Some(&source.lines()));
+    responses.push(response);
+
+    if let Some(summary) = Response::summary(&responses) {
+        summary.display(None)
+    }
+
+    if responses.iter().any(|response| response.is_error()) {
+        return None
+    }
+
+    Some(desugar(ast, &visitor.resolved))
+}
+
+fn write_output(options: &Options, content: &str) -> bool {
+    match options.output {
+        Some(ref path) => match fs::write(path, content) {
+            Ok(())  => true,
+            Err(e)  => {
+                fail(&format!("couldn't write {}: {}", path, e));
+                false
+            },
+        },
+
+        None => {
+            print!("{}", content);
+            true
+        },
+    }
+}
+
+fn process(options: &Options, source: &Source) -> bool {
+    if options.command == Command::Tokens {
+        let tokens = match source.tokens() {
+            Ok(tokens) => tokens,
+            Err(e)     => return !report(&[e], source),
+        };
+
+        for token in tokens {
+            println!("{}:{}\t{:?}\t{:?}", token.position.line, token.position.col, token.token_type, token.content)
+        }
+
+        return true
+    }
+
+    let ast = match source.parse() {
+        Ok(ast) => ast,
+        Err(e)  => return !report(&[e], source),
+    };
+
+    if options.ast || options.command == Command::Parse {
+        println!("{:#?}", ast)
+    }
+
+    let mut loader = Loader::new(source.roots(options));
+
+    if source.name != "<stdin>" {
+        loader.start_from(Path::new(&source.name))
+    }
+
+    if options.command == Command::Parse {
+        return true
+    }
+
+    let ast = match check(&mut loader, &ast, source) {
+        Some(ast) => ast,
+        None      => return false,
+    };
+
+    // from here on, the program carries the modules it imports
+    let ast = match loader.link(&ast) {
+        Ok(ast) => ast,
+        Err(e)  => return !report(&[e], source),
+    };
+
+    match options.command {
+        Command::Check => true,
+
+        Command::Run => {
+            let result = if options.walk {
+                Interpreter::new().run(&ast)
+            } else {
+                Compiler::new().compile(&ast).and_then(|prototype| Machine::new().run(prototype))
+            };
+
+            match result {
+                Ok(_)  => true,
+                Err(e) => {
+                    e.display(Some(&source.lines()));
+                    fail(&format!("{}: aborted at runtime", source.name));
+                    false
+                },
+            }
+        },
+
+        Command::Build => {
+            match LuaGenerator::new().generate(&ast) {
+                Ok(lua) => write_output(options, &lua),
+                Err(e)  => !report(&[e], source),
+            }
+        },
+
+        Command::Disasm => {
+            match Compiler::new().compile(&ast) {
+                Ok(prototype) => write_output(options, &prototype.disassemble()),
+                Err(e)        => !report(&[e], source),
+            }
+        },
+
+        Command::Parse  |
+        Command::Tokens => unreachable!(),
+    }
+}
+
 fn main() {
-    let source = r#"
+    let main = thread::Builder::new().stack_size(STACK_SIZE).spawn(start).expect("failed to start");
+
+    if let Err(e) = main.join() {
+        panic::resume_unwind(e)
+    }
+}
 
-    "#;
+fn start() {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let lexer      = make_lexer(source.clone().chars().collect());
-    let traveler   = Traveler::new(lexer.collect());
-    let mut parser = Parser::new(traveler);
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return
+    }
 
-    match parser.parse() {
-        Ok(ast) => {
-            println!("{:#?}", ast);
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e)      => {
+            fail(&e);
+            eprintln!("{}", USAGE);
+            process::exit(2)
+        }
+    };
 
-            let mut visitor = Visitor::new();
-            
-            for statement in ast {
-                match visitor.visit_statement(&statement) {
-                    Ok(()) => (),
-                    Err(e) => e.display(Some(&source.lines().collect()))
-                }
+    let mut failed = false;
+
+    for input in &options.inputs {
+        match Source::read(input) {
+            Ok(source) => failed |= !process(&options, &source),
+            Err(e)     => {
+                fail(&e);
+                failed = true
             }
         }
-        Err(e) => e.display(Some(&source.lines().collect()))
+    }
+
+    if failed {
+        process::exit(1)
     }
 }
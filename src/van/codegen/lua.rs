@@ -0,0 +1,1346 @@
+use std::collections::HashSet;
+use std::mem;
+
+use super::*;
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// what the generated code leans on where lua doesn't behave like the vm, in the order they're defined,
+// each with the ones it needs. none of the names can clash with a mangled one
+const HELPERS: &[(&str, &[&str], &str)] = &[
+    ("__show", &[], r#"-- writes values the way the vm does, numbers with the shortest digits that read back the same
+local function __show(v)
+    if type(v) ~= "number" then
+        return tostring(v)
+    elseif v ~= v then
+        return "nan"
+    elseif v == math.huge or v == -math.huge then
+        return v > 0 and "inf" or "-inf"
+    elseif v == 0 then
+        return "0"
+    elseif v % 1 == 0 and math.abs(v) < 1e15 then
+        return string.format("%.0f", v)
+    end
+
+    for precision = 1, 17 do
+        local written = string.format("%." .. (precision - 1) .. "e", v)
+
+        if tonumber(written) == v then
+            local sign, digits, exponent = written:match("^(-?)([%d.]+)e([-+]%d+)$")
+
+            digits   = digits:gsub("%.", "")
+            exponent = tonumber(exponent)
+
+            if exponent < 0 then
+                return sign .. "0." .. string.rep("0", -exponent - 1) .. digits
+            elseif exponent + 1 < #digits then
+                return sign .. digits:sub(1, exponent + 1) .. "." .. digits:sub(exponent + 2)
+            else
+                return sign .. digits .. string.rep("0", exponent + 1 - #digits)
+            end
+        end
+    end
+
+    return tostring(v)
+end
+"#),
+
+    ("__check", &["__show"], r#"local function __check(i, length)
+    if i < 0 or i % 1 ~= 0 or i >= length then
+        error("index out of bounds: " .. __show(i), 3)
+    end
+end
+"#),
+
+    ("__at", &["__check"], r#"-- indexes from 0, failing outside of the array like the vm does
+local function __at(t, i)
+    __check(i, #t)
+    return t[i + 1]
+end
+"#),
+
+    ("__slot", &["__check"], r#"-- where in `t` its element `i` is set
+local function __slot(t, i)
+    __check(i, #t)
+    return i + 1
+end
+"#),
+];
+
+// where the value of a lowered expression should end up
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Return,
+    Assign(&'a str),
+    Discard,
+}
+
+// where a name bound by a pattern gets its value, depending on the alternative that fit
+#[derive(Clone)]
+enum Binding {
+    Value(String),
+    Choice(Vec<(String, Binding)>), // (condition, binding)
+}
+
+pub struct LuaGenerator {
+    out:     String,
+    indent:  usize,
+    temps:   usize,
+    structs: HashSet<String>,
+    externs: HashSet<String>,     // names the host provides, which are used as they are
+    helpers: HashSet<&'static str>,
+    loops:   Vec<Option<String>>, // the label `continue` jumps to in each loop being generated
+    blocks:  Vec<BlockExit>,      // the block expressions with a `return` being generated, in the fun being generated
+}
+
+// where a `return` in a block expression puts its value, and the label after the block it jumps to
+#[derive(Clone)]
+struct BlockExit {
+    label: String,
+    into:  Option<String>,
+}
+
+impl LuaGenerator {
+    pub fn new() -> LuaGenerator {
+        LuaGenerator {
+            out:     String::new(),
+            indent:  0,
+            temps:   0,
+            structs: HashSet::new(),
+            externs: HashSet::new(),
+            helpers: HashSet::new(),
+            loops:   Vec::new(),
+            blocks:  Vec::new(),
+        }
+    }
+
+    pub fn generate(&mut self, ast: &[Statement]) -> Result<String, Response> {
+        let mut chunks: Vec<String> = Vec::new();
+
+        for statement in ast {
+            self.statement(statement)?;
+
+            let chunk = mem::take(&mut self.out);
+
+            if !chunk.is_empty() {
+                chunks.push(chunk)
+            }
+        }
+
+        for &(name, _, definition) in HELPERS.iter().rev() {
+            if self.helpers.contains(name) {
+                chunks.insert(0, definition.to_owned())
+            }
+        }
+
+        // multi-line statements get some space around them
+        let mut lua = String::new();
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i > 0 && (chunk.lines().count() > 1 || chunks[i - 1].lines().count() > 1) {
+                lua.push('\n')
+            }
+
+            lua.push_str(chunk)
+        }
+
+        Ok(lua)
+    }
+
+    fn indentation(&self) -> String {
+        "    ".repeat(self.indent)
+    }
+
+    fn line(&mut self, content: &str) {
+        let indentation = self.indentation();
+
+        self.out.push_str(&indentation);
+        self.out.push_str(content);
+        self.out.push('\n')
+    }
+
+    // the name of a helper, which gets defined along with the ones it needs
+    fn helper(&mut self, name: &'static str) -> &'static str {
+        if self.helpers.insert(name) {
+            for &(helper, needs, _) in HELPERS {
+                if helper == name {
+                    for need in needs {
+                        self.helper(need);
+                    }
+                }
+            }
+        }
+
+        name
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("_t{}", self.temps - 1)
+    }
+
+    // runs `f` one level deeper, returning whatever it wrote instead of emitting it
+    fn capture<T>(&mut self, f: &mut dyn FnMut(&mut Self) -> Result<T, Response>) -> Result<(T, String), Response> {
+        let outer = mem::take(&mut self.out);
+
+        self.indent += 1;
+        let result = f(self);
+        self.indent -= 1;
+
+        let inner = mem::replace(&mut self.out, outer);
+
+        Ok((result?, inner))
+    }
+
+    // the body of a fun, where a `return` leaves the fun whatever block expressions are around it
+    fn fun_body(&mut self, f: &mut dyn FnMut(&mut Self) -> Result<(), Response>) -> Result<String, Response> {
+        let blocks = mem::take(&mut self.blocks);
+        let result = self.capture(f);
+
+        self.blocks = blocks;
+
+        Ok(result?.1)
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), Response> {
+        match *s {
+            Statement::Expression(ref e) => self.expression_into(e, Target::Discard),
+
+            Statement::Definition(Definition {ref name, ref right, ..}) => {
+                let name = self.name(name);
+
+                match *right {
+                    Some(ref right) if needs_statements(right) => {
+                        self.line(&format!("local {}", name));
+                        self.expression_into(right, Target::Assign(&name))
+                    },
+
+                    Some(ref right) => {
+                        let right = self.expression(right)?;
+                        self.line(&format!("local {} = {}", name, right));
+
+                        Ok(())
+                    },
+
+                    None => {
+                        self.line(&format!("local {}", name));
+                        Ok(())
+                    },
+                }
+            },
+
+            // the visitor made sure the pattern always fits, so only its bindings matter
+            Statement::Destructure(Destructure {ref pattern, ref right, ..}) => {
+                let subject = self.subject(right)?;
+
+                let mut bindings = Vec::new();
+                self.pattern(pattern, &subject, &mut Vec::new(), &mut bindings)?;
+
+                for (name, binding) in bindings {
+                    match binding {
+                        Binding::Value(ref value) => self.line(&format!("local {} = {}", name, value)),
+                        ref binding               => {
+                            self.line(&format!("local {}", name));
+                            self.bind(&name, binding)
+                        },
+                    }
+                }
+
+                Ok(())
+            },
+
+            Statement::Assignment(Assignment {ref left, ref right, ..}) if matches!(**left, Expression::Tuple(..)) => {
+                let value = self.subject(right)?;
+                self.unpack(left, &value)
+            },
+
+            Statement::Assignment(Assignment {ref left, ref right, ..}) => {
+                let left = self.place(left)?;
+
+                if needs_statements(right) {
+                    self.expression_into(right, Target::Assign(&left))
+                } else {
+                    let right = self.expression(right)?;
+                    self.line(&format!("{} = {}", left, right));
+
+                    Ok(())
+                }
+            },
+
+            Statement::Fun(Fun {ref t, ref name, ref params, ref body, ..}) => {
+                let name   = self.function_name(name)?;
+                let params = params.iter().map(|p| self.name(&p.name)).collect::<Vec<String>>();
+
+                let body   = self.fun_body(&mut |g| g.block(body, body_target(t)))?;
+                let indent = self.indentation();
+
+                self.line(&format!("{}({})\n{}{}end", name, params.join(", "), body, indent));
+
+                Ok(())
+            },
+
+            Statement::FunctionMatch(ref function) => {
+                let name   = self.function_name(&function.name)?;
+                let params = (0 .. function.arity()).map(|_| self.temp()).collect::<Vec<String>>();
+
+                let body   = self.fun_body(&mut |g| g.arms(&function.arms, &params, Target::Return))?;
+                let indent = self.indentation();
+
+                self.line(&format!("{}({})\n{}{}end", name, params.join(", "), body, indent));
+
+                Ok(())
+            },
+
+            Statement::Struct(Struct {ref name, ..}) => {
+                let name = self.name(name);
+
+                self.line(&format!("local {} = {{}}", name));
+                self.line(&format!("{}.__index = {}", name, name));
+
+                self.structs.insert(name);
+
+                Ok(())
+            },
+
+            // variants are tables tagged with their name, holding what they carry in order
+            Statement::Data(Data {ref variants, ..}) => {
+                for variant in variants {
+                    let name = self.name(&variant.name);
+
+                    if variant.fields.is_empty() {
+                        self.line(&format!("local {} = {{tag = \"{}\"}}", name, variant.name));
+                    } else {
+                        let fields = (0 .. variant.fields.len()).map(|i| format!("_{}", i)).collect::<Vec<String>>().join(", ");
+
+                        self.line(&format!("local function {}({})", name, fields));
+                        self.indent += 1;
+                        self.line(&format!("return {{tag = \"{}\", {}}}", variant.name, fields));
+                        self.indent -= 1;
+                        self.line("end");
+                    }
+                }
+
+                Ok(())
+            },
+
+            Statement::If(ref base)         => self.if_into(base, false, Target::Discard),
+            Statement::Unless(ref unless)   => self.if_into(&unless.base, true, Target::Discard),
+            Statement::MatchPattern(ref m)  => self.match_into(m, Target::Discard),
+            Statement::Return(ref e)        => self.ret(e, false),
+
+            Statement::While(While {ref condition, ref body}) => {
+                let (condition, prelude) = self.capture(&mut |g| g.expression(condition))?;
+
+                if prelude.is_empty() {
+                    self.line(&format!("while {} do", condition));
+                } else {
+                    // the condition needs statements of its own, so it's evaluated inside the loop
+                    self.line("while true do");
+                    self.out.push_str(&prelude);
+
+                    self.indent += 1;
+                    self.line(&format!("if not {} then break end", parenthesize(&condition)));
+                    self.indent -= 1;
+                }
+
+                self.loop_body(&[], body)
+            },
+
+            Statement::For(ref f) => self.for_loop(f),
+
+            Statement::Break(_) => {
+                self.line("break");
+                Ok(())
+            },
+
+            Statement::Continue(_) => {
+                let label = self.loops.last().cloned().flatten().expect("continue outside of a loop");
+
+                self.line(&format!("goto {}", label));
+                Ok(())
+            },
+
+            // methods land in the struct's table, which instances use as their metatable
+            Statement::Implementation(ref implementation) => {
+                for (i, method) in implementation.methods().iter().enumerate() {
+                    if i > 0 {
+                        self.out.push('\n')
+                    }
+
+                    self.statement(method)?
+                }
+
+                Ok(())
+            },
+
+            // externs are provided by the host, and the rest only matter to the checker
+            Statement::Extern(ref statement) => {
+                if let Statement::Definition(Definition {ref name, ..}) = **statement {
+                    self.externs.insert(name.clone());
+                }
+
+                Ok(())
+            },
+
+            Statement::Interface(_) |
+            Statement::Import(_)    => Ok(()),
+        }
+    }
+
+    // a literal range is counted through, and anything else is an array, which is what ranges are made into
+    fn for_loop(&mut self, f: &For) -> Result<(), Response> {
+        let name = self.name(&f.name);
+
+        if let Expression::BinaryOp(ref op) = f.iterable {
+            if op.op == Operand::Range {
+                if let (&Expression::Number(from), &Expression::Number(to), &None) = (&*op.left, &*op.right, &f.index) {
+                    if from.fract() == 0.0 && to.fract() == 0.0 {
+                        self.line(&format!("for {} = {}, {} do", name, number(from), number(to - 1.0)));
+                        return self.loop_body(&[], &f.body)
+                    }
+                }
+
+                let from = match *op.left {
+                    Expression::Number(n) => number(n),
+                    ref e                 => self.subject(e)?,
+                };
+
+                let to    = self.operand(&op.right, 5, false)?;
+                let count = f.index.as_ref().map(|index| self.name(index)).unwrap_or_else(|| self.temp());
+
+                let last = match (&*op.left, &*op.right) {
+                    (&Expression::Number(from), &Expression::Number(to)) => number((to - from).ceil() - 1.0),
+                    _                                                    => format!("math.ceil({} - {}) - 1", to, from),
+                };
+
+                self.line(&format!("for {} = 0, {} do", count, last));
+                return self.loop_body(&[format!("local {} = {} + {}", name, from, count)], &f.body)
+            }
+        }
+
+        let array = self.subject(&f.iterable)?;
+        let count = self.temp();
+
+        let mut locals = vec![format!("local {} = {}[{}]", name, array, count)];
+
+        if let Some(ref index) = f.index {
+            locals.push(format!("local {} = {} - 1", self.name(index), count))
+        }
+
+        self.line(&format!("for {} = 1, #{} do", count, array));
+        self.loop_body(&locals, &f.body)
+    }
+
+    // the rest of a loop whose first line is out already, `continue` jumping to the end of it
+    fn loop_body(&mut self, locals: &[String], body: &[Statement]) -> Result<(), Response> {
+        let label = if continues(body) { Some(format!("continue{}", self.temp())) } else { None };
+
+        self.loops.push(label.clone());
+        self.indent += 1;
+
+        for local in locals {
+            self.line(local)
+        }
+
+        // the label has to come after everything, a last `return` included
+        let result = match label {
+            Some(ref label) => {
+                self.line("do");
+
+                self.indent += 1;
+                let result = self.block(body, Target::Discard);
+                self.indent -= 1;
+
+                self.line("end");
+                self.line(&format!("::{}::", label));
+
+                result
+            },
+
+            None => self.block(body, Target::Discard),
+        };
+
+        self.indent -= 1;
+        self.loops.pop();
+
+        self.line("end");
+
+        result
+    }
+
+    fn function_name(&mut self, name: &Option<Expression>) -> Result<String, Response> {
+        match *name {
+            Some(Expression::Identifier(ref name, _)) => Ok(format!("local function {}", self.name(name))),
+
+            // `function t["end"]()` isn't lua, so those are assigned instead
+            Some(ref name) => match self.expression(name)? {
+                target if target.ends_with(']') => Ok(format!("{} = function", target)),
+                target                          => Ok(format!("function {}", target)),
+            },
+
+            None => Ok("function".to_owned()),
+        }
+    }
+
+    // an extern is the host's own name, and anything else is mangled
+    fn name(&self, name: &str) -> String {
+        match self.externs.contains(name) {
+            true if is_lua_name(name) => name.to_owned(),
+            true                      => format!("_G[{}]", string(name)),
+            false                     => mangle(name),
+        }
+    }
+
+    fn block(&mut self, body: &[Statement], target: Target) -> Result<(), Response> {
+        for (i, statement) in body.iter().enumerate() {
+            let last = i + 1 == body.len();
+
+            match *statement {
+                Statement::Expression(ref e) if last => self.expression_into(e, target)?,
+                Statement::Return(ref e)             => self.ret(e, last)?,
+                ref s                                => self.statement(s)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ret(&mut self, e: &Option<Expression>, last: bool) -> Result<(), Response> {
+        let value = match *e {
+            Some(ref e) if needs_statements(e) => return self.expression_into(e, Target::Return),
+            Some(ref e)                        => Some(self.expression(e)?),
+            None                               => None,
+        };
+
+        self.return_value(value, e.as_ref().is_some_and(is_call), last);
+
+        Ok(())
+    }
+
+    // lua only allows `return` as the last statement of a block, and one in a block expression
+    // gives the block its value and jumps past it instead
+    fn return_value(&mut self, value: Option<String>, call: bool, last: bool) {
+        match (self.blocks.last().cloned(), value) {
+            (Some(exit), value) => {
+                match (exit.into, value) {
+                    (Some(into), value) => self.line(&format!("{} = {}", into, value.as_deref().unwrap_or("nil"))),
+                    (None, Some(value)) => if call {
+                        self.line(&value)
+                    } else {
+                        self.line(&format!("local _ = {}", value))
+                    },
+                    (None, None)        => (),
+                }
+
+                self.line(&format!("goto {}", exit.label))
+            },
+
+            (None, Some(value)) if last => self.line(&format!("return {}", value)),
+            (None, Some(value))         => self.line(&format!("do return {} end", value)),
+            (None, None) if last        => self.line("return"),
+            (None, None)                => self.line("do return end"),
+        }
+    }
+
+    fn expression_into(&mut self, e: &Expression, target: Target) -> Result<(), Response> {
+        match *e {
+            Expression::If(ref base)      => self.if_into(base, false, target),
+            Expression::Unless(ref a)     => self.if_into(&a.base, true, target),
+            Expression::MatchPattern(ref m) => self.match_into(m, target),
+            Expression::Block(ref body)   => self.block_into(body, target),
+            Expression::EOF               => Ok(()),
+
+            _ => {
+                let value = self.expression(e)?;
+
+                match target {
+                    Target::Return       => self.return_value(Some(value), is_call(e), true),
+                    Target::Assign(name) => self.line(&format!("{} = {}", name, value)),
+                    Target::Discard      => if is_call(e) {
+                        self.line(&value)
+                    } else {
+                        self.line(&format!("local _ = {}", value))
+                    },
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    fn if_into(&mut self, base: &If, negate: bool, target: Target) -> Result<(), Response> {
+        let condition = self.expression(&base.condition)?;
+
+        if negate {
+            self.line(&format!("if not {} then", parenthesize(&condition)))
+        } else {
+            self.line(&format!("if {} then", condition))
+        }
+
+        self.indent += 1;
+        self.block(&base.body, target)?;
+        self.indent -= 1;
+
+        // conditions that need statements can't go in an `elseif`, so they nest instead
+        let mut nested = 0;
+
+        if let Some(ref elses) = base.elses {
+            for (condition, body) in elses {
+                self.indent += nested;
+
+                match *condition {
+                    Some(ref condition) => {
+                        let (condition, prelude) = self.capture(&mut |g| g.expression(condition))?;
+
+                        if prelude.is_empty() {
+                            self.line(&format!("elseif {} then", condition))
+                        } else {
+                            self.line("else");
+                            self.out.push_str(&prelude);
+
+                            self.indent += 1;
+                            self.line(&format!("if {} then", condition));
+
+                            nested += 1
+                        }
+                    },
+
+                    None => self.line("else"),
+                }
+
+                self.indent += 1;
+                self.block(body, target)?;
+                self.indent -= nested + 1;
+            }
+        }
+
+        for i in (0 ..= nested).rev() {
+            self.indent += i;
+            self.line("end");
+            self.indent -= i;
+        }
+
+        Ok(())
+    }
+
+    fn match_into(&mut self, m: &MatchPattern, target: Target) -> Result<(), Response> {
+        let subject = self.subject(&m.matching)?;
+        self.arms(&m.arms, &[subject], target)
+    }
+
+    fn arms(&mut self, arms: &[MatchArm], subjects: &[String], target: Target) -> Result<(), Response> {
+        // a failing guard has to fall through to the next arm, which an elseif chain can't do
+        let matched = if arms.iter().any(|arm| arm.guard.is_some()) {
+            let matched = self.temp();
+            self.line(&format!("local {} = false", matched));
+
+            Some(matched)
+        } else {
+            None
+        };
+
+        let mut first = true;
+
+        for arm in arms {
+            let mut conditions = Vec::new();
+            let mut bindings   = Vec::new();
+
+            for (param, subject) in arm.params.iter().zip(subjects) {
+                self.pattern(param, subject, &mut conditions, &mut bindings)?
+            }
+
+            let irrefutable = conditions.is_empty() && arm.guard.is_none();
+
+            match matched {
+                Some(ref matched) => {
+                    conditions.insert(0, format!("not {}", matched));
+                    self.line(&format!("if {} then", conditions.join(" and ")))
+                },
+
+                // anything fits, making any following arms unreachable
+                None if irrefutable => self.line(if first { "do" } else { "else" }),
+                None                => self.line(&format!("{} {} then", if first { "if" } else { "elseif" }, conditions.join(" and "))),
+            }
+
+            self.indent += 1;
+
+            for (name, binding) in &bindings {
+                match *binding {
+                    Binding::Value(ref value) => self.line(&format!("local {} = {}", name, value)),
+
+                    Binding::Choice(_) => {
+                        self.line(&format!("local {}", name));
+                        self.bind(name, binding)
+                    },
+                }
+            }
+
+            if let Some(ref guard) = arm.guard {
+                let guard = self.expression(guard)?;
+
+                self.line(&format!("if {} then", guard));
+                self.indent += 1;
+            }
+
+            if let Some(ref matched) = matched {
+                self.line(&format!("{} = true", matched))
+            }
+
+            self.expression_into(&arm.body, target)?;
+
+            if arm.guard.is_some() {
+                self.indent -= 1;
+                self.line("end");
+            }
+
+            self.indent -= 1;
+
+            if matched.is_some() {
+                self.line("end")
+            }
+
+            first = false;
+
+            if irrefutable {
+                break
+            }
+        }
+
+        if matched.is_none() && !first {
+            self.line("end")
+        }
+
+        Ok(())
+    }
+
+    // what has to hold for `subject` to fit `pattern`, and what it binds
+    fn pattern(&mut self, pattern: &Pattern, subject: &str, conditions: &mut Vec<String>, bindings: &mut Vec<(String, Binding)>) -> Result<(), Response> {
+        match *pattern {
+            Pattern::Wildcard(_) => (),
+
+            Pattern::Binding(ref name, _) => bindings.push((self.name(name), Binding::Value(subject.to_owned()))),
+
+            Pattern::Literal(ref literal, _) => {
+                let value = self.expression(literal)?;
+                conditions.push(format!("{} == {}", subject, value))
+            },
+
+            Pattern::Array(ref content, ref rest, _) => {
+                conditions.push(match *rest {
+                    Some(_) => format!("#{} >= {}", subject, content.len()),
+                    None    => format!("#{} == {}", subject, content.len()),
+                });
+
+                for (i, pattern) in content.iter().enumerate() {
+                    self.pattern(pattern, &format!("{}[{}]", subject, i + 1), conditions, bindings)?
+                }
+
+                if let Some(Some(ref rest)) = *rest {
+                    bindings.push((self.name(rest), Binding::Value(format!("{{(table.unpack or unpack)({}, {})}}", subject, content.len() + 1))))
+                }
+            },
+
+            Pattern::Struct(_, ref fields, _) => for (name, pattern) in fields {
+                self.pattern(pattern, &member(subject, name), conditions, bindings)?
+            },
+
+            Pattern::Variant(ref name, ref fields, _) => {
+                conditions.push(format!("{}.tag == \"{}\"", subject, name));
+
+                for (i, pattern) in fields.iter().enumerate() {
+                    self.pattern(pattern, &format!("{}[{}]", subject, i + 1), conditions, bindings)?
+                }
+            },
+
+            Pattern::Tuple(ref content, _) => for (i, pattern) in content.iter().enumerate() {
+                self.pattern(pattern, &format!("{}[{}]", subject, i + 1), conditions, bindings)?
+            },
+
+            // each name gets its value from whichever alternative fit
+            Pattern::Or(ref alternatives) => {
+                let mut choices: Vec<(String, Vec<(String, Binding)>)> = Vec::new();
+
+                for alternative in alternatives {
+                    let mut alternative_conditions = Vec::new();
+                    let mut alternative_bindings   = Vec::new();
+
+                    self.pattern(alternative, subject, &mut alternative_conditions, &mut alternative_bindings)?;
+
+                    let condition = if alternative_conditions.is_empty() {
+                        "true".to_owned()
+                    } else {
+                        alternative_conditions.join(" and ")
+                    };
+
+                    choices.push((condition, alternative_bindings))
+                }
+
+                conditions.push(format!("({})", choices.iter().map(|c| format!("({})", c.0)).collect::<Vec<_>>().join(" or ")));
+
+                for name in pattern.bindings() {
+                    let name = self.name(&name);
+
+                    let choice = choices.iter().map(|(condition, bound)| {
+                        (condition.clone(), bound.iter().find(|b| b.0 == name).unwrap().1.clone())
+                    }).collect();
+
+                    bindings.push((name, Binding::Choice(choice)))
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    // assigns each element of the tuple on the left its part of `value`
+    // what's assigned to, where an element has to be in the array already
+    fn place(&mut self, e: &Expression) -> Result<String, Response> {
+        match *e {
+            Expression::Index(Index {ref id, ref index, field: false, ..}) => {
+                let id    = self.subject(id)?;
+                let index = self.expression(index)?;
+
+                Ok(format!("{}[{}({}, {})]", id, self.helper("__slot"), id, index))
+            },
+
+            ref e => self.expression(e),
+        }
+    }
+
+    fn unpack(&mut self, left: &Expression, value: &str) -> Result<(), Response> {
+        match *left {
+            Expression::Tuple(ref content, _) => for (i, left) in content.iter().enumerate() {
+                self.unpack(left, &format!("{}[{}]", value, i + 1))?
+            },
+
+            ref left => {
+                let left = self.place(left)?;
+                self.line(&format!("{} = {}", left, value))
+            },
+        }
+
+        Ok(())
+    }
+
+    fn bind(&mut self, name: &str, binding: &Binding) {
+        match *binding {
+            Binding::Value(ref value) => self.line(&format!("{} = {}", name, value)),
+
+            Binding::Choice(ref choices) => for (i, (condition, binding)) in choices.iter().enumerate() {
+                self.line(&match i {
+                    0                             => format!("if {} then", condition),
+                    i if i + 1 == choices.len()   => "else".to_owned(),
+                    _                             => format!("elseif {} then", condition),
+                });
+
+                self.indent += 1;
+                self.bind(name, binding);
+                self.indent -= 1;
+
+                if i + 1 == choices.len() {
+                    self.line("end")
+                }
+            },
+        }
+    }
+
+    fn block_into(&mut self, body: &[Statement], target: Target) -> Result<(), Response> {
+        // a `return` inside a block expression only leaves the block, going where its value would,
+        // which for a block whose value is returned is what a `return` does already
+        let exit = match target {
+            Target::Return              => None,
+            _ if !contains_return(body) => None,
+            Target::Assign(name)        => Some(BlockExit {label: format!("block{}", self.temp()), into: Some(name.to_owned())}),
+            Target::Discard             => Some(BlockExit {label: format!("block{}", self.temp()), into: None}),
+        };
+
+        self.blocks.extend(exit.clone());
+        self.line("do");
+
+        self.indent += 1;
+        let result = self.block(body, target);
+        self.indent -= 1;
+
+        self.line("end");
+
+        if let Some(exit) = exit {
+            self.blocks.pop();
+            self.line(&format!("::{}::", exit.label))
+        }
+
+        result
+    }
+
+    fn expression(&mut self, e: &Expression) -> Result<String, Response> {
+        match *e {
+            Expression::Number(n)           => Ok(number(n)),
+            Expression::Bool(b)             => Ok(b.to_string()),
+            Expression::Str(ref s)          => Ok(string(s)),
+            Expression::Char(c)             => Ok(string(&c.to_string())),
+            Expression::Nil                 => Ok("nil".to_owned()),
+            Expression::Identifier(ref n, _) => Ok(self.name(n)),
+
+            Expression::BinaryOp(ref op) => self.binary_op(op),
+
+            Expression::UnaryOp(ref op) => {
+                let value = self.operand(&op.expr, 7, false)?;
+
+                Ok(match op.op {
+                    // `- -a` would be a comment without the space
+                    UnaryOperand::Neg if value.starts_with('-') => format!("-({})", value),
+                    UnaryOperand::Neg                           => format!("-{}", value),
+                    UnaryOperand::Not                           => format!("not {}", value),
+                })
+            },
+
+            Expression::Call(Call {ref callee, ref args, ..}) => {
+                let callee_value = self.expression(callee)?;
+
+                let mut values = Vec::new();
+
+                for arg in args {
+                    values.push(self.expression(arg)?)
+                }
+
+                Ok(format!("{}({})", prefix(callee, callee_value), values.join(", ")))
+            },
+
+            Expression::Index(Index {ref id, ref index, field, ..}) => {
+                let id = prefix(id, self.expression(id)?);
+
+                if field {
+                    match **index {
+                        Expression::Identifier(ref name, _) => Ok(member(&id, name)),
+                        ref c => Err(Response::error(None, format!("can't access field with: {:?}", c))),
+                    }
+                } else {
+                    let index = self.expression(index)?;
+                    Ok(format!("{}({}, {})", self.helper("__at"), id, index))
+                }
+            },
+
+            Expression::Array(ref content, _) |
+            Expression::Tuple(ref content, _) => {
+                let mut values = Vec::new();
+
+                for expression in content {
+                    values.push(self.expression(expression)?)
+                }
+
+                Ok(format!("{{{}}}", values.join(", ")))
+            },
+
+            Expression::Initialization(ref a) => {
+                let Initialization {ref id, ref values, ..} = **a;
+
+                let mut fields = Vec::new();
+
+                self.indent += 1;
+
+                for value in values {
+                    let key = match *value.left {
+                        Expression::Identifier(ref name, _) if is_lua_name(name) => name.clone(),
+                        Expression::Identifier(ref name, _)                      => format!("[{}]", string(name)),
+                        ref c => return Err(Response::error(None, format!("can't initialize invalid key: {:?}", c))),
+                    };
+
+                    fields.push(format!("{} = {}", key, self.expression(&value.right)?))
+                }
+
+                self.indent -= 1;
+
+                let table = if fields.is_empty() {
+                    "{}".to_owned()
+                } else if fields.iter().all(|f| !f.contains('\n')) && fields.iter().map(|f| f.len() + 2).sum::<usize>() < 60 {
+                    format!("{{{}}}", fields.join(", "))
+                } else {
+                    let indent = self.indentation();
+                    let mut table = "{\n".to_owned();
+
+                    for field in fields {
+                        table.push_str(&format!("{}    {},\n", indent, field))
+                    }
+
+                    table.push_str(&format!("{}}}", indent));
+                    table
+                };
+
+                match *id {
+                    Expression::Identifier(ref name, _) if self.structs.contains(&self.name(name)) => {
+                        Ok(format!("setmetatable({}, {})", table, self.name(name)))
+                    },
+
+                    _ => Ok(table),
+                }
+            },
+
+            Expression::Fun(ref a) => {
+                let Fun {ref t, ref params, ref body, ..} = **a;
+
+                let params = params.iter().map(|p| self.name(&p.name)).collect::<Vec<String>>();
+                let body   = self.fun_body(&mut |g| g.block(body, body_target(t)))?;
+
+                Ok(format!("function({})\n{}{}end", params.join(", "), body, self.indentation()))
+            },
+
+            Expression::FunctionMatch(ref a) => {
+                let params = (0 .. a.arity()).map(|_| self.temp()).collect::<Vec<String>>();
+                let body   = self.fun_body(&mut |g| g.arms(&a.arms, &params, Target::Return))?;
+
+                Ok(format!("function({})\n{}{}end", params.join(", "), body, self.indentation()))
+            },
+
+            Expression::Block(_)        |
+            Expression::If(_)           |
+            Expression::Unless(_)       |
+            Expression::MatchPattern(_) => self.hoist(e),
+
+            // types have no runtime value
+            Expression::Struct(_) |
+            Expression::EOF       => Ok("nil".to_owned()),
+        }
+    }
+
+    fn hoist(&mut self, e: &Expression) -> Result<String, Response> {
+        let temp = self.temp();
+
+        self.line(&format!("local {}", temp));
+        self.expression_into(e, Target::Assign(&temp))?;
+
+        Ok(temp)
+    }
+
+    // what's matched or destructured: a name is taken as it is, anything else is evaluated once into a temp
+    fn subject(&mut self, e: &Expression) -> Result<String, Response> {
+        match *e {
+            Expression::Identifier(ref name, _) => Ok(self.name(name)),
+            ref e if needs_statements(e)        => self.hoist(e),
+
+            ref e => {
+                let value = self.expression(e)?;
+                let temp  = self.temp();
+
+                self.line(&format!("local {} = {}", temp, value));
+                Ok(temp)
+            },
+        }
+    }
+
+    fn binary_op(&mut self, op: &BinaryOp) -> Result<String, Response> {
+        use self::Operand::*;
+
+        let (symbol, precedence, right_assoc) = match op.op {
+            PipeRight | PipeLeft => {
+                let (function, argument) = if op.op == PipeRight { (&op.right, &op.left) } else { (&op.left, &op.right) };
+
+                let argument = self.expression(argument)?;
+                let function = prefix(function, self.expression(function)?);
+
+                return Ok(format!("{}({})", function, argument))
+            },
+
+            Concat => {
+                let left  = self.concat_operand(&op.left)?;
+                let right = self.concat_operand(&op.right)?;
+
+                return Ok(format!("{} .. {}", left, right))
+            },
+
+            // a range made into a value is an array of the numbers in it
+            Range => {
+                let from  = self.expression(&op.left)?;
+                let to    = self.expression(&op.right)?;
+                let array = self.temp();
+                let n     = self.temp();
+                let end   = self.temp();
+
+                self.line(&format!("local {}, {}, {} = {{}}, {}, {}", array, n, end, from, to));
+                self.line(&format!("while {} < {} do", n, end));
+                self.indent += 1;
+                self.line(&format!("{}[#{} + 1] = {}", array, array, n));
+                self.line(&format!("{} = {} + 1", n, n));
+                self.indent -= 1;
+                self.line("end");
+
+                return Ok(array)
+            },
+
+            // `or` would also pass over false, so the right side gets a branch of its own
+            Default => {
+                let temp = self.hoist(&op.left)?;
+
+                self.line(&format!("if {} == nil then", temp));
+                self.indent += 1;
+                self.expression_into(&op.right, Target::Assign(&temp))?;
+                self.indent -= 1;
+                self.line("end");
+
+                return Ok(temp)
+            },
+
+            // the right side only runs when the left one doesn't settle it, so when it needs
+            // statements of its own, it gets a branch like `??` does
+            And | Or => {
+                let (right, prelude) = self.capture(&mut |g| g.expression(&op.right))?;
+
+                if prelude.is_empty() {
+                    if op.op == And { ("and", 2, false) } else { ("or", 1, false) }
+                } else {
+                    let temp = self.hoist(&op.left)?;
+
+                    self.line(&format!("if {}{} then", if op.op == And { "" } else { "not " }, temp));
+                    self.out.push_str(&prelude);
+
+                    self.indent += 1;
+                    self.line(&format!("{} = {}", temp, right));
+                    self.indent -= 1;
+
+                    self.line("end");
+
+                    return Ok(temp)
+                }
+            },
+
+            Xor     => ("~=", 3, false), // only ever given booleans
+            Equal   => ("==", 3, false),
+            NEqual  => ("~=", 3, false),
+            Lt      => ("<",  3, false),
+            Gt      => (">",  3, false),
+            LtEqual => ("<=", 3, false),
+            GtEqual => (">=", 3, false),
+            Add     => ("+",  5, false),
+            Sub     => ("-",  5, false),
+            Mul     => ("*",  6, false),
+            Div     => ("/",  6, false),
+            Mod     => ("%",  6, false),
+            Pow     => ("^",  8, true),
+        };
+
+        let left  = self.operand(&op.left, precedence, right_assoc)?;
+        let right = self.operand(&op.right, precedence, !right_assoc)?;
+
+        Ok(format!("{} {} {}", left, symbol, right))
+    }
+
+    // renders an operand, adding parens where lua's precedence would regroup it
+    fn operand(&mut self, e: &Expression, precedence: u8, tight: bool) -> Result<String, Response> {
+        let value = self.expression(e)?;
+
+        let inner = match *e {
+            Expression::BinaryOp(ref op) => match op.op {
+                Operand::Or  => 1,
+                Operand::And => 2,
+                Operand::Equal | Operand::NEqual | Operand::Xor | Operand::Lt | Operand::Gt | Operand::LtEqual | Operand::GtEqual => 3,
+                Operand::Concat => 4,
+                Operand::Add | Operand::Sub => 5,
+                Operand::Mul | Operand::Div | Operand::Mod => 6,
+                Operand::Pow => 8,
+                Operand::PipeLeft | Operand::PipeRight | Operand::Default | Operand::Range => return Ok(value),
+            },
+
+            Expression::UnaryOp(_) => 7,
+
+            _ => return Ok(value),
+        };
+
+        if inner < precedence || (inner == precedence && tight) {
+            Ok(format!("({})", value))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn concat_operand(&mut self, e: &Expression) -> Result<String, Response> {
+        match *e {
+            Expression::Str(_) | Expression::Char(_) => self.expression(e),
+            Expression::Number(n)                    => Ok(string(&format_number(n))),
+
+            Expression::BinaryOp(ref op) if op.op == Operand::Concat => {
+                let value = self.expression(e)?;
+                Ok(format!("({})", value))
+            },
+
+            _ => {
+                let value = self.expression(e)?;
+                Ok(format!("{}({})", self.helper("__show"), value))
+            },
+        }
+    }
+}
+
+// funs declared to return nil have nothing to return
+fn body_target<'a>(t: &Option<Type>) -> Target<'a> {
+    match *t {
+        Some(Type::Nil) => Target::Discard,
+        _               => Target::Return,
+    }
+}
+
+fn needs_statements(e: &Expression) -> bool {
+    matches!(*e, Expression::If(_) | Expression::Unless(_) | Expression::MatchPattern(_) | Expression::Block(_))
+}
+
+fn is_call(e: &Expression) -> bool {
+    match *e {
+        Expression::Call(_)          => true,
+        Expression::BinaryOp(ref op) => op.op == Operand::PipeLeft || op.op == Operand::PipeRight,
+        _                            => false,
+    }
+}
+
+// whether a `return` in this body leaves it, not counting nested functions and block expressions
+pub fn contains_return(body: &[Statement]) -> bool {
+    fn if_returns(base: &If) -> bool {
+        contains_return(&base.body) || base.elses.as_ref().is_some_and(|elses| elses.iter().any(|e| contains_return(&e.1)))
+    }
+
+    fn returns(e: &Expression) -> bool {
+        match *e {
+            Expression::If(ref base)   => if_returns(base),
+            Expression::Unless(ref a)  => if_returns(&a.base),
+            _                          => false,
+        }
+    }
+
+    body.iter().any(|s| match *s {
+        Statement::Return(_)            => true,
+        Statement::Expression(ref e)    => returns(e),
+        Statement::If(ref base)         => if_returns(base),
+        Statement::Unless(ref unless)   => if_returns(&unless.base),
+        Statement::While(ref w)         => contains_return(&w.body),
+        Statement::For(ref f)           => contains_return(&f.body),
+        _                               => false,
+    })
+}
+
+// whether a `continue` in this body goes on with the loop it's the body of, not counting nested loops
+fn continues(body: &[Statement]) -> bool {
+    fn if_continues(base: &If) -> bool {
+        continues(&base.body) || base.elses.as_ref().is_some_and(|elses| elses.iter().any(|e| continues(&e.1)))
+    }
+
+    fn in_expression(e: &Expression) -> bool {
+        match *e {
+            Expression::If(ref base)          => if_continues(base),
+            Expression::Unless(ref a)         => if_continues(&a.base),
+            Expression::MatchPattern(ref m)   => m.arms.iter().any(|arm| in_expression(&arm.body)),
+            Expression::Block(ref body)       => continues(body),
+            Expression::BinaryOp(ref op)      => in_expression(&op.left) || in_expression(&op.right),
+            Expression::UnaryOp(ref op)       => in_expression(&op.expr),
+            Expression::Call(ref call)        => in_expression(&call.callee) || call.args.iter().any(|arg| in_expression(arg)),
+            Expression::Index(ref index)      => in_expression(&index.id) || in_expression(&index.index),
+            Expression::Array(ref content, _) |
+            Expression::Tuple(ref content, _) => content.iter().any(in_expression),
+            _                                 => false,
+        }
+    }
+
+    body.iter().any(|s| match *s {
+        Statement::Continue(_)                                    => true,
+        Statement::Expression(ref e)                              => in_expression(e),
+        Statement::Definition(Definition {right: Some(ref e), ..}) => in_expression(e),
+        Statement::Assignment(Assignment {ref right, ..})         => in_expression(right),
+        Statement::Return(Some(ref e))                            => in_expression(e),
+        Statement::If(ref base)                                   => if_continues(base),
+        Statement::Unless(ref unless)                             => if_continues(&unless.base),
+        Statement::MatchPattern(ref m)                            => m.arms.iter().any(|arm| in_expression(&arm.body)),
+        _                                                         => false,
+    })
+}
+
+// whether lua can spell `name` as it is
+fn is_lua_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+// the field `name` of `table`, quoted when lua can't spell it
+fn member(table: &str, name: &str) -> String {
+    if is_lua_name(name) {
+        format!("{}.{}", table, name)
+    } else {
+        format!("{}[{}]", table, string(name))
+    }
+}
+
+// a name lua can take is kept as it is, as long as it has no `__` and doesn't end in `_`. any
+// other one has every `_`, `!`, `?` and `$` escaped, and gets a `_` at the end, so no two names
+// end up the same and none of them is a keyword
+fn mangle(name: &str) -> String {
+    let plain = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !name.contains("__") && !name.ends_with('_');
+
+    if plain && !KEYWORDS.contains(&name) {
+        return name.to_owned()
+    }
+
+    let mut mangled = String::new();
+
+    for c in name.chars() {
+        match c {
+            '_' => mangled.push_str("__"),
+            '!' => mangled.push_str("_b"),
+            '?' => mangled.push_str("_p"),
+            '$' => mangled.push_str("_v"),
+            c   => mangled.push(c),
+        }
+    }
+
+    mangled.push('_');
+    mangled
+}
+
+// wraps anything that can't be called or indexed as is
+fn prefix(e: &Expression, value: String) -> String {
+    match *e {
+        Expression::Identifier(..) |
+        Expression::Index(_)       |
+        Expression::Call(_)        => value,
+        _ if is_call(e)            => value,
+        _                          => format!("({})", value),
+    }
+}
+
+fn parenthesize(value: &str) -> String {
+    if value.chars().all(|c| c.is_alphanumeric() || "_.".contains(c)) {
+        value.to_owned()
+    } else {
+        format!("({})", value)
+    }
+}
+
+fn number(n: f64) -> String {
+    if n.is_nan() {
+        "(0 / 0)".to_owned()
+    } else if n.is_infinite() {
+        if n > 0.0 { "math.huge".to_owned() } else { "-math.huge".to_owned() }
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{:?}", n)
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"'  => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => result.push_str(&format!("\\{:03}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
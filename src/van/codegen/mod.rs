@@ -0,0 +1,5 @@
+pub mod lua;
+
+pub use super::*;
+
+pub use self::lua::*;
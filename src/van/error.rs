@@ -33,6 +33,45 @@ impl Response {
         Response::Group(responses)
     }
 
+    // a count of what went wrong across all of the responses, when anything did
+    pub fn summary(responses: &[Response]) -> Option<Response> {
+        let (errors, warnings) = responses.iter().fold((0, 0), |(e, w), r| {
+            let (a, b) = r.count();
+            (e + a, w + b)
+        });
+
+        let summary = match (errors, warnings) {
+            (0, 0) => return None,
+            (e, 0) => plural(e, "error"),
+            (0, w) => plural(w, "warning"),
+            (e, w) => format!("{} and {}", plural(e, "error"), plural(w, "warning")),
+        };
+
+        Some(Response::note(None, format!("found {}", summary)))
+    }
+
+    // (errors, warnings), counting inside groups
+    pub fn count(&self) -> (usize, usize) {
+        match *self {
+            Response::Error(..)            => (1, 0),
+            Response::Warning(..)          => (0, 1),
+            Response::Note(..)             => (0, 0),
+            Response::Group(ref responses) => responses.iter().fold((0, 0), |(e, w), r| {
+                let (a, b) = r.count();
+                (e + a, w + b)
+            }),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Response::Error(..)            => true,
+            Response::Group(ref responses) => responses.iter().any(|r| r.is_error()),
+            _                              => false,
+        }
+    }
+
+    // diagnostics go to stderr, keeping stdout for what programs and `build` print
     pub fn display(&self, lines: Option<&Vec<&str>>) {
         match *self {
             Response::Group(ref responses) => for response in responses {
@@ -51,28 +90,31 @@ impl Response {
 
                 let message = format!("{}{}{}\n", message_t.color(color).bold(), ": ".white().bold(), message.bold());
 
-                if lines.is_some() {
-                    if let &Some(ref pos) = location {
-                        let line = lines.unwrap().get(pos.0.line);
-
-                        if let Some(line) = line {
-                            let prefix      = format!("{:5} |", pos.0.line + 1).blue().bold();
-                            let source_line = format!("{} {}\n", prefix, line);
-                            let indicator   = format!(
-                                "{:offset$}{:^<count$}", " ", " ".color(color).bold(),
-                                offset = prefix.len() + pos.0.col - 2,
-                                count  = pos.1 + 1,
-                            );
-                            
-                            println!("{}{}{}{}\n", message, "      |\n".blue().bold(), source_line, indicator)
-                        }
-                    } else {
-                        println!("{}", message);
+                if let (Some(lines), Some(pos)) = (lines, location) {
+                    if let Some(line) = lines.get(pos.0.line.saturating_sub(1)) {
+                        let prefix      = format!("{:5} |", pos.0.line).blue().bold();
+                        let source_line = format!("{} {}\n", prefix, line);
+                        let indicator   = format!(
+                            "{:offset$}{:^<count$}", " ", " ".color(color).bold(),
+                            offset = prefix.len() + pos.0.col - 1,
+                            count  = pos.1 + 1,
+                        );
+
+                        eprintln!("{}{}{}{}\n", message, "      |\n".blue().bold(), source_line, indicator);
+                        return
                     }
-                } else {
-                    println!("{}", message)
                 }
+
+                eprintln!("{}", message)
             }
         }
     }
 }
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("1 {}", word)
+    } else {
+        format!("{} {}s", n, word)
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+
+use super::*;
+
+pub type BuiltinFn = fn(&[Value]) -> Result<Value, Response>;
+
+pub struct Builtin {
+    pub name:     &'static str,
+    pub arity:    usize,
+    pub function: BuiltinFn,
+}
+
+// externs the interpreter can provide itself
+pub fn builtin(name: &str) -> Option<Builtin> {
+    let (name, arity, function): (&'static str, usize, BuiltinFn) = match name {
+        "print"  => ("print", 1, print),
+        "write"  => ("write", 1, write),
+        "len"    => ("len", 1, len),
+        "floor"  => ("floor", 1, floor),
+        "sqrt"   => ("sqrt", 1, sqrt),
+        "string" => ("string", 1, string),
+        _        => return None,
+    };
+
+    Some(Builtin {
+        name,
+        arity,
+        function,
+    })
+}
+
+fn print(args: &[Value]) -> Result<Value, Response> {
+    println!("{}", args[0]);
+    Ok(Value::Nil)
+}
+
+fn write(args: &[Value]) -> Result<Value, Response> {
+    print!("{}", args[0]);
+    io::stdout().flush().map_err(|e| Response::error(None, format!("couldn't write: {}", e)))?;
+
+    Ok(Value::Nil)
+}
+
+fn len(args: &[Value]) -> Result<Value, Response> {
+    match args[0] {
+        Value::Str(ref s)         => Ok(Value::Number(s.chars().count() as f64)),
+        Value::Array(ref content) => Ok(Value::Number(content.borrow().len() as f64)),
+        ref c                     => Err(Response::error(None, format!("can't get length of {}", c.type_name()))),
+    }
+}
+
+fn number(name: &str, args: &[Value], f: fn(f64) -> f64) -> Result<Value, Response> {
+    match args[0] {
+        Value::Number(n) => Ok(Value::Number(f(n))),
+        ref c            => Err(Response::error(None, format!("{} expected number, found: {}", name, c.type_name()))),
+    }
+}
+
+fn floor(args: &[Value]) -> Result<Value, Response> {
+    number("floor", args, f64::floor)
+}
+
+fn sqrt(args: &[Value]) -> Result<Value, Response> {
+    number("sqrt", args, f64::sqrt)
+}
+
+fn string(args: &[Value]) -> Result<Value, Response> {
+    Ok(Value::str(&args[0].to_string()))
+}
@@ -0,0 +1,67 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use super::*;
+
+// runtime values laid out like a `SymTab`, so the same (index, env_index) pairs address them
+pub struct Environment {
+    pub parent: Option<Rc<Environment>>,
+    pub values: RefCell<Vec<Value>>,
+}
+
+impl Environment {
+    pub fn new(parent: Rc<Environment>, values: Vec<Value>) -> Environment {
+        Environment {
+            parent: Some(parent),
+            values: RefCell::new(values),
+        }
+    }
+
+    pub fn new_global() -> Environment {
+        Environment {
+            parent: None,
+            values: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn define(&self, index: usize, value: Value) {
+        let mut values = self.values.borrow_mut();
+
+        if index >= values.len() {
+            values.resize(index + 1, Value::Nil)
+        }
+
+        values[index] = value
+    }
+
+    pub fn set(&self, index: usize, env_index: usize, value: Value) -> Result<(), Response> {
+        if env_index == 0 {
+            match self.values.borrow_mut().get_mut(index) {
+                Some(v) => {
+                    *v = value;
+                    Ok(())
+                },
+                None => Err(Response::error(None, format!("invalid value index: {}", index))),
+            }
+        } else {
+            match self.parent {
+                Some(ref p) => p.set(index, env_index - 1, value),
+                None        => Err(Response::error(None, format!("invalid value env index: {}", env_index))),
+            }
+        }
+    }
+
+    pub fn get(&self, index: usize, env_index: usize) -> Result<Value, Response> {
+        if env_index == 0 {
+            match self.values.borrow().get(index) {
+                Some(v) => Ok(v.clone()),
+                None    => Err(Response::error(None, format!("invalid value index: {}", index))),
+            }
+        } else {
+            match self.parent {
+                Some(ref p) => p.get(index, env_index - 1),
+                None        => Err(Response::error(None, format!("invalid value env index: {}", env_index))),
+            }
+        }
+    }
+}
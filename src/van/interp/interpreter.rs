@@ -0,0 +1,607 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+use std::mem;
+
+use super::*;
+
+// as many calls deep as the vm goes
+const MAX_DEPTH: usize = 4096;
+
+// anything that unwinds out of an expression
+pub enum Signal {
+    Return(Value),
+    Break,
+    Continue,
+    Error(Response),
+}
+
+impl From<Response> for Signal {
+    fn from(response: Response) -> Signal {
+        Signal::Error(response)
+    }
+}
+
+fn error<T>(location: Option<ErrorLocation>, message: String) -> Result<T, Signal> {
+    Err(Signal::Error(Response::error(location, message)))
+}
+
+// catches a `return` unwinding out of a function or block expression
+fn catch_return(result: Result<Value, Signal>) -> Result<Value, Signal> {
+    match result {
+        Err(Signal::Return(value)) => Ok(value),
+        result                     => result,
+    }
+}
+
+pub struct Interpreter {
+    symtab: Rc<SymTab>,
+    env:    Rc<Environment>,
+    depth:  usize, // how many calls deep it is
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            symtab: Rc::new(SymTab::new_global()),
+            env:    Rc::new(Environment::new_global()),
+            depth:  0,
+        }
+    }
+
+    pub fn run(&mut self, ast: &[Statement]) -> Result<Value, Response> {
+        for statement in ast {
+            match self.statement(statement) {
+                Ok(())                     => (),
+                Err(Signal::Return(value)) => return Ok(value),
+                Err(Signal::Error(e))      => return Err(e),
+
+                // the visitor only lets these be inside of loops
+                Err(Signal::Break) | Err(Signal::Continue) => (),
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn scoped<T>(&mut self, symtab: Rc<SymTab>, env: Rc<Environment>, f: &mut dyn FnMut(&mut Self) -> Result<T, Signal>) -> Result<T, Signal> {
+        let symtab = mem::replace(&mut self.symtab, symtab);
+        let env    = mem::replace(&mut self.env, env);
+
+        let result = f(self);
+
+        self.symtab = symtab;
+        self.env    = env;
+
+        result
+    }
+
+    fn local<T>(&mut self, f: &mut dyn FnMut(&mut Self) -> Result<T, Signal>) -> Result<T, Signal> {
+        let symtab = Rc::new(SymTab::new(self.symtab.clone(), &[]));
+        let env    = Rc::new(Environment::new(self.env.clone(), Vec::new()));
+
+        self.scoped(symtab, env, f)
+    }
+
+    fn define(&mut self, name: &str, value: Value) {
+        let index = self.symtab.add_name(name);
+        self.env.define(index, value)
+    }
+
+    fn lookup(&self, name: &str, position: TokenPosition) -> Result<Value, Signal> {
+        match self.symtab.get_name(name) {
+            Some((index, env_index)) => Ok(self.env.get(index, env_index)?),
+            None                     => error(Some(ErrorLocation::new(position, name.len())), format!("undefined: {}", name)),
+        }
+    }
+
+    fn closure(&self, closure: Closure) -> Value {
+        Value::Closure(Rc::new(closure))
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), Signal> {
+        match *s {
+            Statement::Expression(ref e) => {
+                self.expression(e)?;
+                Ok(())
+            },
+
+            Statement::Definition(Definition {ref name, ref right, ..}) => {
+                let value = match *right {
+                    Some(ref right) => self.expression(right)?,
+                    None            => Value::Nil,
+                };
+
+                self.define(name, value);
+
+                Ok(())
+            },
+
+            Statement::Destructure(Destructure {ref pattern, ref right, position, ..}) => {
+                let value     = self.expression(right)?;
+                let mut bound = HashMap::new();
+
+                if !destructure(pattern, &value, &mut bound) {
+                    return error(Some(ErrorLocation::new(position, 1)), format!("can't destructure: {}", value))
+                }
+
+                for name in pattern.bindings() {
+                    self.define(&name, bound[&name].clone())
+                }
+
+                Ok(())
+            },
+
+            Statement::Assignment(Assignment {ref left, ref right, ..}) => {
+                let value = self.expression(right)?;
+                self.assign(left, value)
+            },
+
+            Statement::Fun(ref fun) => {
+                let fun = Rc::new(fun.clone());
+
+                self.function(&fun.name, &mut |symtab, env| Closure::Fun {
+                    fun: fun.clone(),
+                    symtab,
+                    env,
+                })
+            },
+
+            Statement::FunctionMatch(ref function) => {
+                let function = Rc::new(function.clone());
+
+                self.function(&function.name, &mut |symtab, env| Closure::Match {
+                    function: function.clone(),
+                    symtab,
+                    env,
+                })
+            },
+
+            Statement::Extern(ref statement) => match **statement {
+                Statement::Definition(Definition {ref name, ref position, ..}) => match builtin(name) {
+                    Some(builtin) => {
+                        let value = self.closure(Closure::Builtin(builtin));
+                        self.define(name, value);
+
+                        Ok(())
+                    },
+
+                    None => error(Some(ErrorLocation::new(*position, name.len())), format!("no builtin extern named: {}", name)),
+                },
+
+                Statement::Struct(_) => Ok(()),
+                ref statement        => self.statement(statement),
+            },
+
+            Statement::If(ref base) => {
+                self.if_expression(base, false)?;
+                Ok(())
+            },
+
+            Statement::Unless(ref unless) => {
+                self.if_expression(&unless.base, true)?;
+                Ok(())
+            },
+
+            Statement::MatchPattern(ref m) => {
+                self.match_pattern(m)?;
+                Ok(())
+            },
+
+            Statement::While(While {ref condition, ref body}) => {
+                while self.condition(condition)? {
+                    match self.block(body) {
+                        Err(Signal::Break)            => break,
+                        Ok(_) | Err(Signal::Continue) => (),
+                        Err(signal)                   => return Err(signal),
+                    }
+                }
+
+                Ok(())
+            },
+
+            Statement::For(For {ref index, ref name, ref iterable, ref body, position}) => {
+                let iterable = self.expression(iterable)?;
+
+                for i in 0 .. {
+                    let value = match element(&iterable, i) {
+                        Ok(Some(value)) => value,
+                        Ok(None)        => break,
+                        Err(message)    => return error(Some(ErrorLocation::new(position, 3)), message),
+                    };
+
+                    let looped = self.local(&mut |interpreter| {
+                        interpreter.define(name, value.clone());
+
+                        if let Some(ref index) = *index {
+                            interpreter.define(index, Value::Number(i as f64))
+                        }
+
+                        interpreter.block(body)
+                    });
+
+                    match looped {
+                        Err(Signal::Break)            => break,
+                        Ok(_) | Err(Signal::Continue) => (),
+                        Err(signal)                   => return Err(signal),
+                    }
+                }
+
+                Ok(())
+            },
+
+            Statement::Break(_)    => Err(Signal::Break),
+            Statement::Continue(_) => Err(Signal::Continue),
+
+            Statement::Return(ref e) => {
+                let value = match *e {
+                    Some(ref e) => self.expression(e)?,
+                    None        => Value::Nil,
+                };
+
+                Err(Signal::Return(value))
+            },
+
+            // a struct's name holds its methods, which instances start out with
+            Statement::Struct(Struct {ref name, ..}) => {
+                self.define(name, Value::structure(Fields::default()));
+                Ok(())
+            },
+
+            Statement::Data(ref data) => {
+                for variant in &data.variants {
+                    self.define(&variant.name, Value::variant(variant))
+                }
+
+                Ok(())
+            },
+
+            Statement::Implementation(ref implementation) => {
+                for method in implementation.methods() {
+                    self.statement(&method)?
+                }
+
+                Ok(())
+            },
+
+            // only the checker cares about these
+            Statement::Interface(_) |
+            Statement::Import(_)    => Ok(()),
+        }
+    }
+
+    // the names a closure made here sees, which a later definition of the same name doesn't change
+    fn captured(&self) -> Rc<SymTab> {
+        Rc::new(self.symtab.snapshot())
+    }
+
+    // defines a named function, making the name visible inside it for recursion
+    fn function(&mut self, name: &Option<Expression>, make: &mut dyn FnMut(Rc<SymTab>, Rc<Environment>) -> Closure) -> Result<(), Signal> {
+        match *name {
+            Some(Expression::Identifier(ref name, _)) => {
+                let index = self.symtab.add_name(name);
+
+                let value = self.closure(make(self.captured(), self.env.clone()));
+                self.env.define(index, value);
+
+                Ok(())
+            },
+
+            Some(ref name) => {
+                let value = self.closure(make(self.captured(), self.env.clone()));
+                self.assign(name, value)
+            },
+
+            None => Ok(()),
+        }
+    }
+
+    fn assign(&mut self, left: &Expression, value: Value) -> Result<(), Signal> {
+        match *left {
+            Expression::Identifier(ref name, ref position) => match self.symtab.get_name(name) {
+                Some((index, env_index)) => Ok(self.env.set(index, env_index, value)?),
+                None                     => error(Some(ErrorLocation::new(*position, name.len())), format!("undefined: {}", name)),
+            },
+
+            Expression::Index(Index {ref id, ref index, field, ref position}) => {
+                let id = self.expression(id)?;
+
+                let result = if field {
+                    set_field(&id, &Rc::new(field_name(index)?.to_owned()), value)
+                } else {
+                    let index = self.expression(index)?;
+                    set_index(&id, &index, value)
+                };
+
+                result.or_else(|message| error(Some(ErrorLocation::new(*position, 1)), message))
+            },
+
+            Expression::Tuple(ref content, position) => match value {
+                Value::Tuple(ref values) if values.len() == content.len() => {
+                    for (left, value) in content.iter().zip(values.iter()) {
+                        self.assign(left, value.clone())?
+                    }
+
+                    Ok(())
+                },
+
+                value => error(Some(ErrorLocation::new(position, 1)), format!("can't destructure: {}", value)),
+            },
+
+            ref c => error(None, format!("can't assign to: {:?}", c)),
+        }
+    }
+
+    fn block(&mut self, body: &[Statement]) -> Result<Value, Signal> {
+        self.local(&mut |interpreter| {
+            for (i, statement) in body.iter().enumerate() {
+                if i + 1 == body.len() {
+                    if let Statement::Expression(ref e) = *statement {
+                        return interpreter.expression(e)
+                    }
+                }
+
+                interpreter.statement(statement)?
+            }
+
+            Ok(Value::Nil)
+        })
+    }
+
+    fn condition(&mut self, e: &Expression) -> Result<bool, Signal> {
+        match self.expression(e)? {
+            Value::Bool(b) => Ok(b),
+            c              => error(None, format!("expected boolean condition, found: {}", c.type_name())),
+        }
+    }
+
+    fn if_expression(&mut self, base: &If, negate: bool) -> Result<Value, Signal> {
+        if self.condition(&base.condition)? != negate {
+            return self.block(&base.body)
+        }
+
+        if let Some(ref elses) = base.elses {
+            for (condition, body) in elses {
+                let taken = match *condition {
+                    Some(ref condition) => self.condition(condition)?,
+                    None                => true,
+                };
+
+                if taken {
+                    return self.block(body)
+                }
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn match_pattern(&mut self, m: &MatchPattern) -> Result<Value, Signal> {
+        let subject = self.expression(&m.matching)?;
+        self.arms(&m.arms, &[subject])
+    }
+
+    fn arms(&mut self, arms: &[MatchArm], subjects: &[Value]) -> Result<Value, Signal> {
+        for arm in arms {
+            let mut bound = HashMap::new();
+
+            if !arm.params.iter().zip(subjects).all(|(param, subject)| destructure(param, subject, &mut bound)) {
+                continue
+            }
+
+            // `None` when the guard doesn't hold, moving on to the next arm
+            let taken = self.local(&mut |interpreter| {
+                for name in arm.bindings() {
+                    interpreter.define(&name, bound[&name].clone())
+                }
+
+                if let Some(ref guard) = arm.guard {
+                    if !interpreter.condition(guard)? {
+                        return Ok(None)
+                    }
+                }
+
+                interpreter.expression(&arm.body).map(Some)
+            })?;
+
+            if let Some(value) = taken {
+                return Ok(value)
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn call(&mut self, callee: Value, args: Vec<Value>, position: TokenPosition) -> Result<Value, Signal> {
+        let closure = match callee {
+            Value::Closure(closure) => closure,
+            c                       => return error(Some(ErrorLocation::new(position, 1)), format!("can't call non-fun: {}", c.type_name())),
+        };
+
+        let arity = match *closure {
+            Closure::Fun {ref fun, ..}        => fun.params.len(),
+            Closure::Match {ref function, ..} => function.arity(),
+            Closure::Builtin(ref b)           => b.arity,
+            Closure::Constructor {arity, ..}  => arity,
+            Closure::Compiled {..}            => return error(None, "can't call a compiled fun from the interpreter".to_owned()),
+        };
+
+        if arity != args.len() {
+            return error(Some(ErrorLocation::new(position, 1)), format!("function given {} arguments, expected: {}", args.len(), arity))
+        }
+
+        if self.depth >= MAX_DEPTH {
+            return error(Some(ErrorLocation::new(position, 1)), "stack overflow".to_owned())
+        }
+
+        self.depth += 1;
+
+        let result = match *closure {
+            Closure::Fun {ref fun, ref symtab, ref env} => {
+                let names = fun.params.iter().map(|p| p.name.clone()).collect::<Vec<String>>();
+
+                let symtab = Rc::new(SymTab::new(symtab.clone(), &names));
+                let env    = Rc::new(Environment::new(env.clone(), args));
+
+                catch_return(self.scoped(symtab, env, &mut |interpreter| interpreter.block(&fun.body)))
+            },
+
+            Closure::Match {ref function, ref symtab, ref env} => {
+                catch_return(self.scoped(symtab.clone(), env.clone(), &mut |interpreter| interpreter.arms(&function.arms, &args)))
+            },
+
+            Closure::Builtin(ref builtin)   => (builtin.function)(&args).map_err(Signal::from),
+            Closure::Constructor {ref tag, ..} => Ok(Value::Variant(tag.clone(), Rc::new(args))),
+            Closure::Compiled {..}          => unreachable!(),
+        };
+
+        self.depth -= 1;
+
+        result
+    }
+
+    fn expression(&mut self, e: &Expression) -> Result<Value, Signal> {
+        match *e {
+            Expression::Number(n)      => Ok(Value::Number(n)),
+            Expression::Bool(b)        => Ok(Value::Bool(b)),
+            Expression::Str(ref s)     => Ok(Value::str(s)),
+            Expression::Char(c)        => Ok(Value::Char(c)),
+            Expression::Nil            => Ok(Value::Nil),
+
+            Expression::Identifier(ref name, position) => self.lookup(name, position),
+
+            Expression::BinaryOp(ref op) => self.binary_op(op),
+
+            Expression::UnaryOp(ref op) => {
+                let value = self.expression(&op.expr)?;
+                unary_op(&op.op, &value).or_else(|message| error(Some(ErrorLocation::new(op.position, 1)), message))
+            },
+
+            Expression::Call(Call {ref callee, ref args, position}) => {
+                let callee = self.expression(callee)?;
+
+                let mut values = Vec::new();
+
+                for arg in args {
+                    values.push(self.expression(arg)?)
+                }
+
+                self.call(callee, values, position)
+            },
+
+            Expression::Index(Index {ref id, ref index, field, ref position}) => {
+                let id = self.expression(id)?;
+
+                let result = if field {
+                    get_field(&id, field_name(index)?)
+                } else {
+                    let index = self.expression(index)?;
+                    get_index(&id, &index)
+                };
+
+                result.or_else(|message| error(Some(ErrorLocation::new(*position, 1)), message))
+            },
+
+            Expression::Array(ref content, _) => {
+                let mut values = Vec::new();
+
+                for expression in content {
+                    values.push(self.expression(expression)?)
+                }
+
+                Ok(Value::array(values))
+            },
+
+            Expression::Tuple(ref content, _) => {
+                let mut values = Vec::new();
+
+                for expression in content {
+                    values.push(self.expression(expression)?)
+                }
+
+                Ok(Value::Tuple(Rc::new(values)))
+            },
+
+            Expression::If(ref base)        => self.if_expression(base, false),
+            Expression::Unless(ref a)       => self.if_expression(&a.base, true),
+            Expression::MatchPattern(ref m) => self.match_pattern(m),
+            Expression::Block(ref body)     => catch_return(self.block(body)),
+
+            Expression::Initialization(ref a) => {
+                let mut fields = Fields::default();
+
+                for value in &a.values {
+                    let name = field_name(&value.left)?;
+                    fields.insert(&Rc::new(name.to_owned()), self.expression(&value.right)?);
+                }
+
+                let instance = Value::structure(fields);
+
+                if let Expression::Identifier(ref name, position) = a.id {
+                    if self.symtab.get_name(name).is_some() {
+                        inherit(&instance, &self.lookup(name, position)?)
+                    }
+                }
+
+                Ok(instance)
+            },
+
+            Expression::Fun(ref fun) => Ok(self.closure(Closure::Fun {
+                fun:    fun.clone(),
+                symtab: self.captured(),
+                env:    self.env.clone(),
+            })),
+
+            Expression::FunctionMatch(ref function) => Ok(self.closure(Closure::Match {
+                function: function.clone(),
+                symtab:   self.captured(),
+                env:      self.env.clone(),
+            })),
+
+            Expression::Struct(_) |
+            Expression::EOF       => Ok(Value::Nil),
+        }
+    }
+
+    fn binary_op(&mut self, op: &BinaryOp) -> Result<Value, Signal> {
+        use self::Operand::*;
+
+        match op.op {
+            PipeRight | PipeLeft => {
+                let (function, argument) = if op.op == PipeRight { (&op.right, &op.left) } else { (&op.left, &op.right) };
+
+                let argument = self.expression(argument)?;
+                let function = self.expression(function)?;
+
+                return self.call(function, vec![argument], op.position)
+            },
+
+            // the right side only runs when the left doesn't settle it
+            And | Or => {
+                if self.condition(&op.left)? == (op.op == Or) {
+                    return Ok(Value::Bool(op.op == Or))
+                }
+
+                return self.condition(&op.right).map(Value::Bool)
+            },
+
+            Default => match self.expression(&op.left)? {
+                Value::Nil => return self.expression(&op.right),
+                value      => return Ok(value),
+            },
+
+            _ => (),
+        }
+
+        let left  = self.expression(&op.left)?;
+        let right = self.expression(&op.right)?;
+
+        binary_op(&op.op, &left, &right).or_else(|message| error(Some(ErrorLocation::new(op.position, 1)), message))
+    }
+}
+
+fn field_name(e: &Expression) -> Result<&str, Signal> {
+    match *e {
+        Expression::Identifier(ref name, _) => Ok(name),
+        ref c                               => error(None, format!("invalid field: {:?}", c)),
+    }
+}
@@ -0,0 +1,13 @@
+pub mod value;
+pub mod environment;
+pub mod builtins;
+pub mod operations;
+pub mod interpreter;
+
+pub use super::*;
+
+pub use self::value::*;
+pub use self::environment::*;
+pub use self::builtins::*;
+pub use self::operations::*;
+pub use self::interpreter::*;
@@ -0,0 +1,223 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+
+use super::*;
+
+// runtime semantics shared by the interpreter and the vm, the callers decide where errors point
+
+pub fn binary_op(op: &Operand, left: &Value, right: &Value) -> Result<Value, String> {
+    use self::Operand::*;
+
+    let value = match (op, left, right) {
+        (&Add, &Value::Number(a), &Value::Number(b)) => Value::Number(a + b),
+        (&Sub, &Value::Number(a), &Value::Number(b)) => Value::Number(a - b),
+        (&Mul, &Value::Number(a), &Value::Number(b)) => Value::Number(a * b),
+        (&Div, &Value::Number(a), &Value::Number(b)) => Value::Number(a / b),
+        (&Mod, &Value::Number(a), &Value::Number(b)) => Value::Number(a - (a / b).floor() * b),
+        (&Pow, &Value::Number(a), &Value::Number(b)) => Value::Number(a.powf(b)),
+
+        (&Range, &Value::Number(a), &Value::Number(b)) => Value::Range(a, b),
+
+        (&Equal, a, b)  => Value::Bool(a.equals(b)),
+        (&NEqual, a, b) => Value::Bool(!a.equals(b)),
+
+        (&Lt, &Value::Number(a), &Value::Number(b))      => Value::Bool(a < b),
+        (&Gt, &Value::Number(a), &Value::Number(b))      => Value::Bool(a > b),
+        (&LtEqual, &Value::Number(a), &Value::Number(b)) => Value::Bool(a <= b),
+        (&GtEqual, &Value::Number(a), &Value::Number(b)) => Value::Bool(a >= b),
+
+        (&Lt, Value::Str(a), Value::Str(b))      => Value::Bool(a < b),
+        (&Gt, Value::Str(a), Value::Str(b))      => Value::Bool(a > b),
+        (&LtEqual, Value::Str(a), Value::Str(b)) => Value::Bool(a <= b),
+        (&GtEqual, Value::Str(a), Value::Str(b)) => Value::Bool(a >= b),
+
+        (&Concat, a, b) => Value::str(&format!("{}{}", a, b)),
+
+        (&And, &Value::Bool(a), &Value::Bool(b)) => Value::Bool(a && b),
+        (&Xor, &Value::Bool(a), &Value::Bool(b)) => Value::Bool(a != b),
+        (&Or, &Value::Bool(a), &Value::Bool(b))  => Value::Bool(a || b),
+
+        (o, a, b) => return Err(format!("can't apply {:?} to {} and {}", o, a.type_name(), b.type_name())),
+    };
+
+    Ok(value)
+}
+
+// the element of a loop's `iterable` at `i`, or `None` once it's gone through all of them
+pub fn element(iterable: &Value, i: usize) -> Result<Option<Value>, String> {
+    match *iterable {
+        Value::Array(ref content) => Ok(content.borrow().get(i).cloned()),
+
+        Value::Range(from, to) => {
+            let n = from + i as f64;
+            Ok(if n < to { Some(Value::Number(n)) } else { None })
+        },
+
+        ref c => Err(format!("can't iterate over {}", c.type_name())),
+    }
+}
+
+pub fn unary_op(op: &UnaryOperand, value: &Value) -> Result<Value, String> {
+    match (op, value) {
+        (&UnaryOperand::Neg, &Value::Number(a)) => Ok(Value::Number(-a)),
+        (&UnaryOperand::Not, &Value::Bool(a))   => Ok(Value::Bool(!a)),
+
+        (o, a) => Err(format!("can't apply {:?} to {}", o, a.type_name())),
+    }
+}
+
+// whether `value` fits `pattern`, adding what it binds to `bound`
+pub fn destructure(pattern: &Pattern, value: &Value, bound: &mut HashMap<String, Value>) -> bool {
+    match *pattern {
+        Pattern::Wildcard(_) => true,
+
+        Pattern::Binding(ref name, _) => {
+            bound.insert(name.clone(), value.clone());
+            true
+        },
+
+        Pattern::Literal(ref literal, _) => value.equals(&match *literal {
+            Expression::Number(n)  => Value::Number(n),
+            Expression::Str(ref s) => Value::str(s),
+            Expression::Char(c)    => Value::Char(c),
+            Expression::Bool(b)    => Value::Bool(b),
+            Expression::Nil        => Value::Nil,
+            _                      => return false,
+        }),
+
+        Pattern::Array(ref patterns, ref rest, _) => match *value {
+            Value::Array(ref content) => {
+                let content = content.borrow();
+
+                let fits = match *rest {
+                    Some(_) => content.len() >= patterns.len(),
+                    None    => content.len() == patterns.len(),
+                };
+
+                if !fits || !patterns.iter().zip(content.iter()).all(|(pattern, value)| destructure(pattern, value, bound)) {
+                    return false
+                }
+
+                if let Some(Some(ref rest)) = *rest {
+                    bound.insert(rest.clone(), Value::array(content[patterns.len() ..].to_vec()));
+                }
+
+                true
+            },
+
+            _ => false,
+        },
+
+        Pattern::Struct(_, ref fields, _) => match *value {
+            Value::Struct(ref content) => {
+                let content = content.borrow();
+
+                fields.iter().all(|(name, pattern)| content.get(name).is_some_and(|value| destructure(pattern, value, bound)))
+            },
+
+            _ => false,
+        },
+
+        Pattern::Variant(ref name, ref fields, _) => match *value {
+            Value::Variant(ref tag, ref payload) => {
+                **tag == *name && payload.len() == fields.len() && fields.iter().zip(payload.iter()).all(|(pattern, value)| destructure(pattern, value, bound))
+            },
+
+            _ => false,
+        },
+
+        Pattern::Tuple(ref patterns, _) => match *value {
+            Value::Tuple(ref content) => {
+                content.len() == patterns.len() && patterns.iter().zip(content.iter()).all(|(pattern, value)| destructure(pattern, value, bound))
+            },
+
+            _ => false,
+        },
+
+        Pattern::Or(ref alternatives) => alternatives.iter().any(|alternative| {
+            let mut alternative_bound = HashMap::new();
+
+            if destructure(alternative, value, &mut alternative_bound) {
+                bound.extend(alternative_bound);
+                true
+            } else {
+                false
+            }
+        }),
+    }
+}
+
+pub fn array_index(n: f64, len: usize) -> Option<usize> {
+    if n >= 0.0 && n.fract() == 0.0 && (n as usize) < len {
+        Some(n as usize)
+    } else {
+        None
+    }
+}
+
+pub fn get_index(id: &Value, index: &Value) -> Result<Value, String> {
+    let value = match (id, index) {
+        (Value::Array(content), &Value::Number(n)) => {
+            let content = content.borrow();
+            array_index(n, content.len()).map(|i| content[i].clone())
+        },
+
+        (Value::Str(s), &Value::Number(n)) => {
+            array_index(n, s.chars().count()).and_then(|i| s.chars().nth(i)).map(Value::Char)
+        },
+
+        (a, b) => return Err(format!("can't index {} with {}", a.type_name(), b.type_name())),
+    };
+
+    value.ok_or_else(|| format!("index out of bounds: {}", index))
+}
+
+pub fn set_index(id: &Value, index: &Value, value: Value) -> Result<(), String> {
+    match (id, index) {
+        (Value::Array(content), &Value::Number(n)) => {
+            let mut content = content.borrow_mut();
+
+            match array_index(n, content.len()) {
+                Some(i) => {
+                    content[i] = value;
+                    Ok(())
+                },
+
+                None => Err(format!("index out of bounds: {}", format_number(n))),
+            }
+        },
+
+        (a, b) => Err(format!("can't index {} with {}", a.type_name(), b.type_name())),
+    }
+}
+
+pub fn get_field(id: &Value, name: &str) -> Result<Value, String> {
+    match *id {
+        Value::Struct(ref fields) => Ok(fields.borrow().get(name).cloned().unwrap_or(Value::Nil)),
+        ref c                     => Err(format!("can't get field {} of {}", name, c.type_name())),
+    }
+}
+
+pub fn set_field(id: &Value, name: &Rc<String>, value: Value) -> Result<(), String> {
+    match *id {
+        Value::Struct(ref fields) => {
+            fields.borrow_mut().insert(name, value);
+            Ok(())
+        },
+
+        ref c => Err(format!("can't set field {} of {}", name, c.type_name())),
+    }
+}
+
+// gives `instance` the methods of `structure` it doesn't already have a field for
+pub fn inherit(instance: &Value, structure: &Value) {
+    if let (Value::Struct(fields), Value::Struct(methods)) = (instance, structure) {
+        let mut fields = fields.borrow_mut();
+
+        for (name, method) in methods.borrow().iter() {
+            if fields.index(name).is_none() {
+                fields.insert(name, method.clone())
+            }
+        }
+    }
+}
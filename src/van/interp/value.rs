@@ -0,0 +1,239 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+
+use super::*;
+
+#[derive(Clone)]
+pub enum Value {
+    Number(f64),
+    Str(Rc<String>),
+    Char(char),
+    Bool(bool),
+    Nil,
+    Array(Rc<RefCell<Vec<Value>>>),
+    Struct(Rc<RefCell<Fields>>),
+    Closure(Rc<Closure>),
+    Variant(Rc<String>, Rc<Vec<Value>>),
+    Tuple(Rc<Vec<Value>>),
+    Range(f64, f64),
+}
+
+// the names of a struct's fields, in the order they were given
+pub type FieldNames = Rc<Vec<Rc<String>>>;
+
+// a struct's fields, few enough to be looked through in order. the names are shared with every
+// struct made by the same code, until one gets a field of its own, and the vm uses the same names
+// it accesses fields with, so most lookups only compare pointers
+#[derive(Default)]
+pub struct Fields {
+    pub names:  FieldNames,
+    pub values: Vec<Value>,
+}
+
+impl Fields {
+    pub fn new(names: FieldNames, values: Vec<Value>) -> Fields {
+        Fields {names, values}
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|field| ptr::eq(field.as_str(), name) || field.as_str() == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.index(name).map(|i| &self.values[i])
+    }
+
+    pub fn insert(&mut self, name: &Rc<String>, value: Value) {
+        match self.index(name) {
+            Some(i) => self.values[i] = value,
+
+            None => {
+                Rc::make_mut(&mut self.names).push(name.clone());
+                self.values.push(value)
+            },
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Rc<String>, &Value)> {
+        self.names.iter().zip(self.values.iter())
+    }
+}
+
+pub enum Closure {
+    Fun {
+        fun:    Rc<Fun>,
+        symtab: Rc<SymTab>,
+        env:    Rc<Environment>,
+    },
+
+    Match {
+        function: Rc<FunctionMatch>,
+        symtab:   Rc<SymTab>,
+        env:      Rc<Environment>,
+    },
+
+    Compiled {
+        prototype: Rc<Prototype>,
+        env:       Rc<Environment>,
+    },
+
+    Constructor {
+        tag:   Rc<String>,
+        arity: usize,
+    },
+
+    Builtin(Builtin),
+}
+
+impl Value {
+    pub fn str(s: &str) -> Value {
+        Value::Str(Rc::new(s.to_owned()))
+    }
+
+    pub fn array(content: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(content)))
+    }
+
+    pub fn structure(fields: Fields) -> Value {
+        Value::Struct(Rc::new(RefCell::new(fields)))
+    }
+
+    // a variant carrying nothing is the value itself, any other is made by calling it
+    pub fn variant(variant: &Variant) -> Value {
+        let tag = Rc::new(variant.name.clone());
+
+        if variant.fields.is_empty() {
+            Value::Variant(tag, Rc::new(Vec::new()))
+        } else {
+            Value::Closure(Rc::new(Closure::Constructor { tag, arity: variant.fields.len() }))
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Number(_)  => "number",
+            Value::Str(_)     => "string",
+            Value::Char(_)    => "char",
+            Value::Bool(_)    => "boolean",
+            Value::Nil        => "nil",
+            Value::Array(_)   => "array",
+            Value::Struct(_)  => "struct",
+            Value::Closure(_) => "fun",
+            Value::Variant(..) => "variant",
+            Value::Tuple(_)    => "tuple",
+            Value::Range(..)   => "range",
+        }
+    }
+
+    // arrays, structs, funs, tuples and the payload of variants are compared by reference, like the tables they become in lua
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b))   => a == b,
+            (Value::Str(a), Value::Str(b))         => a == b,
+            (Value::Char(a), Value::Char(b))       => a == b,
+            (Value::Bool(a), Value::Bool(b))       => a == b,
+            (Value::Nil, Value::Nil)               => true,
+            (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+            (Value::Array(a), Value::Array(b))     => Rc::ptr_eq(a, b),
+            (Value::Struct(a), Value::Struct(b))   => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Tuple(a), Value::Tuple(b))     => Rc::ptr_eq(a, b),
+
+            (Value::Variant(a, a_payload), Value::Variant(b, b_payload)) => a == b && Rc::ptr_eq(a_payload, b_payload),
+
+            _ => false,
+        }
+    }
+}
+
+pub fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "nan".to_owned()
+    } else if n.is_infinite() {
+        if n > 0.0 { "inf".to_owned() } else { "-inf".to_owned() }
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number(n)  => write!(f, "{}", format_number(n)),
+            Value::Str(ref s) => write!(f, "{}", s),
+            Value::Char(c)    => write!(f, "{}", c),
+            Value::Bool(b)    => write!(f, "{}", b),
+            Value::Nil        => write!(f, "nil"),
+            Value::Range(a, b) => write!(f, "{}..{}", format_number(a), format_number(b)),
+
+            Value::Array(ref content) => {
+                write!(f, "[")?;
+
+                for (i, value) in content.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+
+                    write!(f, "{}", value)?
+                }
+
+                write!(f, "]")
+            },
+
+            Value::Struct(ref fields) => {
+                let fields = fields.borrow();
+
+                let mut sorted = fields.iter().collect::<Vec<(&Rc<String>, &Value)>>();
+                sorted.sort_by(|a, b| a.0.cmp(b.0));
+
+                write!(f, "{{")?;
+
+                for (i, (name, value)) in sorted.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?
+                    }
+
+                    write!(f, " {} = {}", name, value)?
+                }
+
+                write!(f, " }}")
+            },
+
+            Value::Variant(ref tag, ref payload) => {
+                write!(f, "{}", tag)?;
+
+                for value in payload.iter() {
+                    match *value {
+                        Value::Variant(_, ref inner) if !inner.is_empty() => write!(f, " ({})", value)?,
+                        _                                                 => write!(f, " {}", value)?,
+                    }
+                }
+
+                Ok(())
+            },
+
+            Value::Tuple(ref content) => {
+                write!(f, "(")?;
+
+                for (i, value) in content.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+
+                    write!(f, "{}", value)?
+                }
+
+                write!(f, ")")
+            },
+
+            Value::Closure(ref closure) => match **closure {
+                Closure::Builtin(ref builtin) => write!(f, "<builtin {}>", builtin.name),
+                _                             => write!(f, "<fun>"),
+            },
+        }
+    }
+}
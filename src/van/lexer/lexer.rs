@@ -1,36 +1,43 @@
 use std::rc::Rc;
-use super::token::{Token, TokenType};
+use super::token::{Token, TokenType, TokenPosition};
 use super::tokenizer::Tokenizer;
 use super::matcher::*;
+use super::super::{Response, ErrorLocation};
 
 pub fn make_lexer(data: Vec<char>) -> Lexer {
     let tokenizer = Tokenizer::new(data);
     let mut lexer = Lexer::new(tokenizer);
 
-    lexer.matchers_mut().push(Rc::new(IntLiteralMatcher));
+    lexer.matchers_mut().push(Rc::new(CommentMatcher));
+    lexer.matchers_mut().push(Rc::new(NumberLiteralMatcher));
     lexer.matchers_mut().push(Rc::new(StringLiteralMatcher));
 
     let bool_matcher = ConstantStringMatcher::new(TokenType::Bool, &["true", "false"]);
     lexer.matchers_mut().push(Rc::new(bool_matcher));
 
     let key_matcher = KeyMatcher::new(TokenType::Keyword, &[
-        "...", "while", "import", "expose", "return", "mut", "function", "fun", "struct", "interface", "new", "implement", "as", "if", "unless", "elif", "else", "match", "->", "extern",
+        "...", "while", "for", "break", "continue", "import", "expose", "return", "mut", "function", "fun", "struct", "interface", "new", "implement", "as", "if", "unless", "elif", "else", "match", "->", "extern", "data", "in",
     ]);
     lexer.matchers_mut().push(Rc::new(key_matcher));
 
+    let word_operator_matcher = KeyMatcher::new(TokenType::Operator, &[
+        "and", "or", "not",
+    ]);
+    lexer.matchers_mut().push(Rc::new(word_operator_matcher));
+
     lexer.matchers_mut().push(Rc::new(IdentifierMatcher));
 
     let eol_matcher = ConstantCharMatcher::new(TokenType::EOL, &['\n']);
     lexer.matchers_mut().push(Rc::new(eol_matcher));
 
     let operator_matcher = ConstantStringMatcher::new(TokenType::Operator, &[
-        "++", "+", "-", "*", "/", "^^", "^", ">=", "<=", "==", "!=", "<|", "|>", "<", ">", "%",
+        "++", "+", "-", "*", "/", "^^", "^", ">=", "<=", "==", "!=", "<|", "|>", "<", ">", "%", "??", "..",
     ]);
 
     lexer.matchers_mut().push(Rc::new(operator_matcher));
 
     let symbol_matcher = ConstantCharMatcher::new(TokenType::Symbol, &[
-        '(', ')', '[', ']', '{', '}', ',', ':', ';', '!', '|', '=', '\\', '.'
+        '(', ')', '[', ']', '{', '}', ',', ':', ';', '!', '|', '=', '\\', '.', '?'
     ]);
 
     lexer.matchers_mut().push(Rc::new(symbol_matcher));
@@ -42,7 +49,8 @@ pub fn make_lexer(data: Vec<char>) -> Lexer {
 
 pub struct Lexer {
     tokenizer: Tokenizer,
-    matchers: Vec<Rc<Matcher>>,
+    matchers: Vec<Rc<dyn Matcher>>,
+    errors: Vec<Response>,
 }
 
 impl Lexer {
@@ -50,24 +58,43 @@ impl Lexer {
         Lexer {
             tokenizer,
             matchers: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn match_token(&mut self) -> Option<Token> {
+    pub fn match_token(&mut self) -> Result<Option<Token>, Response> {
         for matcher in &mut self.matchers {
-            match self.tokenizer.try_match_token(matcher.as_ref()) {
-                Some(t) => return Some(t),
+            match self.tokenizer.try_match_token(matcher.as_ref())? {
+                Some(t) => return Ok(Some(t)),
                 None => continue,
             }
         }
-        None
+        Ok(None)
+    }
+
+    // for lexing a piece of a larger text, such as the code inside a string
+    pub fn starting_at(mut self, position: TokenPosition) -> Lexer {
+        self.tokenizer.pos = position;
+        self
+    }
+
+    // every token, or every bad one that was skipped along the way
+    pub fn tokenize(mut self) -> Result<Vec<Token>, Response> {
+        let tokens = self.by_ref().collect();
+
+        if self.errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(Response::group(self.errors))
+        }
     }
 
-    pub fn matchers(&self) -> &Vec<Rc<Matcher>> {
+    #[allow(dead_code)]
+    pub fn matchers(&self) -> &Vec<Rc<dyn Matcher>> {
         &self.matchers
     }
 
-    pub fn matchers_mut(&mut self) -> &mut Vec<Rc<Matcher>> {
+    pub fn matchers_mut(&mut self) -> &mut Vec<Rc<dyn Matcher>> {
         &mut self.matchers
     }
 }
@@ -75,14 +102,24 @@ impl Lexer {
 impl Iterator for Lexer {
     type Item = Token;
 
+    // bad tokens are skipped, and kept as errors
     fn next(&mut self) -> Option<Token> {
-        let token = match self.match_token() {
-            Some(n) => n,
-            None    => return None,
-        };
-        match token.token_type {
-            TokenType::EOF => None,
-            _ => Some(token),
+        loop {
+            match self.match_token() {
+                Ok(Some(token)) => return match token.token_type {
+                    TokenType::EOF => None,
+                    _ => Some(token),
+                },
+
+                Ok(None) => {
+                    let position = self.tokenizer.pos;
+                    let c        = self.tokenizer.next().unwrap();
+
+                    self.errors.push(Response::error(Some(ErrorLocation::new(position, 1)), format!("unexpected character: {:?}", c)))
+                },
+
+                Err(e) => self.errors.push(e),
+            }
         }
     }
 }
@@ -1,5 +1,6 @@
 use super::tokenizer::Tokenizer;
-use super::token::{Token, TokenType};
+use super::token::{Token, TokenType, TokenPosition};
+use super::super::{Response, ErrorLocation};
 
 macro_rules! token {
     ($tokenizer:expr, $token_type:ident, $accum:expr) => {{
@@ -12,112 +13,382 @@ macro_rules! token {
     }};
 }
 
-pub struct IntLiteralMatcher;
+// decimals with an optional fraction and exponent, or `0x`, `0o` and `0b` integers. anything
+// glued to a literal is kept in its token, so the parser can report the literal as malformed
+pub struct NumberLiteralMatcher;
 
-impl Matcher for IntLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
-        let string = tokenizer.collect_if(|c| c.is_digit(10));
+impl Matcher for NumberLiteralMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
+        if !tokenizer.peek().unwrap().is_ascii_digit() {
+            return Ok(None)
+        }
 
-        if string.is_empty() {
-            None
-        } else {
-            Some(token!(tokenizer, Int, string))
+        let radix = tokenizer.peek() == Some(&'0') && tokenizer.peek_n(1).is_some_and(|c| "xob".contains(*c));
+
+        let mut string = tokenizer.collect_if(|c| c.is_ascii_digit() || *c == '_');
+
+        if !radix {
+            if tokenizer.peek() == Some(&'.') && tokenizer.peek_n(1).is_some_and(|c| c.is_ascii_digit()) {
+                string.push(tokenizer.next().unwrap());
+                string.push_str(&tokenizer.collect_if(|c| c.is_ascii_digit() || *c == '_'))
+            }
+
+            if let Some('e') | Some('E') = tokenizer.peek() {
+                let sign  = tokenizer.peek_n(1).is_some_and(|c| *c == '+' || *c == '-');
+                let digit = tokenizer.peek_n(if sign { 2 } else { 1 }).is_some_and(|c| c.is_ascii_digit());
+
+                if digit {
+                    string.push(tokenizer.next().unwrap());
+
+                    if sign {
+                        string.push(tokenizer.next().unwrap())
+                    }
+
+                    string.push_str(&tokenizer.collect_if(|c| c.is_ascii_digit() || *c == '_'))
+                }
+            }
         }
+
+        string.push_str(&tokenizer.collect_if(|c| c.is_alphanumeric() || *c == '_'));
+
+        Ok(Some(token!(tokenizer, Number, string)))
     }
 }
 
+// strings, raw strings and chars. a string with `{...}` in it is kept raw as an interpolated
+// token, for the parser to split with `segments`
 pub struct StringLiteralMatcher;
 
 impl Matcher for StringLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
         let mut raw_marker = false;
         let delimeter  = match *tokenizer.peek().unwrap() {
-            '"'  => Some('"'),
-            '\'' => Some('\''),
-            'r' => {
-                if tokenizer.peek_n(1) == Some(&'"') {
-                    raw_marker = true;
-                    tokenizer.advance();
-
-                    Some('"')
-                } else {
-                    None
-                }
+            '"'  => '"',
+            '\'' => '\'',
+            'r' if tokenizer.peek_n(1) == Some(&'"') => {
+                raw_marker = true;
+                tokenizer.advance();
+
+                '"'
             },
-            _ => return None,
+            _ => return Ok(None),
         };
 
+        let quote = tokenizer.pos;
+
         tokenizer.advance();
 
-        let mut string       = String::new();
-        let mut found_escape = false;
+        let mut body   = String::new();
+        let mut scan   = Scan::new(delimeter == '"' && !raw_marker);
+        let mut opened = quote; // where the outermost `{...}` being scanned starts
 
         while !tokenizer.end() {
+            let c = *tokenizer.peek().unwrap();
+
             if raw_marker {
-                if tokenizer.peek().unwrap() == &'"' {
+                if c == '"' {
                     break
                 }
-                string.push(tokenizer.next().unwrap())
-            } else if found_escape {
-                string.push(
-                    match tokenizer.next().unwrap() {
-                        c @ '\\' | c @ '\'' | c @ '"' => c,
-                        'n' => '\n',
-                        'r' => '\r',
-                        't' => '\t',
-                        s => panic!("invalid character escape: {}", s),
-                    }
-                );
-                found_escape = false
-            } else {
-                match *tokenizer.peek().unwrap() {
-                    '\\' => {
-                        tokenizer.next();
-                        found_escape = true
-                    },
-                    c if delimeter.is_some() && c == delimeter.unwrap() => break,
-                    _ => string.push(tokenizer.next().unwrap()),
-                }
+            } else if c == delimeter && scan.at_end() {
+                break
+            }
+
+            if scan.depth == 0 {
+                opened = tokenizer.pos
             }
+
+            scan.step(c);
+            body.push(tokenizer.next().unwrap())
+        }
+
+        // the quotes of a `"{"` are taken as a string inside of it, so it's the brace that's left open
+        if tokenizer.end() && scan.depth > 0 {
+            return Err(Response::error(Some(ErrorLocation::new(opened, 1)), "unterminated interpolation, missing `}`".to_string()))
         }
+
+        if tokenizer.end() {
+            let what = if delimeter == '"' { "string" } else { "char literal" };
+            return Err(Response::error(Some(ErrorLocation::new(tokenizer.last_position(), 1)), format!("unterminated {}", what)))
+        }
+
         tokenizer.advance();
-        if delimeter.is_some() {
-            match delimeter.unwrap() {
-                '"'  => {
-                    Some(token!(tokenizer, Str, string))
-                },
+
+        if raw_marker {
+            return Ok(Some(token!(tokenizer, Str, body)))
+        }
+
+        let segments = segments(&body, quote, delimeter == '"')?;
+
+        match delimeter {
+            '"'  => match segments.as_slice() {
+                []                    => Ok(Some(token!(tokenizer, Str, String::new()))),
+                [Segment::Text(text)] => Ok(Some(token!(tokenizer, Str, text.clone()))),
+                _                     => Ok(Some(token!(tokenizer, Interpolated, body))),
+            },
+
+            _ => match segments.as_slice() {
+                [Segment::Text(text)] if text.chars().count() == 1 => Ok(Some(token!(tokenizer, Char, text.clone()))),
+
                 _ => {
-                    if string.len() == 1 {
-                        Some(token!(tokenizer, Char, string))
-                    } else {
-                        panic!("invalid char literal")
+                    let span = body.chars().count() + 2;
+                    Err(Response::error(Some(ErrorLocation::new(tokenizer.last_position(), span)), format!("invalid char literal, expected a single character: '{}'", body)))
+                },
+            },
+        }
+    }
+}
+
+// where a string body is, so its end can be told apart from quotes inside `{...}`
+struct Scan {
+    interpolate: bool,
+    escaped:     bool,
+    depth:       usize, // of braces, inside `{...}`
+    inner:       bool,  // inside a string inside `{...}`
+}
+
+impl Scan {
+    fn new(interpolate: bool) -> Scan {
+        Scan {
+            interpolate,
+            escaped: false,
+            depth:   0,
+            inner:   false,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        !self.escaped && self.depth == 0
+    }
+
+    fn step(&mut self, c: char) {
+        if self.escaped {
+            self.escaped = false;
+            return
+        }
+
+        match c {
+            '\\' if self.depth == 0 || self.inner => self.escaped = true,
+
+            '"' if self.depth > 0 => self.inner = !self.inner,
+
+            '{' if self.interpolate && !self.inner => self.depth += 1,
+            '}' if self.depth > 0 && !self.inner   => self.depth -= 1,
+
+            _ => (),
+        }
+    }
+}
+
+// a piece of a string literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Code(String, TokenPosition), // the source inside `{...}`, and where it starts
+}
+
+// splits the body of a literal opened at `quote` into text, with escapes applied, and, when
+// interpolating, the code of every `{...}`
+pub fn segments(body: &str, quote: TokenPosition, interpolate: bool) -> Result<Vec<Segment>, Response> {
+    let chars: Vec<char> = body.chars().collect();
+
+    // positions the same way the tokenizer counts them
+    let mut positions = Vec::with_capacity(chars.len());
+    let mut position  = quote;
+
+    for c in &chars {
+        position = if *c == '\n' {
+            TokenPosition::new(position.line + 1, 0)
+        } else {
+            TokenPosition::new(position.line, position.col + 1)
+        };
+
+        positions.push(position)
+    }
+
+    let mut segments = Vec::new();
+    let mut errors   = Vec::new();
+    let mut text     = String::new();
+    let mut i        = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let (c, length) = match escape(&chars[i + 1 ..]) {
+                    Ok(escaped)           => escaped,
+                    Err((message, length)) => {
+                        errors.push(Response::error(Some(ErrorLocation::new(positions[i], length + 1)), message));
+                        i += length + 1;
+                        continue
+                    },
+                };
+
+                text.push(c);
+                i += length + 1
+            },
+
+            '{' if interpolate => {
+                let mut scan = Scan::new(true);
+                scan.step('{');
+
+                let mut end = i + 1;
+
+                while end < chars.len() {
+                    scan.step(chars[end]);
+
+                    if scan.depth == 0 {
+                        break
                     }
+
+                    end += 1
+                }
+
+                let code: String = chars[i + 1 .. end].iter().collect();
+
+                if code.trim().is_empty() {
+                    errors.push(Response::error(Some(ErrorLocation::new(positions[i], end - i + 1)), "empty interpolation".to_string()))
+                } else {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(text.clone()));
+                        text.clear()
+                    }
+
+                    segments.push(Segment::Code(code, positions[i + 1]))
+                }
+
+                i = end + 1
+            },
+
+            c => {
+                text.push(c);
+                i += 1
+            },
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::Text(text))
+    }
+
+    match errors.len() {
+        0 => Ok(segments),
+        1 => Err(errors.remove(0)),
+        _ => Err(Response::group(errors)),
+    }
+}
+
+// the character an escape stands for, and how many characters after the `\` it took up
+fn escape(chars: &[char]) -> Result<(char, usize), (String, usize)> {
+    let c = match chars.first() {
+        Some(c) => *c,
+        None    => return Err(("unfinished character escape".to_string(), 0)),
+    };
+
+    match c {
+        '\\' | '\'' | '"' | '{' | '}' => Ok((c, 1)),
+        'n' => Ok(('\n', 1)),
+        'r' => Ok(('\r', 1)),
+        't' => Ok(('\t', 1)),
+        '0' => Ok(('\0', 1)),
+
+        'x' => {
+            let digits: String = chars[1 ..].iter().take(2).take_while(|c| c.is_ascii_hexdigit()).collect();
+
+            match u32::from_str_radix(&digits, 16) {
+                Ok(value) if digits.len() == 2 && value <= 0x7f => Ok((value as u8 as char, 3)),
+                Ok(_) if digits.len() == 2 => Err((format!("\\x escape out of range, must be at most \\x7f: \\x{}", digits), 3)),
+                _                          => Err(("invalid \\x escape, expected two hex digits".to_string(), 1 + digits.len())),
+            }
+        },
+
+        'u' => {
+            if chars.get(1) != Some(&'{') {
+                return Err(("invalid unicode escape, expected \\u{...}".to_string(), 1))
+            }
+
+            let close = match chars.iter().take(9).position(|c| *c == '}') {
+                Some(close) => close,
+                None        => return Err(("invalid unicode escape, expected 1 to 6 hex digits and a }".to_string(), 2)),
+            };
+
+            let digits: String = chars[2 .. close].iter().collect();
+
+            if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(("invalid unicode escape, expected 1 to 6 hex digits".to_string(), close + 1))
+            }
+
+            match u32::from_str_radix(&digits, 16) {
+                Ok(value) => match ::std::char::from_u32(value) {
+                    Some(c) => Ok((c, close + 1)),
+                    None    => Err((format!("invalid unicode escape, not a character: \\u{{{}}}", digits), close + 1)),
                 },
+
+                Err(_) => Err(("invalid unicode escape, expected 1 to 6 hex digits".to_string(), close + 1)),
+            }
+        },
+
+        c => Err((format!("invalid character escape: \\{}", c), 1)),
+    }
+}
+
+// `#` to the end of the line, or `#[` to the matching `]#`, nesting
+pub struct CommentMatcher;
+
+impl Matcher for CommentMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
+        if tokenizer.peek() != Some(&'#') {
+            return Ok(None)
+        }
+
+        if tokenizer.peek_n(1) != Some(&'[') {
+            let string = tokenizer.collect_if(|c| *c != '\n');
+            return Ok(Some(token!(tokenizer, Comment, string)))
+        }
+
+        let mut string = String::new();
+        let mut depth  = 0;
+
+        while let Some(c) = tokenizer.next() {
+            string.push(c);
+
+            match (c, tokenizer.peek().cloned()) {
+                ('#', Some('[')) => depth += 1,
+                (']', Some('#')) => depth -= 1,
+                _                => continue,
+            }
+
+            string.push(tokenizer.next().unwrap());
+
+            if depth == 0 {
+                return Ok(Some(token!(tokenizer, Comment, string)))
             }
-        } else {
-            None
         }
+
+        Err(Response::error(Some(ErrorLocation::new(tokenizer.last_position(), 2)), "unterminated block comment, missing `]#`".to_string()))
     }
 }
 
+// `Ok(None)` when the input doesn't start with what the matcher is looking for, and an error
+// when it does but is malformed, in which case the bad token is skipped
 pub trait Matcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token>;
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response>;
 }
 
 pub struct IdentifierMatcher;
 
 impl Matcher for IdentifierMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
-        if !tokenizer.peek().unwrap().is_alphabetic() {
-            return None
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
+        let first = *tokenizer.peek().unwrap();
+
+        if !first.is_alphabetic() && first != '_' {
+            return Ok(None)
         }
 
         let string = tokenizer.collect_if(|c| c.is_alphanumeric() || "_!?".contains(*c));
 
         if string.is_empty() {
-            None
+            Ok(None)
         } else {
-            Some(token!(tokenizer, Identifier, string))
+            Ok(Some(token!(tokenizer, Identifier, string)))
         }
     }
 }
@@ -125,13 +396,13 @@ impl Matcher for IdentifierMatcher {
 pub struct WhitespaceMatcher;
 
 impl Matcher for WhitespaceMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
         let string = tokenizer.collect_if(|c| c.is_whitespace());
 
-        if string.len() > 0 {
-            Some(token!(tokenizer, Whitespace, string))
+        if !string.is_empty() {
+            Ok(Some(token!(tokenizer, Whitespace, string)))
         } else {
-            None
+            Ok(None)
         }
     }
 }
@@ -151,15 +422,15 @@ impl ConstantCharMatcher {
 }
 
 impl Matcher for ConstantCharMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
-        let c = tokenizer.peek().unwrap().clone();
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
+        let c = *tokenizer.peek().unwrap();
         for constant in self.constants {
             if c == *constant {
                 tokenizer.advance();
-                return Some(token!(tokenizer, self.token_type, constant.to_string()))
+                return Ok(Some(token!(tokenizer, self.token_type, constant.to_string())))
             }
         }
-        None
+        Ok(None)
     }
 }
 
@@ -178,18 +449,18 @@ impl ConstantStringMatcher {
 }
 
 impl Matcher for ConstantStringMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
         for constant in self.constants {
             let dat = tokenizer.clone().take(constant.len());
             if dat.size_hint().1.unwrap() != constant.len() {
-                return None
+                return Ok(None)
             }
             if dat.collect::<String>() == *constant {
                 tokenizer.advance_n(constant.len());
-                return Some(token!(tokenizer, self.token_type.clone(), constant.to_string()))
+                return Ok(Some(token!(tokenizer, self.token_type, constant.to_string())))
             }
         }
-        None
+        Ok(None)
     }
 }
 
@@ -208,22 +479,25 @@ impl KeyMatcher {
 }
 
 impl Matcher for KeyMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
-        for constant in self.constants.clone() {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Result<Option<Token>, Response> {
+        for constant in self.constants {
             let dat = tokenizer.clone().take(constant.len());
             if dat.size_hint().1.unwrap() != constant.len() {
-                return None
-            } else if &&dat.collect::<String>() == constant {
+                continue
+            } else if &dat.collect::<String>() == constant {
+                // only words need to end where a name would, `...rest` is still `...`
+                let word = constant.ends_with(|c: char| c.is_alphanumeric());
+
                 if let Some(c) = tokenizer.peek_n(constant.len()) {
-                    if "_!".contains(*c) || c.is_alphanumeric() {
-                        return None
+                    if word && ("_!".contains(*c) || c.is_alphanumeric()) {
+                        return Ok(None)
                     }
                 }
 
                 tokenizer.advance_n(constant.len());
-                return Some(token!(tokenizer, self.token_type.clone(), constant.to_string()))
+                return Ok(Some(token!(tokenizer, self.token_type, constant.to_string())))
             }
         }
-        None
+        Ok(None)
     }
 }
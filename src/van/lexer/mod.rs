@@ -4,6 +4,5 @@ pub mod matcher;
 pub mod lexer;
 
 pub use self::token::*;
-pub use self::tokenizer::*;
 pub use self::matcher::*;
 pub use self::lexer::*;
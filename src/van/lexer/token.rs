@@ -1,7 +1,8 @@
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TokenType {
-    Int,
+    Number,
     Str,
+    Interpolated,
     Char,
     Bool,
     Symbol,
@@ -9,11 +10,12 @@ pub enum TokenType {
     Identifier,
     Keyword,
     Whitespace,
+    Comment,
     EOL,
     EOF,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TokenPosition {
     pub line: usize,
     pub col: usize,
@@ -1,4 +1,5 @@
 use super::{Token, TokenType, TokenPosition, Matcher};
+use super::super::Response;
 
 #[derive(Clone)]
 pub struct Snapshot {
@@ -86,36 +87,38 @@ impl Tokenizer {
         self.peek_snapshot().unwrap().pos
     }
 
-    pub fn try_match_token(&mut self, matcher: &Matcher) -> Option<Token> {
+    pub fn try_match_token(&mut self, matcher: &dyn Matcher) -> Result<Option<Token>, Response> {
         if self.end() {
-            return Some(Token::new(TokenType::EOF,
-                                   TokenPosition::new(self.index, self.index),
-                                   String::new()));
+            return Ok(Some(Token::new(TokenType::EOF,
+                                      TokenPosition::new(self.index, self.index),
+                                      String::new())));
         }
 
         self.take_snapshot();
         match matcher.try_match(self) {
-            Some(t) => {
+            Ok(Some(t)) => {
                 self.commit_snapshot();
-                Some(t)
+                Ok(Some(t))
             }
 
-            None => {
+            Ok(None) => {
                 self.rollback_snapshot();
-                None
+                Ok(None)
+            }
+
+            // the bad token stays consumed, so lexing goes on right after it
+            Err(e) => {
+                self.commit_snapshot();
+                Err(e)
             }
         }
     }
 
     pub fn collect_if(&mut self, func: fn(&char) -> bool) -> String {
         let mut accum = String::new();
-        loop {
-            if let Some(c) = self.peek() {
-                if func(c) {
-                    accum.push(*c);
-                } else {
-                    break
-                }
+        while let Some(c) = self.peek() {
+            if func(c) {
+                accum.push(*c);
             } else {
                 break
             }
@@ -0,0 +1,355 @@
+use std::rc::Rc;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+// a checked module, along with what importing it gives access to
+pub struct Module {
+    pub name:      String,
+    pub ast:       Vec<Statement>,
+    pub exports:   Vec<(String, Type)>,
+    pub aliases:   HashMap<String, Type>,
+    pub overloads: HashMap<String, Vec<String>>, // the variants of every overloaded fun, which are exported along with it
+}
+
+impl Module {
+    pub fn export(&self, name: &str) -> Option<&Type> {
+        self.exports.iter().find(|export| export.0 == name).map(|export| &export.1)
+    }
+
+    // `name`, followed by the other variants it stands for when it's overloaded
+    pub fn exported_with_variants(&self, name: &str) -> Vec<String> {
+        match self.overloads.get(name) {
+            Some(variants) => variants.clone(),
+            None           => vec![name.to_owned()],
+        }
+    }
+
+    // the module seen as a struct of everything it defines
+    pub fn namespace(&self) -> Type {
+        let mut fields = HashMap::new();
+
+        for (name, t) in &self.exports {
+            fields.insert(name.clone(), Rc::new(t.clone()));
+        }
+
+        Type::Struct(fields)
+    }
+
+    // the name the linked program keeps the module's value under, which no identifier can spell
+    pub fn binding(&self) -> String {
+        format!("${}", self.name.replace('.', "$"))
+    }
+}
+
+// a response that belongs to some module's file
+pub struct Failure {
+    pub path:     PathBuf,
+    pub text:     String,
+    pub response: Response,
+}
+
+pub struct Loader {
+    pub roots:   Vec<PathBuf>,
+    pub modules: HashMap<String, Rc<Module>>,
+    pub order:   Vec<Rc<Module>>, // dependencies come before what imports them
+    loading:     Vec<(String, PathBuf)>, // the modules being loaded, by name and where their file is
+    broken:      HashSet<String>, // modules that failed to load, so they're only reported once
+    failures:    Vec<Failure>,
+    inference:   Rc<Inference>, // so variables in exported types keep meaning the same thing
+}
+
+impl Loader {
+    pub fn new(roots: Vec<PathBuf>) -> Loader {
+        Loader {
+            roots,
+            modules:   HashMap::new(),
+            order:     Vec::new(),
+            loading:   Vec::new(),
+            broken:    HashSet::new(),
+            failures:  Vec::new(),
+            inference: Rc::new(Inference::new()),
+        }
+    }
+
+    // the file the program is in, which modules can't import back without a cycle
+    pub fn start_from(&mut self, path: &Path) {
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        self.loading.push((name, canonical(path)))
+    }
+
+    // loads everything `ast` imports, along with what those import in turn. errors in the
+    // importing file itself come back as a response, errors in modules as failures of their own
+    pub fn load_imports(&mut self, ast: &[Statement]) -> Result<(), (Option<Response>, Vec<Failure>)> {
+        let mut errors = Vec::new();
+        let before     = self.failures.len();
+
+        for statement in ast {
+            if let Statement::Import(ref import) = *statement {
+                if let Err(e) = self.import(import) {
+                    errors.push(e)
+                }
+            }
+        }
+
+        if errors.is_empty() && self.failures.len() == before {
+            Ok(())
+        } else {
+            let response = if errors.is_empty() {
+                None
+            } else {
+                Some(Response::group(errors))
+            };
+
+            Err((response, self.failures.drain(before ..).collect()))
+        }
+    }
+
+    fn import(&mut self, import: &Import) -> Result<Rc<Module>, Response> {
+        let name     = module_name(&import.from)?;
+        let location = || Some(ErrorLocation::new(module_position(&import.from), name.len()));
+
+        if let Some(module) = self.modules.get(&name) {
+            return Ok(module.clone())
+        }
+
+        if self.broken.contains(&name) {
+            return Err(Response::error(location(), format!("couldn't load module: {}", name)))
+        }
+
+        let path = match self.resolve(&name) {
+            Some(path) => path,
+            None       => return Err(Response::error(location(), format!("can't find module {} in: {}", name, self.describe_roots(&name)))),
+        };
+
+        // told apart by file, as the program's own has no module name
+        if let Some(start) = self.loading.iter().position(|loading| loading.1 == canonical(&path)) {
+            let mut cycle: Vec<String> = self.loading[start ..].iter().map(|loading| loading.0.clone()).collect();
+            cycle.push(name.clone());
+
+            return Err(Response::error(location(), format!("import cycle: {}", cycle.join(" -> "))))
+        }
+
+        let text = fs::read_to_string(&path).map_err(|e| Response::error(location(), format!("couldn't read {}: {}", path.display(), e)))?;
+
+        self.loading.push((name.clone(), canonical(&path)));
+        let module = self.load(&name, path, text);
+        self.loading.pop();
+
+        match module {
+            Some(module) => {
+                self.modules.insert(name.clone(), module.clone());
+                self.order.push(module.clone());
+
+                Ok(module)
+            },
+
+            None => {
+                self.broken.insert(name.clone());
+                Err(Response::error(location(), format!("couldn't load module: {}", name)))
+            },
+        }
+    }
+
+    // parses and checks a module, recording whatever goes wrong against its own file
+    fn load(&mut self, name: &str, path: PathBuf, text: String) -> Option<Rc<Module>> {
+        let ast = match make_lexer(text.chars().collect()).tokenize().and_then(|tokens| Parser::new(Traveler::new(tokens)).parse()) {
+            Ok(ast) => ast,
+            Err(response) => {
+                self.failures.push(Failure {path, text, response});
+                return None
+            },
+        };
+
+        // the module is still checked against the modules that did load
+        let imports = self.load_imports(&ast);
+
+        let mut visitor  = self.visitor();
+        let response     = visitor.visit(&ast);
+        let (errors, _)  = response.count();
+
+        if let Err((imported, mut failures)) = imports {
+            self.failures.append(&mut failures);
+
+            let responses: Vec<Response> = imported.into_iter().chain(Some(response).filter(|_| errors > 0)).collect();
+
+            if !responses.is_empty() {
+                self.failures.push(Failure {path, text, response: Response::group(responses)})
+            }
+
+            return None
+        }
+
+        if errors > 0 {
+            self.failures.push(Failure {path, text, response});
+            return None
+        }
+
+        // what it imports is for itself, and only what it defines goes on to what imports it
+        let mut exports: Vec<(String, Type)> = Vec::new();
+
+        for (name, index) in visitor.symtab.names.borrow().iter().filter(|(_, index)| !visitor.imported.contains(index)) {
+            let t = visitor.typetab.get_type(*index, 0).unwrap_or(Type::Undefined);
+            exports.push((name.clone(), visitor.inference.resolve(&t)))
+        }
+
+        exports.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let aliases   = visitor.typetab.aliases.borrow().clone();
+        let overloads = visitor.symtab.overloads.borrow().iter().filter(|(name, variants)| variants.len() > 1 && exports.iter().any(|export| export.0 == **name)).map(|(name, variants)| {
+            (name.clone(), variants.clone())
+        }).collect();
+
+        Some(Rc::new(Module {
+            name: name.to_owned(),
+            ast:  desugar(&ast, &visitor.resolved),
+            exports,
+            aliases,
+            overloads,
+        }))
+    }
+
+    // a visitor that knows about every module loaded so far
+    pub fn visitor(&self) -> Visitor {
+        let mut visitor = Visitor::new();
+        visitor.modules   = self.modules.clone();
+        visitor.inference = self.inference.clone();
+
+        visitor
+    }
+
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let relative = module_path(name);
+
+        self.roots.iter().map(|root| root.join(&relative)).find(|path| path.is_file())
+    }
+
+    fn describe_roots(&self, name: &str) -> String {
+        let relative = module_path(name);
+        let tried: Vec<String> = self.roots.iter().map(|root| root.join(&relative).display().to_string()).collect();
+
+        tried.join(", ")
+    }
+
+    // one program out of the modules and `ast`: every module becomes a block, evaluated once
+    // and in order, and imports become definitions taken from those blocks
+    pub fn link(&self, ast: &[Statement]) -> Result<Vec<Statement>, Response> {
+        let mut program = Vec::new();
+
+        for module in &self.order {
+            let mut body = self.link_imports(&module.ast)?;
+
+            let values = module.exports.iter().map(|(name, _)| Assignment {
+                left:     Rc::new(Expression::Identifier(name.clone(), TokenPosition::default())),
+                right:    Rc::new(Expression::Identifier(name.clone(), TokenPosition::default())),
+                position: TokenPosition::default(),
+            }).collect();
+
+            body.push(Statement::Expression(Rc::new(Expression::Initialization(Rc::new(Initialization {
+                id:       Expression::Identifier(module.binding(), TokenPosition::default()),
+                values,
+                position: TokenPosition::default(),
+            })))));
+
+            program.push(Statement::Definition(Definition {
+                t:        None,
+                name:     module.binding(),
+                right:    Some(Rc::new(Expression::Block(body))),
+                position: TokenPosition::default(),
+            }))
+        }
+
+        program.append(&mut self.link_imports(ast)?);
+
+        Ok(program)
+    }
+
+    fn link_imports(&self, ast: &[Statement]) -> Result<Vec<Statement>, Response> {
+        let mut linked = Vec::new();
+
+        for statement in ast {
+            match *statement {
+                Statement::Import(Import {ref from, ref expose}) => {
+                    let name = module_name(from)?;
+
+                    let module = match self.modules.get(&name) {
+                        Some(module) => module,
+                        None         => return Err(Response::error(None, format!("unresolved module: {}", name))),
+                    };
+
+                    let position = module_position(from);
+                    let binding  = Rc::new(Expression::Identifier(module.binding(), position));
+
+                    let define = |name: &str, right: Expression| Statement::Definition(Definition {
+                        t:     None,
+                        name:  name.to_owned(),
+                        right: Some(Rc::new(right)),
+                        position,
+                    });
+
+                    let field = |name: &str| Expression::Index(Index {
+                        id:    binding.clone(),
+                        index: Rc::new(Expression::Identifier(name.to_owned(), position)),
+                        field: true,
+                        position,
+                    });
+
+                    match *expose {
+                        Expose::Nothing => linked.push(define(last_segment(&name), (*binding).clone())),
+
+                        Expose::Everything => for (name, _) in &module.exports {
+                            linked.push(define(name, field(name)))
+                        },
+
+                        Expose::Specifically(ref names) => for name in names.iter().flat_map(|name| module.exported_with_variants(name)) {
+                            linked.push(define(&name, field(&name)))
+                        },
+                    }
+                },
+
+                ref statement => linked.push(statement.clone()),
+            }
+        }
+
+        Ok(linked)
+    }
+}
+
+// `foo.bar` out of the expression the parser reads it as
+pub fn module_name(from: &Expression) -> Result<String, Response> {
+    match *from {
+        Expression::Identifier(ref name, _) => Ok(name.clone()),
+
+        Expression::Index(Index {ref id, ref index, field: true, ..}) => match **index {
+            Expression::Identifier(ref name, _) => Ok(format!("{}.{}", module_name(id)?, name)),
+            ref c                               => Err(Response::error(None, format!("invalid module name: {:?}", c))),
+        },
+
+        ref c => Err(Response::error(c.position().map(|position| ErrorLocation::new(position, 1)), format!("invalid module name: {:?}", c))),
+    }
+}
+
+// where the name of the module starts, at its first segment
+pub fn module_position(from: &Expression) -> TokenPosition {
+    match *from {
+        Expression::Index(Index {ref id, field: true, ..}) => module_position(id),
+        ref c                                              => c.position().unwrap_or_default(),
+    }
+}
+
+pub fn last_segment(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn module_path(name: &str) -> PathBuf {
+    let mut path: PathBuf = name.split('.').collect();
+    path.set_extension("van");
+
+    path
+}
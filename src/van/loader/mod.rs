@@ -0,0 +1,5 @@
+pub mod loader;
+
+pub use super::*;
+
+pub use self::loader::*;
@@ -2,8 +2,16 @@ pub mod lexer;
 pub mod parser;
 pub mod error;
 pub mod semantics;
+pub mod codegen;
+pub mod interp;
+pub mod vm;
+pub mod loader;
 
 pub use self::lexer::*;
 pub use self::parser::*;
 pub use self::error::*;
 pub use self::semantics::*;
+pub use self::codegen::*;
+pub use self::interp::*;
+pub use self::vm::*;
+pub use self::loader::*;
@@ -11,12 +11,15 @@ pub enum Expression {
     Bool(bool),
     Str(String),
     Char(char),
+    Nil,
     Identifier(String, TokenPosition),
     BinaryOp(BinaryOp),
+    UnaryOp(UnaryOp),
     MatchPattern(MatchPattern),
     Call(Call),
     Index(Index),
-    Array(Vec<Expression>),
+    Array(Vec<Expression>, TokenPosition),
+    Tuple(Vec<Expression>, TokenPosition),
     If(Rc<If>),
     Unless(Rc<Unless>),
     Struct(Vec<TypeDefinition>),
@@ -26,6 +29,30 @@ pub enum Expression {
     EOF,
 }
 
+impl Expression {
+    // where the expression starts, when the parser kept track of it
+    pub fn position(&self) -> Option<TokenPosition> {
+        match *self {
+            Expression::Identifier(_, position) |
+            Expression::Array(_, position)      |
+            Expression::Tuple(_, position)      => Some(position),
+
+            Expression::BinaryOp(ref op)      => op.left.position().or(Some(op.position)),
+            Expression::UnaryOp(ref op)       => Some(op.position),
+            Expression::Index(ref index)      => index.id.position().or(Some(index.position)),
+            Expression::Call(ref call)        => Some(call.position),
+            Expression::If(ref a)             => Some(a.position),
+            Expression::Unless(ref a)         => Some(a.base.position),
+            Expression::MatchPattern(ref m)   => Some(m.position),
+            Expression::Initialization(ref a) => Some(a.position),
+            Expression::FunctionMatch(ref a)  => Some(a.position),
+            Expression::Fun(ref a)            => Some(a.position),
+
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BinaryOp {
     pub left:     Rc<Expression>,
@@ -36,7 +63,7 @@ pub struct BinaryOp {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct UnaryOp {
-    pub op:       Operand,
+    pub op:       UnaryOperand,
     pub expr:     Rc<Expression>,
     pub position: TokenPosition,
 }
@@ -45,24 +72,128 @@ pub struct UnaryOp {
 pub struct MatchPattern {
     pub matching: Rc<Expression>,
     pub arms:     Vec<MatchArm>,
+    pub position: TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchArm {
-    pub param: Rc<Expression>,
-    pub body:  Rc<Expression>,
+    pub params:   Vec<Pattern>,
+    pub guard:    Option<Expression>,
+    pub body:     Rc<Expression>,
+    pub position: TokenPosition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard(TokenPosition),
+    Binding(String, TokenPosition),
+    Literal(Expression, TokenPosition),                          // a number, string, char or boolean
+    Array(Vec<Pattern>, Option<Option<String>>, TokenPosition),  // `[a, b, ...rest]`, the rest maybe unnamed
+    Struct(String, Vec<(String, Pattern)>, TokenPosition),      // `Point { x = 0, y }`
+    Variant(String, Vec<Pattern>, TokenPosition),                // `Rect w h`
+    Tuple(Vec<Pattern>, TokenPosition),                          // `(a, b)`
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn position(&self) -> TokenPosition {
+        match *self {
+            Pattern::Wildcard(position)       |
+            Pattern::Binding(_, position)     |
+            Pattern::Literal(_, position)     |
+            Pattern::Array(_, _, position)    |
+            Pattern::Struct(_, _, position)   |
+            Pattern::Variant(_, _, position)  |
+            Pattern::Tuple(_, position)       => position,
+            Pattern::Or(ref alternatives)     => alternatives[0].position(),
+        }
+    }
+
+    // the names bound by matching, in order; alternatives all bind the same ones
+    pub fn bindings(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.bind_into(&mut names);
+
+        names
+    }
+
+    fn bind_into(&self, names: &mut Vec<String>) {
+        match *self {
+            Pattern::Binding(ref name, _) => if !names.contains(name) {
+                names.push(name.clone())
+            },
+
+            Pattern::Array(ref content, ref rest, _) => {
+                for pattern in content {
+                    pattern.bind_into(names)
+                }
+
+                if let Some(Some(ref rest)) = *rest {
+                    if !names.contains(rest) {
+                        names.push(rest.clone())
+                    }
+                }
+            },
+
+            Pattern::Struct(_, ref fields, _) => for (_, pattern) in fields {
+                pattern.bind_into(names)
+            },
+
+            Pattern::Variant(_, ref fields, _) |
+            Pattern::Tuple(ref fields, _)      => for pattern in fields {
+                pattern.bind_into(names)
+            },
+
+            Pattern::Or(ref alternatives) => alternatives[0].bind_into(names),
+
+            Pattern::Wildcard(_) | Pattern::Literal(..) => (),
+        }
+    }
+}
+
+impl MatchArm {
+    // the names bound by every param, in order
+    pub fn bindings(&self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for param in &self.params {
+            param.bind_into(&mut names)
+        }
+
+        names
+    }
+
+    // the params as a single pattern, an array of them when there are several
+    pub fn pattern(&self) -> Pattern {
+        match self.params.len() {
+            1 => self.params[0].clone(),
+            _ => Pattern::Array(self.params.clone(), None, self.position),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Call {
     pub callee:   Rc<Expression>,
     pub args:     Vec<Rc<Expression>>,
+    pub position: TokenPosition,
+}
+
+impl Call {
+    // how many calls deep the callee goes, telling apart `(f a) b` from `f a`, which start at the same place
+    pub fn nesting(&self) -> usize {
+        match *self.callee {
+            Expression::Call(ref call) => call.nesting() + 1,
+            _                          => 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Index {
     pub id:       Rc<Expression>,
     pub index:    Rc<Expression>,
+    pub field:    bool, // `a.b` rather than `a[b]`
     pub position: TokenPosition,
 }
 
@@ -74,27 +205,33 @@ pub struct TypeDefinition {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Initialization {
-    pub id:     Expression,
-    pub values: Vec<Assignment>,
+    pub id:       Expression,
+    pub values:   Vec<Assignment>,
+    pub position: TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Expression(Rc<Expression>),
     Definition(Definition),
+    Destructure(Destructure),
     Assignment(Assignment),
     FunctionMatch(FunctionMatch),
     Fun(Fun),
     Struct(Struct),
+    Data(Data),
     If(If),
     Unless(Unless),
     MatchPattern(MatchPattern),
-    Interface(Interface), // todo
-    Implementation(Implementation), // todo
+    Interface(Interface),
+    Implementation(Implementation),
     Return(Option<Expression>),
-    Import(Import), // todo
+    Import(Import),
     Extern(Rc<Statement>),
     While(While),
+    For(For),
+    Break(TokenPosition),
+    Continue(TokenPosition),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -105,17 +242,28 @@ pub enum Function {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionMatch {
-    pub t:    Option<Type>,
-    pub name: Option<Expression>,
-    pub arms: Vec<MatchArm>,
+    pub t:        Option<Type>,
+    pub name:     Option<Expression>,
+    pub generics: Vec<String>, // the names of its type parameters
+    pub arms:     Vec<MatchArm>,
+    pub position: TokenPosition,
+}
+
+impl FunctionMatch {
+    // how many params it takes, which every arm agrees on
+    pub fn arity(&self) -> usize {
+        self.arms.first().map_or(1, |arm| arm.params.len())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Fun {
-    pub t:      Option<Type>,
-    pub name:   Option<Expression>,
-    pub params: Vec<TypeDefinition>,
-    pub body:   Vec<Statement>,
+    pub t:        Option<Type>,
+    pub name:     Option<Expression>,
+    pub generics: Vec<String>,
+    pub params:   Vec<TypeDefinition>,
+    pub body:     Vec<Statement>,
+    pub position: TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -126,10 +274,37 @@ pub struct Definition {
     pub position: TokenPosition,
 }
 
+// a definition binding every name in a pattern, as in `(x, y) := split s` or `{x, y} := pos`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Destructure {
+    pub t:        Option<Type>,
+    pub pattern:  Pattern,
+    pub right:    Rc<Expression>,
+    pub position: TokenPosition,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
-    pub name: String,
-    pub body: Vec<TypeDefinition>,
+    pub name:     String,
+    pub generics: Vec<String>,
+    pub body:     Vec<TypeDefinition>,
+    pub position: TokenPosition,
+}
+
+// a type whose values are one of its variants, each carrying what it's declared with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Data {
+    pub name:     String,
+    pub generics: Vec<String>,
+    pub variants: Vec<Variant>,
+    pub position: TokenPosition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name:     String,
+    pub fields:   Vec<Type>,
+    pub position: TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -137,6 +312,7 @@ pub struct If {
     pub condition: Expression,
     pub body:      Vec<Statement>,
     pub elses:     Option<Vec<(Option<Expression>, Vec<Statement>)>>, // vec<(condition, body)?>
+    pub position:  TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -145,10 +321,22 @@ pub struct While {
     pub body:      Vec<Statement>,
 }
 
+// `for x in xs`, or `for i, x in xs` also naming where `x` is
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub index:    Option<String>,
+    pub name:     String,
+    pub iterable: Expression,
+    pub body:     Vec<Statement>,
+    pub position: TokenPosition,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Interface {
-    pub name:  String,
-    pub types: Vec<TypeDefinition>,
+    pub name:     String,
+    pub generics: Vec<String>,
+    pub types:    Vec<TypeDefinition>,
+    pub position: TokenPosition,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -159,8 +347,37 @@ pub struct Unless {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Implementation {
     pub structure: String,
-    pub interface: Option<String>,
+    pub interface: Option<Type>, // a name, given type arguments when it's generic
     pub body:      Vec<Function>,
+    pub position:  TokenPosition,
+}
+
+impl Implementation {
+    // the methods as plain functions named `structure.method`, which is what they compile to
+    pub fn methods(&self) -> Vec<Statement> {
+        let method_name = |name: &Option<Expression>| match *name {
+            Some(Expression::Identifier(ref name, position)) => Some(Expression::Index(Index {
+                id:    Rc::new(Expression::Identifier(self.structure.clone(), position)),
+                index: Rc::new(Expression::Identifier(name.clone(), position)),
+                field: true,
+                position,
+            })),
+
+            ref c => c.clone(),
+        };
+
+        self.body.iter().map(|function| match *function {
+            Function::Fun(ref fun) => Statement::Fun(Fun {
+                name: method_name(&fun.name),
+                ..fun.clone()
+            }),
+
+            Function::Match(ref function) => Statement::FunctionMatch(FunctionMatch {
+                name: method_name(&function.name),
+                ..function.clone()
+            }),
+        }).collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -183,7 +400,7 @@ pub struct Assignment {
     pub position: TokenPosition,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operand {
     Pow,
     Mul, Div, Mod,
@@ -191,27 +408,83 @@ pub enum Operand {
     Equal, NEqual,
     Lt, Gt, LtEqual, GtEqual,
     Concat,
+    Range,
+    And, Xor, Or,
+    Default,
     PipeLeft, PipeRight,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    Left, Right,
+}
+
+// every binary operator, with how tight it binds; the higher, the tighter
+pub const OPERATORS: &[(&str, Operand, u8, Associativity)] = &[
+    ("<|",  Operand::PipeLeft,  1,  Associativity::Right),
+    ("|>",  Operand::PipeRight, 2,  Associativity::Left),
+    ("??",  Operand::Default,   3,  Associativity::Right),
+    ("or",  Operand::Or,        4,  Associativity::Left),
+    ("^^",  Operand::Xor,       5,  Associativity::Left),
+    ("and", Operand::And,       6,  Associativity::Left),
+    ("==",  Operand::Equal,     7,  Associativity::Left),
+    ("!=",  Operand::NEqual,    7,  Associativity::Left),
+    ("<",   Operand::Lt,        8,  Associativity::Left),
+    (">",   Operand::Gt,        8,  Associativity::Left),
+    ("<=",  Operand::LtEqual,   8,  Associativity::Left),
+    (">=",  Operand::GtEqual,   8,  Associativity::Left),
+    ("..",  Operand::Range,     9,  Associativity::Left),
+    ("+",   Operand::Add,       10, Associativity::Left),
+    ("-",   Operand::Sub,       10, Associativity::Left),
+    ("++",  Operand::Concat,    10, Associativity::Left),
+    ("*",   Operand::Mul,       11, Associativity::Left),
+    ("/",   Operand::Div,       11, Associativity::Left),
+    ("%",   Operand::Mod,       11, Associativity::Left),
+    ("^",   Operand::Pow,       13, Associativity::Right),
+];
+
 impl Operand {
-    pub fn from_str(v: &str) -> Option<(Operand, u8)> {
+    pub fn from_str(v: &str) -> Option<Operand> {
+        OPERATORS.iter().find(|o| o.0 == v).map(|o| o.1)
+    }
+
+    fn spec(&self) -> &'static (&'static str, Operand, u8, Associativity) {
+        OPERATORS.iter().find(|o| o.1 == *self).unwrap()
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.spec().0
+    }
+
+    pub fn precedence(&self) -> u8 {
+        self.spec().2
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        self.spec().3
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperand {
+    Neg, Not,
+}
+
+impl UnaryOperand {
+    // between `^` and `*`, so `-a ^ b` is `-(a ^ b)` and `-a * b` is `(-a) * b`
+    pub const PRECEDENCE: u8 = 12;
+
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            UnaryOperand::Neg => "-",
+            UnaryOperand::Not => "not",
+        }
+    }
+
+    pub fn from_str(v: &str) -> Option<UnaryOperand> {
         match v {
-            "^"   => Some((Operand::Pow, 0)),
-            "*"   => Some((Operand::Mul, 1)),
-            "/"   => Some((Operand::Div, 1)),
-            "%"   => Some((Operand::Mod, 1)),
-            "+"   => Some((Operand::Add, 2)),
-            "-"   => Some((Operand::Sub, 2)),
-            "++"  => Some((Operand::Concat, 2)),
-            "=="  => Some((Operand::Equal, 3)),
-            "~="  => Some((Operand::NEqual, 3)),
-            "<"   => Some((Operand::Lt, 4)),
-            ">"   => Some((Operand::Gt, 4)),
-            "<="  => Some((Operand::LtEqual, 4)),
-            ">="  => Some((Operand::GtEqual, 4)),
-            "<|"  => Some((Operand::PipeLeft, 5)),
-            "|>"  => Some((Operand::PipeRight, 5)),
+            "-"   => Some(UnaryOperand::Neg),
+            "not" => Some(UnaryOperand::Not),
             _     => None,
         }
     }
@@ -226,51 +499,27 @@ pub enum Type {
 
     Mut(Option<Rc<Type>>),
     Array(Rc<Type>, Option<Expression>),
+    Optional(Rc<Type>),              // `?Point`, either a value of the type or nil
+    Tuple(Vec<Type>),                // `(number, string)`
+    Range,                           // `0..10`, the numbers from the first up to the last
     Fun(Vec<Type>, Option<Rc<Type>>),
     Identifier(String),
+    Generic(String, Vec<Type>),      // a generic struct or interface given its type arguments
+    Params(Vec<String>, Rc<Type>),   // what a generic struct or interface is declared as, in terms of its parameters
     Struct(HashMap<String, Rc<Type>>),
+    Data(String, Vec<(String, Vec<Type>)>), // the name of a data type, and its variants with what they carry
+    Interface(HashMap<String, Rc<Type>>),
+    Var(usize), // to be inferred
     Undefined,
 }
 
 impl Type {
-    pub fn equals(&self, other: &Type) -> bool {
-        match (other, self) {
-            (&Type::Mut(ref a), &Type::Mut(ref b)) => a.clone().unwrap().equals(&**b.as_ref().unwrap()),
-            (&Type::Mut(ref a), b)                 => a.clone().unwrap().equals(b),
-            (a, &Type::Mut(ref b))                 => a.equals(&**b.as_ref().unwrap()),
-
-            _ => {
-                match *other {
-                    Type::Array(ref other_t, ref other_len) => match *self {
-                        Type::Array(ref t, ref len) => {
-                            if !other_len.is_some() {
-                                self == &Type::Array(other_t.clone(), len.clone())
-                            } else if !len.is_some() {
-                                Type::Array(t.clone(), other_len.clone()) == Type::Array(other_t.clone(), other_len.clone())
-                            } else {
-                                self == other
-                            }
-                        },
-
-                        _ => self == other
-                    },
-                    
-                    _ => self == other
-                }
-            }
-        }
-    }
-
     pub fn is_mut(&self) -> bool {
-        if let &Type::Mut(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(*self, Type::Mut(_))
     }
 
     pub fn unmut(&self) -> Option<Rc<Type>> {
-        if let &Type::Mut(ref unmut) = self {
+        if let Type::Mut(unmut) = self {
             (*unmut.clone().unwrap()).unmut()
         } else {
             Some(Rc::new(self.clone()))
@@ -304,6 +553,7 @@ impl fmt::Display for Type {
             Str    => write!(f, "string"),
             Bool   => write!(f, "boolean"),
             Nil    => write!(f, "nil"),
+            Range  => write!(f, "range"),
 
             Fun(ref params, ref retty) => {
                 write!(f, "fun")?;
@@ -312,7 +562,7 @@ impl fmt::Display for Type {
                     write!(f, " {}", param)?;
                 }
 
-                if let &Some(ref retty) = retty {
+                if let Some(retty) = retty {
                     write!(f, " -> {}", retty)
                 } else {
                     write!(f, " -> nil")
@@ -320,12 +570,50 @@ impl fmt::Display for Type {
             }
 
             Mut(ref a)          => write!(f, "mut {}", a.as_ref().unwrap_or(&Rc::new(Undefined))),
-            Array(ref t, ref e) => if let &Some(ref e) = e {
+            Array(ref t, ref e) => if let Some(e) = e {
                 write!(f, "[{}; {:?}]", t, e)
             } else {
                 write!(f, "[{}]", t)
             },
+            Optional(ref t)   => write!(f, "?{}", t),
+            Tuple(ref content) => {
+                write!(f, "(")?;
+
+                for (i, t) in content.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+
+                    write!(f, "{}", t)?
+                }
+
+                write!(f, ")")
+            },
             Identifier(ref a) => write!(f, "{}", a),
+            Generic(ref name, ref args) => {
+                write!(f, "{}[", name)?;
+
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+
+                    write!(f, "{}", arg)?
+                }
+
+                write!(f, "]")
+            },
+            Params(ref params, ref t) => write!(f, "[{}] {}", params.join(", "), t),
+            Data(ref name, _)         => write!(f, "{}", name),
+            Interface(ref hash) => {
+                write!(f, "interface {{")?;
+
+                for def in hash {
+                    write!(f, "{}: {}, ", def.0, def.1)?;
+                }
+
+                write!(f, "}}")
+            },
             Struct(ref hash)  => {
                 write!(f, "{{")?;
                 
@@ -336,9 +624,13 @@ impl fmt::Display for Type {
                 write!(f, "}}")
             }
             
-            Undefined => write!(f, "undefined"),
+            Var(var) => if var < 26 {
+                write!(f, "'{}", (b'a' + var as u8) as char)
+            } else {
+                write!(f, "'{}{}", (b'a' + (var % 26) as u8) as char, var / 26)
+            },
 
-            _ => Ok(())
+            Undefined => write!(f, "undefined"),
         }
     }
 }
@@ -1,17 +1,36 @@
 use std::rc::Rc;
+use std::mem;
 
 use super::*;
 
 pub struct Parser {
     traveler: Traveler,
     inside:   String,
+    errors:   Vec<Response>,
+    args:     bool, // parsing the args of a call, each of which stands on its own
 }
 
 impl Parser {
-    pub fn new(traveler: Traveler) -> Parser {
+    pub fn new(mut traveler: Traveler) -> Parser {
+        // comments stay in the token stream for tools, but mean nothing to the parser, and neither
+        // does the whitespace before them, which would otherwise be left in front of a newline
+        let mut tokens: Vec<Token> = Vec::with_capacity(traveler.tokens.len());
+
+        for token in mem::take(&mut traveler.tokens) {
+            if token.token_type != TokenType::Comment {
+                tokens.push(token)
+            } else if tokens.last().is_some_and(|last| last.token_type == TokenType::Whitespace) {
+                tokens.pop();
+            }
+        }
+
+        traveler.tokens = tokens;
+
         Parser {
             traveler,
             inside: String::new(),
+            errors: Vec::new(),
+            args:   false,
         }
     }
 
@@ -19,10 +38,42 @@ impl Parser {
         let mut stack = Vec::new();
 
         while self.traveler.remaining() > 1 {
-            stack.push(self.statement()?)
+            match self.statement() {
+                Ok(statement) => stack.push(statement),
+                Err(e)        => {
+                    self.errors.push(e);
+                    self.synchronize()
+                },
+            }
         }
 
-        Ok(stack)
+        if self.errors.is_empty() {
+            Ok(stack)
+        } else {
+            Err(Response::group(mem::take(&mut self.errors)))
+        }
+    }
+
+    // skips past the end of the current line, ignoring line breaks inside brackets
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        while self.traveler.remaining() > 1 {
+            let token = self.traveler.current().clone();
+
+            self.traveler.next();
+
+            match token.token_type {
+                TokenType::Symbol => match token.content.as_str() {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = if depth > 0 { depth - 1 } else { 0 },
+                    _               => (),
+                },
+
+                TokenType::EOL if depth == 0 => return,
+                _                            => (),
+            }
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -40,12 +91,8 @@ impl Parser {
     }
 
     fn skip_whitespace_eol(&mut self) {
-        loop {
-            match self.traveler.current().token_type {
-                TokenType::Whitespace |
-                TokenType::EOL => { self.traveler.next(); }
-                _ => break,
-            }
+        while let TokenType::Whitespace | TokenType::EOL = self.traveler.current().token_type {
+            self.traveler.next();
 
             if self.traveler.remaining() < 2 {
                 break
@@ -101,6 +148,11 @@ impl Parser {
 
             "fun" => self.get_fun_type(),
 
+            "?" => {
+                self.traveler.next();
+                Ok(Type::Optional(Rc::new(self.get_type()?)))
+            },
+
             "nil" => {
                 self.traveler.next();
                 Ok(Type::Nil)
@@ -153,31 +205,121 @@ impl Parser {
                 self.traveler.next();
                 self.skip_whitespace();
                 
-                let a = self.get_type()?;
+                let mut content = vec![self.get_type()?];
 
                 self.skip_whitespace();
+
+                // several types separated by commas are a tuple of them
+                while self.traveler.current_content() == "," {
+                    self.traveler.next();
+                    self.skip_whitespace();
+
+                    if self.traveler.current_content() == ")" {
+                        break
+                    }
+
+                    content.push(self.get_type()?);
+                    self.skip_whitespace()
+                }
+
                 self.traveler.expect_content(")")?;
                 self.traveler.next();
                 
-                Ok(a)
+                if content.len() == 1 {
+                    Ok(content.pop().unwrap())
+                } else {
+                    Ok(Type::Tuple(content))
+                }
             }
 
             _ => {
-                let a = Type::Identifier(self.traveler.expect(TokenType::Identifier)?);
+                let name = self.traveler.expect(TokenType::Identifier)?;
                 self.traveler.next();
-                Ok(a)
+
+                // type arguments go right after the name, as in `Pair[number, string]`
+                if self.traveler.current_content() != "[" {
+                    return Ok(Type::Identifier(name))
+                }
+
+                let mut args = Vec::new();
+
+                self.bracketed(&mut |parser| {
+                    args.push(parser.get_type()?);
+                    Ok(())
+                })?;
+
+                Ok(Type::Generic(name, args))
+            }
+        }
+    }
+
+    // `[a, b, ...]` right after a name, calling `element` on each
+    fn bracketed(&mut self, element: &mut dyn FnMut(&mut Self) -> Result<(), Response>) -> Result<(), Response> {
+        self.traveler.next();
+        self.skip_whitespace_eol();
+
+        while self.traveler.current_content() != "]" {
+            element(self)?;
+            self.skip_whitespace_eol();
+
+            if self.traveler.current_content() == "," {
+                self.traveler.next();
+                self.skip_whitespace_eol()
+            } else {
+                break
             }
         }
+
+        self.traveler.expect_content("]")?;
+        self.traveler.next();
+
+        Ok(())
+    }
+
+    // the names of the type parameters of a declaration, when it has any
+    fn generics(&mut self) -> Result<Vec<String>, Response> {
+        let mut generics = Vec::new();
+
+        if self.traveler.current_content() == "[" {
+            self.bracketed(&mut |parser| {
+                generics.push(parser.traveler.expect(TokenType::Identifier)?);
+                parser.traveler.next();
+
+                Ok(())
+            })?
+        }
+
+        Ok(generics)
     }
     
-    fn match_arm(self: &mut Self) -> Result<Option<MatchArm>, Response> {
+    fn match_arm(&mut self) -> Result<Option<MatchArm>, Response> {
         self.skip_whitespace_eol();
 
         if self.traveler.current_content() == "|" {
+            let position = self.traveler.current().position;
+
             self.traveler.next();
             self.skip_whitespace_eol();
 
-            let param = Rc::new(self.expression()?);
+            let mut params = vec![self.alternatives()?];
+
+            // whitespace separated patterns, one for each param
+            loop {
+                self.skip_whitespace();
+
+                match (self.traveler.current().token_type, self.traveler.current_content().as_str()) {
+                    (TokenType::EOL, _) | (TokenType::EOF, _)                 => break,
+                    (TokenType::Keyword, "if") | (TokenType::Keyword, "->") => break,
+                    _                                                         => params.push(self.alternatives()?),
+                }
+            }
+
+            let guard = if self.traveler.current_content() == "if" {
+                self.traveler.next();
+                Some(self.expression()?)
+            } else {
+                None
+            };
 
             self.skip_whitespace_eol();
 
@@ -192,15 +334,248 @@ impl Parser {
             self.skip_whitespace_eol();
 
             Ok(Some(MatchArm {
-                param,
+                params,
+                guard,
                 body,
+                position,
             }))
         } else {
             Ok(None)
         }
     }
 
+    // `a | b | c`, on one line so the next arm isn't taken for another alternative
+    fn alternatives(&mut self) -> Result<Pattern, Response> {
+        let mut alternatives = vec![self.pattern()?];
+
+        loop {
+            let backup = self.traveler.top;
+            self.skip_whitespace();
+
+            if self.traveler.current_content() != "|" {
+                self.traveler.top = backup;
+                break
+            }
+
+            self.traveler.next();
+            alternatives.push(self.pattern()?)
+        }
+
+        if alternatives.len() == 1 {
+            Ok(alternatives.pop().unwrap())
+        } else {
+            Ok(Pattern::Or(alternatives))
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, Response> {
+        self.pattern_of(true)
+    }
+
+    // capitalized names are variants, taking the patterns after them when `fields` is set, and
+    // anything else made of several patterns has to be in parens to be one of them
+    fn pattern_of(&mut self, fields: bool) -> Result<Pattern, Response> {
+        self.skip_whitespace();
+
+        let position = self.traveler.current().position;
+        let content  = self.traveler.current_content();
+
+        match self.traveler.current().token_type {
+            TokenType::Identifier => {
+                self.traveler.next();
+
+                if content == "_" {
+                    return Ok(Pattern::Wildcard(position))
+                }
+
+                let backup = self.traveler.top;
+                self.skip_whitespace();
+
+                if self.traveler.current_content() == "{" {
+                    let fields = self.block_of(&Self::field_pattern_, ("{", "}"))?;
+                    return Ok(Pattern::Struct(content, fields, position))
+                }
+
+                self.traveler.top = backup;
+
+                if !content.starts_with(|c: char| c.is_uppercase()) {
+                    return Ok(Pattern::Binding(content, position))
+                }
+
+                let mut patterns = Vec::new();
+
+                while fields && self.traveler.remaining() > 1 {
+                    let backup = self.traveler.top;
+                    self.skip_whitespace();
+
+                    let starts_pattern = match self.traveler.current().token_type {
+                        TokenType::Identifier |
+                        TokenType::Number     |
+                        TokenType::Str        |
+                        TokenType::Char       |
+                        TokenType::Bool       => true,
+                        TokenType::Symbol     => self.traveler.current_content() == "[" || self.traveler.current_content() == "(",
+                        _                     => false,
+                    };
+
+                    self.traveler.top = backup;
+
+                    if !starts_pattern {
+                        break
+                    }
+
+                    patterns.push(self.pattern_of(false)?)
+                }
+
+                Ok(Pattern::Variant(content, patterns, position))
+            },
+
+            TokenType::Number | TokenType::Str | TokenType::Char | TokenType::Bool => Ok(Pattern::Literal(self.atom()?, position)),
+
+            TokenType::Operator if content == "-" && self.traveler.tokens.get(self.traveler.top + 1).is_some_and(|t| t.token_type == TokenType::Number) => {
+                self.traveler.next();
+
+                match self.atom()? {
+                    Expression::Number(n) => Ok(Pattern::Literal(Expression::Number(-n), position)),
+                    _                     => unreachable!(),
+                }
+            },
+
+            TokenType::Symbol if content == "[" => self.array_pattern(),
+
+            TokenType::Symbol if content == "(" => {
+                let mut patterns = self.block_of(&Self::alternatives_, ("(", ")"))?;
+
+                match patterns.len() {
+                    0 => Err(Response::error(Some(ErrorLocation::new(position, 1)), "expected pattern inside parens".to_string())),
+                    1 => Ok(patterns.pop().unwrap()),
+                    _ => Ok(Pattern::Tuple(patterns, position)),
+                }
+            },
+
+            _ => Err(Response::error(Some(ErrorLocation::new(position, content.len())), format!("expected pattern, found: {:?}", content))),
+        }
+    }
+
+    fn alternatives_(&mut self) -> Result<Option<Pattern>, Response> {
+        self.skip_whitespace_eol();
+
+        if self.traveler.remaining() < 2 {
+            return Ok(None)
+        }
+
+        let pattern = self.alternatives()?;
+        self.skip_whitespace_eol();
+
+        if self.traveler.current_content() == "," {
+            self.traveler.next();
+        }
+
+        Ok(Some(pattern))
+    }
+
+    // `[a, b, ...rest]`, where `...` on its own ignores the rest
+    fn array_pattern(&mut self) -> Result<Pattern, Response> {
+        let position = self.traveler.current().position;
+        self.traveler.next();
+
+        let mut content = Vec::new();
+        let mut rest    = None;
+
+        loop {
+            self.skip_whitespace_eol();
+
+            if self.traveler.remaining() < 2 {
+                return Err(Response::error(Some(ErrorLocation::new(position, 1)), "unclosed \"[\"".to_string()))
+            }
+
+            if self.traveler.current_content() == "]" {
+                break
+            }
+
+            if self.traveler.current_content() == "..." {
+                self.traveler.next();
+
+                rest = Some(if self.traveler.current().token_type == TokenType::Identifier {
+                    let name = self.traveler.current_content();
+                    self.traveler.next();
+
+                    Some(name)
+                } else {
+                    None
+                });
+
+                self.skip_whitespace_eol();
+
+                if self.traveler.current_content() == "," {
+                    self.traveler.next();
+                    self.skip_whitespace_eol();
+                }
+
+                if self.traveler.current_content() != "]" {
+                    return Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, 1)), "the rest of an array pattern has to come last".to_string()))
+                }
+
+                break
+            }
+
+            content.push(self.pattern()?);
+            self.skip_whitespace_eol();
+
+            match self.traveler.current_content().as_str() {
+                "," => { self.traveler.next(); },
+                "]" => (),
+                c   => return Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, c.len())), format!("expected `,` or `]` in array pattern, found: {:?}", c))),
+            }
+        }
+
+        self.traveler.next();
+
+        Ok(Pattern::Array(content, rest, position))
+    }
+
+    // `x = pattern`, or just `x` to bind the field to its own name
+    fn field_pattern_(&mut self) -> Result<Option<(String, Pattern)>, Response> {
+        self.skip_whitespace_eol();
+
+        if self.traveler.current_content() == "," {
+            self.traveler.next();
+            self.skip_whitespace_eol();
+        }
+
+        if self.traveler.remaining() < 2 {
+            return Ok(None)
+        }
+
+        let position = self.traveler.current().position;
+        let name     = self.traveler.current_content();
+
+        if self.traveler.current().token_type != TokenType::Identifier {
+            return Err(Response::error(Some(ErrorLocation::new(position, name.len())), format!("expected field name, found: {:?}", name)))
+        }
+
+        self.traveler.next();
+        self.skip_whitespace();
+
+        let pattern = if self.traveler.current_content() == "=" {
+            self.traveler.next();
+            self.alternatives()?
+        } else {
+            Pattern::Binding(name.clone(), position)
+        };
+
+        self.skip_whitespace_eol();
+
+        if self.traveler.current_content() == "," {
+            self.traveler.next();
+        }
+
+        Ok(Some((name, pattern)))
+    }
+
     fn match_pattern(&mut self) -> Result<MatchPattern, Response> {
+        let position = self.traveler.current().position;
+
         self.traveler.next();
 
         self.skip_whitespace();
@@ -209,25 +584,25 @@ impl Parser {
 
         self.skip_whitespace();
 
-        if self.traveler.current_content() == "{" {
-            let arms = self.block_of(&Self::match_arm, ("{", "}"))?;
-            
-            println!("here m: {:?}", self.traveler.current_content());
+        if self.traveler.current_content() != "{" {
+            return Err(Response::error(Some(ErrorLocation::new(position, 5)), format!("expected `{{` with the arms of the match, found: {:?}", self.traveler.current_content())))
+        }
 
-            Ok(MatchPattern {
-                matching,
-                arms,
-            })
+        let arms = self.block_of(&Self::match_arm, ("{", "}"))?;
 
-        } else {
-            panic!("pattern: {:?} : {:#?}", self.traveler.current().token_type, self.traveler.current_content())
-        }
+        Ok(MatchPattern {
+            matching,
+            arms,
+            position,
+        })
     }
 
-    fn block_of<B>(&mut self, match_with: &Fn(&mut Self) -> Result<Option<B>, Response>, delimeters: (&str, &str)) -> Result<Vec<B>, Response> {
+    fn block_of<B>(&mut self, match_with: &dyn Fn(&mut Self) -> Result<Option<B>, Response>, delimeters: (&str, &str)) -> Result<Vec<B>, Response> {
         let backup_inside = self.inside.clone();
         self.inside       = delimeters.0.to_owned();
 
+        let start = self.traveler.current().position;
+
         if self.traveler.current_content() == delimeters.0 {
             self.traveler.next();
         }
@@ -236,6 +611,11 @@ impl Parser {
         let mut nested = 1;
 
         while nested != 0 {
+            if self.traveler.remaining() < 2 {
+                self.inside = backup_inside;
+                return Err(Response::error(Some(ErrorLocation::new(start, 1)), format!("unclosed {:?}", delimeters.0)))
+            }
+
             if self.traveler.current_content() == delimeters.1 {
                 nested -= 1
             } else if self.traveler.current_content() == delimeters.0 {
@@ -252,40 +632,96 @@ impl Parser {
 
         self.traveler.next();
 
+        if stack.is_empty() {
+            self.inside = backup_inside;
+            return Ok(Vec::new())
+        }
+
         let mut parser  = Parser::new(Traveler::new(stack));
         parser.inside   = self.inside.clone();
 
         let mut stack_b = Vec::new();
-        
-        while let Some(n) = match_with(&mut parser)? {
-            stack_b.push(n)
+
+        // errors inside the block are kept, so the rest of it still gets parsed
+        loop {
+            let top = parser.traveler.top;
+
+            match match_with(&mut parser) {
+                Ok(Some(n)) => stack_b.push(n),
+                Ok(None)    => break,
+                Err(e)      => {
+                    parser.errors.push(e);
+                    parser.synchronize();
+
+                    if parser.traveler.top == top || parser.traveler.remaining() < 2 {
+                        break
+                    }
+                },
+            }
         }
 
+        self.errors.append(&mut parser.errors);
         self.inside = backup_inside;
 
         Ok(stack_b)
     }
 
     fn expression(&mut self) -> Result<Expression, Response> {
-        let expr = self.atom()?;
+        self.expression_bp(0)
+    }
 
-        if expr == Expression::EOF {
-            return Ok(expr)
-        }
-        
-        let backup = self.traveler.top;
-        self.skip_whitespace();
+    // precedence climbing, only taking operators that bind at least as tight as `min`
+    fn expression_bp(&mut self, min: u8) -> Result<Expression, Response> {
+        let mut left = self.atom()?;
 
-        if self.traveler.current().token_type == TokenType::Operator {
-            return self.operation(expr)
+        if left == Expression::EOF {
+            return Ok(left)
         }
 
-        self.traveler.top = backup;
+        loop {
+            let backup = self.traveler.top;
+            self.skip_whitespace();
+
+            if self.traveler.current().token_type != TokenType::Operator {
+                self.traveler.top = backup;
+                break
+            }
+
+            let op         = self.binary_operator()?;
+            let precedence = op.precedence();
+
+            if precedence < min {
+                self.traveler.top = backup;
+                break
+            }
+
+            let position = self.traveler.current().position;
+            self.traveler.next();
+
+            // a left associative operator leaves its own kind for the loop, a right associative one takes it
+            let right = match op.associativity() {
+                Associativity::Left  => self.expression_bp(precedence + 1)?,
+                Associativity::Right => self.expression_bp(precedence)?,
+            };
 
-        Ok(expr)
+            if right == Expression::EOF {
+                return Err(Response::error(Some(ErrorLocation::new(position, op.symbol().len())), format!("expected operand after: {}", op.symbol())))
+            }
+
+            left = Expression::BinaryOp(BinaryOp {
+                left:  Rc::new(left),
+                op,
+                right: Rc::new(right),
+                position,
+            })
+        }
+
+        Ok(left)
     }
 
-    fn call(self: &mut Self, callee: Rc<Expression>) -> Result<Call, Response> {
+    fn call(&mut self, callee: Rc<Expression>) -> Result<Call, Response> {
+        let position = callee.position().unwrap_or(self.traveler.current().position);
+
         let mut args = Vec::new();
 
         while self.traveler.remaining() > 1 {
@@ -296,10 +732,13 @@ impl Parser {
         Ok(Call {
             callee,
             args,
+            position,
         })
     }
     
     fn initialization(&mut self) -> Result<Initialization, Response> {
+        let position = self.traveler.current().position;
+
         self.traveler.expect_content("new")?;
         self.traveler.next();
         
@@ -313,6 +752,7 @@ impl Parser {
         Ok(Initialization {
             id,
             values,
+            position,
         })
     }
 
@@ -328,7 +768,7 @@ impl Parser {
 
                     let position = self.traveler.current().position;
 
-                    let a = self.try_index(Expression::Index(Index {id: Rc::new(a), index, position}), call)?;
+                    let a = self.try_index(Expression::Index(Index {id: Rc::new(a), index, field: true, position}), call)?;
 
                     if call {
                         self.skip_whitespace();
@@ -350,7 +790,7 @@ impl Parser {
 
                     let position = self.traveler.current().position;
 
-                    let a = self.try_index(Expression::Index(Index {id: Rc::new(a), index, position}), call)?;
+                    let a = self.try_index(Expression::Index(Index {id: Rc::new(a), index, field: false, position}), call)?;
 
                     if call {
                         self.skip_whitespace();
@@ -373,13 +813,18 @@ impl Parser {
     }
 
     fn try_call(&mut self, a: Expression) -> Result<Expression, Response> {
+        if self.args {
+            return Ok(a)
+        }
+
         match self.traveler.current().token_type {
-            TokenType::Int        |
-            TokenType::Identifier |
-            TokenType::Bool       |
-            TokenType::Str        |
-            TokenType::Char       |
-            TokenType::Symbol     => {
+            TokenType::Number       |
+            TokenType::Identifier   |
+            TokenType::Bool         |
+            TokenType::Str          |
+            TokenType::Interpolated |
+            TokenType::Char         |
+            TokenType::Symbol       => {
                 let backup = self.traveler.top;
 
                 if self.traveler.current().token_type == TokenType::Symbol {
@@ -412,11 +857,21 @@ impl Parser {
                     nested = 1
                 }
 
-                while (self.traveler.current().token_type != TokenType::Operator && self.traveler.current_content() != "," && self.traveler.current_content() != "]") || nested != 0 {
+                // how deep in brackets the args are, as a comma or operator outside of them ends the call
+                let mut depth = 0;
+
+                while (self.traveler.current().token_type != TokenType::Operator && self.traveler.current_content() != "," && self.traveler.current_content() != "]" && self.traveler.current_content() != "->") || nested != 0 || depth != 0 {
                     if self.traveler.current_content() == "\n" || self.traveler.remaining() < 2 {
                         break
                     }
 
+                    match self.traveler.current_content().as_str() {
+                        "," if depth == 0 => break,
+                        "(" | "[" | "{"   => depth += 1,
+                        ")" | "]" | "}"   => depth -= 1,
+                        _                 => (),
+                    }
+
                     if self.traveler.current_content() == "(" {
                         nested += 1
                     } else if self.traveler.current_content() == ")" {
@@ -428,8 +883,12 @@ impl Parser {
                 }
 
                 let mut parser = Parser::new(Traveler::new(stack));
+                parser.args    = true;
+
+                let call = Self::call(&mut parser, Rc::new(a));
+                self.errors.append(&mut parser.errors);
 
-                Ok(Expression::Call(Self::call(&mut parser, Rc::new(a))?))
+                Ok(Expression::Call(call?))
             }
             _ => Ok(a)
         }
@@ -443,8 +902,11 @@ impl Parser {
         }
 
         match self.traveler.current().token_type {
-            TokenType::Int => {
-                let a = Expression::Number(self.traveler.current_content().parse::<f64>().unwrap());
+            TokenType::Number => {
+                let content  = self.traveler.current_content();
+                let position = self.traveler.current().position;
+
+                let a = Expression::Number(number(&content).map_err(|message| Response::error(Some(ErrorLocation::new(position, content.len())), message))?);
                 self.traveler.next();
                 Ok(a)
             }
@@ -461,12 +923,24 @@ impl Parser {
                 Ok(a)
             }
 
+            TokenType::Interpolated => {
+                let token = self.traveler.current().clone();
+                self.traveler.next();
+
+                self.interpolation(&token)
+            }
+
             TokenType::Char => {
                 let a = Expression::Char(self.traveler.current_content().clone().remove(0));
                 self.traveler.next();
                 Ok(a)
             }
 
+            TokenType::Identifier if self.traveler.current_content() == "nil" => {
+                self.traveler.next();
+                Ok(Expression::Nil)
+            },
+
             TokenType::Identifier => {
                 let a = Expression::Identifier(self.traveler.current_content().clone(), self.traveler.current().position);
                 self.traveler.next();
@@ -497,15 +971,35 @@ impl Parser {
 
             TokenType::Symbol => match self.traveler.current_content().as_str() {
                 "(" => {
-                    let a = self.block_of(&Self::expression_, ("(", ")"))?.get(0).unwrap().clone();
+                    let position = self.traveler.current().position;
+
+                    let mut content = self.block_of(&Self::element_, ("(", ")"))?;
+
+                    let a = match content.len() {
+                        0 => return Err(Response::error(Some(ErrorLocation::new(position, 1)), "expected expression inside parens".to_string())),
+                        1 => content.pop().unwrap(),
+                        _ => Expression::Tuple(content, position),
+                    };
+
                     self.skip_whitespace();
 
                     let a = self.try_call(a)?;
                     self.try_index(a, true)
                 },
                 "[" => {
-                    let a = self.try_list(("[", "]"))?.unwrap();
-                    self.try_index(Expression::Array(a), true)
+                    let position = self.traveler.current().position;
+
+                    let a = match self.try_list(("[", "]"))? {
+                        Some(a) => a,
+
+                        // without the trailing comma, the brackets aren't an array
+                        None => return Err(Response::error(Some(ErrorLocation::new(position, 1)), match self.traveler.current_content().as_str() {
+                            "]" => "an array needs at least one element".to_owned(),
+                            _   => "an array needs a trailing comma, like `[a,]`".to_owned(),
+                        })),
+                    };
+
+                    self.try_index(Expression::Array(a, position), true)
                 }
                 "{" => {
                     let a = Expression::Block(self.block_of(&Self::statement_, ("{", "}"))?);
@@ -514,11 +1008,95 @@ impl Parser {
                 ref c => Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, c.len())), format!("bad symbol: {:?}", c))),
             },
 
+            TokenType::Operator => match UnaryOperand::from_str(&self.traveler.current_content()) {
+                Some(op) => {
+                    let position = self.traveler.current().position;
+                    self.traveler.next();
+
+                    let expr = self.expression_bp(UnaryOperand::PRECEDENCE)?;
+
+                    if expr == Expression::EOF {
+                        return Err(Response::error(Some(ErrorLocation::new(position, op.symbol().len())), format!("expected operand after: {}", op.symbol())))
+                    }
+
+                    Ok(Expression::UnaryOp(UnaryOp {
+                        op,
+                        expr: Rc::new(expr),
+                        position,
+                    }))
+                },
+
+                None => Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, self.traveler.current_content().len())), format!("expected operand, found operator: {}", self.traveler.current_content()))),
+            },
+
             _ => Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, self.traveler.current_content().len())), format!("unexpected: {:?}", self.traveler.current_content()))),
         }
     }
 
+    // `"a {b} c"` as `"a " ++ b ++ " c"`, with every `{...}` parsed as an expression of its own
+    fn interpolation(&mut self, token: &Token) -> Result<Expression, Response> {
+        let mut parts = Vec::new();
+
+        for segment in segments(&token.content, token.position, true)? {
+            match segment {
+                Segment::Text(text) => parts.push((Expression::Str(text), token.position)),
+
+                Segment::Code(code, position) => {
+                    let tokens     = make_lexer(code.chars().collect()).starting_at(position).tokenize()?;
+                    let mut parser = Parser::new(Traveler::new(tokens));
+
+                    let expression = parser.expression()?;
+                    parser.skip_whitespace_eol();
+
+                    if parser.traveler.remaining() > 1 {
+                        let unexpected = parser.traveler.current();
+                        return Err(Response::error(Some(ErrorLocation::new(unexpected.position, unexpected.content.len())), format!("unexpected {:?} in interpolation", unexpected.content)))
+                    }
+
+                    parts.push((expression, position))
+                },
+            }
+        }
+
+        let mut parts = parts.into_iter();
+
+        let mut interpolation = match parts.next() {
+            Some((Expression::Str(text), _)) => Expression::Str(text),
+            Some((expression, position))     => Expression::BinaryOp(BinaryOp {
+                left:  Rc::new(Expression::Str(String::new())),
+                op:    Operand::Concat,
+                right: Rc::new(expression),
+                position,
+            }),
+
+            None => Expression::Str(String::new()),
+        };
+
+        for (expression, position) in parts {
+            interpolation = Expression::BinaryOp(BinaryOp {
+                left:  Rc::new(interpolation),
+                op:    Operand::Concat,
+                right: Rc::new(expression),
+                position,
+            })
+        }
+
+        Ok(interpolation)
+    }
+
     fn try_list(&mut self, delimeters: (&str, &str)) -> Result<Option<Vec<Expression>>, Response> {
+        let start = self.traveler.current().position;
+
+        // the elements are back to calling, even among args
+        let args  = mem::replace(&mut self.args, false);
+        let list  = self.list(delimeters, start);
+        self.args = args;
+
+        list
+    }
+
+    fn list(&mut self, delimeters: (&str, &str), start: TokenPosition) -> Result<Option<Vec<Expression>>, Response> {
+
         if self.traveler.current_content() == delimeters.0 {
             self.traveler.next();
         }
@@ -533,6 +1111,10 @@ impl Parser {
         let checkpoint = self.traveler.top;
 
         while nested != 0 {
+            if self.traveler.remaining() < 2 {
+                return Err(Response::error(Some(ErrorLocation::new(start, 1)), format!("unclosed {:?}", delimeters.0)))
+            }
+
             if self.traveler.current_content() == delimeters.1 {
                 nested -= 1
             } else if self.traveler.current_content() == delimeters.0 {
@@ -581,7 +1163,9 @@ impl Parser {
         }
     }
     
-    fn assignment(&mut self, left: Rc<Expression>) -> Result<Assignment, Response> {        
+    fn assignment(&mut self, left: Rc<Expression>) -> Result<Assignment, Response> {
+        let position = left.position().unwrap_or(self.traveler.current().position);
+
         self.traveler.next();
         self.skip_whitespace();
 
@@ -590,13 +1174,11 @@ impl Parser {
         Ok(Assignment {
             left,
             right,
-            position: self.traveler.current().position,
+            position,
         })
     }
 
-    fn definition(&mut self, name: String) -> Result<Definition, Response> {
-        let position = self.traveler.current().position;
-
+    fn definition(&mut self, name: String, position: TokenPosition) -> Result<Definition, Response> {
         self.skip_whitespace();
         
         self.traveler.expect_content(":")?;
@@ -613,30 +1195,103 @@ impl Parser {
             self.skip_whitespace();
         }
 
-        if self.traveler.current_content() == "=" {
-            self.traveler.next();
+        if self.traveler.current_content() == "=" {
+            self.traveler.next();
+
+            let right = Some(Rc::new(self.expression()?));
+            
+            Ok(Definition {
+                t, name, right, position,
+            })
+
+        } else {
+            Ok(Definition {
+                t, name, right: None, position,
+            })
+        }
+    }
+
+    // `(x, y) := a` or `{x, y} := a`, binding what the pattern on the left takes out of the right
+    fn destructure(&mut self) -> Result<Destructure, Response> {
+        let position = self.traveler.current().position;
+
+        let pattern = if self.traveler.current_content() == "{" {
+            Pattern::Struct(String::new(), self.block_of(&Self::field_pattern_, ("{", "}"))?, position)
+        } else {
+            self.pattern_of(false)?
+        };
+
+        self.skip_whitespace();
+        
+        self.traveler.expect_content(":")?;
+        self.traveler.next();
+
+        self.skip_whitespace();
+
+        let t = if self.traveler.current_content() == "=" {
+            None
+        } else {
+            let t = self.get_type()?;
+            self.skip_whitespace();
+
+            Some(t)
+        };
+
+        self.traveler.expect_content("=")?;
+        self.traveler.next();
+
+        let right = Rc::new(self.expression()?);
+
+        Ok(Destructure {
+            t, pattern, right, position,
+        })
+    }
+
+    // what comes right after the brackets starting here, telling `(x, y) := a` apart from an expression
+    fn after_brackets(&self) -> String {
+        let tokens    = &self.traveler.tokens;
+        let mut i     = self.traveler.top;
+        let mut depth = 0;
+
+        while i < tokens.len() {
+            match tokens[i].content.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        break
+                    }
+                },
+                _ => (),
+            }
+
+            i += 1
+        }
 
-            let right = Some(Rc::new(self.expression()?));
-            
-            Ok(Definition {
-                t, name, right, position,
-            })
+        i += 1;
 
-        } else {
-            Ok(Definition {
-                t, name, right: None, position,
-            })
+        while tokens.get(i).is_some_and(|t| t.token_type == TokenType::Whitespace) {
+            i += 1
         }
+
+        tokens.get(i).map_or(String::new(), |t| t.content.clone())
     }
 
     fn function_match(&mut self, named: bool) -> Result<FunctionMatch, Response> {
+        let position = self.traveler.current().position;
+
         self.traveler.next();
         self.skip_whitespace();
         
+        let mut generics = Vec::new();
+
         let name = if named {
             let position = self.traveler.current().position;
             let a        = self.traveler.expect(TokenType::Identifier)?;
             self.traveler.next();
+
+            generics = self.generics()?;
             self.skip_whitespace();
 
             Some(self.try_index(Expression::Identifier(a, position), false)?)
@@ -659,18 +1314,34 @@ impl Parser {
         Ok(FunctionMatch {
             t,
             name,
+            generics,
             arms,
+            position,
         })
     }
 
-    fn expression_(self: &mut Self) -> Result<Option<Expression>, Response> {
+    fn expression_(&mut self) -> Result<Option<Expression>, Response> {
         match self.expression()? {
             Expression::EOF => Ok(None),
             c               => Ok(Some(c)),
         }
     }
 
-    fn statement_(self: &mut Self) -> Result<Option<Statement>, Response> {
+    // an expression in parens, along with the comma after it when it's one of a tuple
+    fn element_(&mut self) -> Result<Option<Expression>, Response> {
+        self.skip_whitespace_eol();
+
+        let a = self.expression_()?;
+        self.skip_whitespace_eol();
+
+        if self.traveler.current_content() == "," {
+            self.traveler.next();
+        }
+
+        Ok(a)
+    }
+
+    fn statement_(&mut self) -> Result<Option<Statement>, Response> {
         match self.statement()? {
             Statement::Expression(e) => match *e {
                 Expression::EOF => Ok(None),
@@ -680,15 +1351,14 @@ impl Parser {
         }
     }
 
-    fn assignment_(self: &mut Self) -> Result<Option<Assignment>, Response> {
+    fn assignment_(&mut self) -> Result<Option<Assignment>, Response> {
         self.skip_whitespace_eol();
 
         if self.traveler.remaining() > 2 {
-            let position = self.traveler.current().position;
-            
             self.skip_whitespace_eol();
 
-            let left = Rc::new(Expression::Identifier(self.traveler.expect(TokenType::Identifier)?, position));
+            let position = self.traveler.current().position;
+            let left     = Rc::new(Expression::Identifier(self.traveler.expect(TokenType::Identifier)?, position));
             self.traveler.next();
             self.skip_whitespace();
             
@@ -701,7 +1371,7 @@ impl Parser {
             Ok(Some(Assignment {
                 left,
                 right,
-                position: self.traveler.current().position,
+                position,
             }))
         } else {
             Ok(None)
@@ -709,13 +1379,19 @@ impl Parser {
     }
 
     fn function(&mut self, named: bool) -> Result<Fun, Response> {
+        let position = self.traveler.current().position;
+
         self.traveler.next();
         self.skip_whitespace();
         
+        let mut generics = Vec::new();
+
         let name = if named {
             let position = self.traveler.current().position;
             let a        = self.traveler.expect(TokenType::Identifier)?;
             self.traveler.next();
+
+            generics = self.generics()?;
             self.skip_whitespace();
 
             Some(self.try_index(Expression::Identifier(a, position), false)?)
@@ -742,7 +1418,7 @@ impl Parser {
                 "{" => break,
 
                 _ => {
-                    params.push(self.type_definition()?);
+                    params.push(self.parameter()?);
                     self.skip_whitespace()
                 }
             }
@@ -753,12 +1429,14 @@ impl Parser {
         Ok(Fun {
             t,
             name,
+            generics,
             params,
             body,
+            position,
         })
     }
 
-    fn type_definition(self: &mut Self) -> Result<TypeDefinition, Response> {
+    fn type_definition(&mut self) -> Result<TypeDefinition, Response> {
         self.skip_whitespace_eol();
         let name = self.traveler.expect(TokenType::Identifier)?.to_owned();
         self.traveler.next();
@@ -778,7 +1456,33 @@ impl Parser {
         })
     }
 
-    fn type_definition_(self: &mut Self) -> Result<Option<TypeDefinition>, Response> {
+    // like a type definition, but the type can be left for the checker to infer
+    fn parameter(&mut self) -> Result<TypeDefinition, Response> {
+        self.skip_whitespace_eol();
+        let name = self.traveler.expect(TokenType::Identifier)?.to_owned();
+        self.traveler.next();
+
+        self.skip_whitespace();
+
+        if self.traveler.current_content() != ":" {
+            return Ok(TypeDefinition {
+                name,
+                t: Type::Undefined,
+            })
+        }
+
+        self.traveler.next();
+        self.skip_whitespace();
+
+        let t = self.get_type()?;
+
+        Ok(TypeDefinition {
+            name,
+            t,
+        })
+    }
+
+    fn type_definition_(&mut self) -> Result<Option<TypeDefinition>, Response> {
         if self.traveler.remaining() > 2 {
             Ok(Some(self.type_definition()?))
         } else {
@@ -790,8 +1494,11 @@ impl Parser {
         self.traveler.next();
         self.skip_whitespace();
         
-        let name = self.traveler.current_content().clone();
+        let position = self.traveler.current().position;
+        let name     = self.traveler.current_content().clone();
         self.traveler.next();
+
+        let generics = self.generics()?;
         self.skip_whitespace();
         
         self.traveler.expect_content("{")?;
@@ -803,11 +1510,79 @@ impl Parser {
         
         Ok(Struct {
             name,
+            generics,
             body,
+            position,
+        })
+    }
+
+    // `data Shape = Circle number | Rect number number`, the variants maybe each on a line of their own
+    fn data(&mut self) -> Result<Data, Response> {
+        self.traveler.next();
+        self.skip_whitespace();
+
+        let position = self.traveler.current().position;
+        let name     = self.traveler.expect(TokenType::Identifier)?;
+        self.traveler.next();
+
+        let generics = self.generics()?;
+        self.skip_whitespace();
+
+        self.traveler.expect_content("=")?;
+        self.traveler.next();
+        self.skip_whitespace_eol();
+
+        if self.traveler.current_content() == "|" {
+            self.traveler.next();
+        }
+
+        let mut variants = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let position = self.traveler.current().position;
+            let name     = self.traveler.expect(TokenType::Identifier)?;
+            self.traveler.next();
+
+            let mut fields = Vec::new();
+
+            loop {
+                self.skip_whitespace();
+
+                if self.traveler.remaining() < 2 || self.traveler.current_content() == "|" || self.traveler.current_content().contains('\n') {
+                    break
+                }
+
+                fields.push(self.get_type()?)
+            }
+
+            variants.push(Variant {
+                name,
+                fields,
+                position,
+            });
+
+            let backup = self.traveler.top;
+            self.skip_whitespace_eol();
+
+            if self.traveler.current_content() != "|" {
+                self.traveler.top = backup;
+                break
+            }
+
+            self.traveler.next();
+        }
+
+        Ok(Data {
+            name,
+            generics,
+            variants,
+            position,
         })
     }
 
-    fn function_(self: &mut Self) -> Result<Option<Function>, Response> {
+    fn function_(&mut self) -> Result<Option<Function>, Response> {
         self.skip_whitespace_eol();
         match self.traveler.current_content().as_str() {
             "fun"      => Ok(Some(Function::Fun(self.function(true)?))),
@@ -820,6 +1595,7 @@ impl Parser {
         self.traveler.next();
         self.skip_whitespace();
         
+        let position  = self.traveler.current().position;
         let structure = self.traveler.expect(TokenType::Identifier)?;
         self.traveler.next();
         
@@ -830,8 +1606,7 @@ impl Parser {
 
             self.skip_whitespace();
 
-            let interface = self.traveler.expect(TokenType::Identifier)?;
-            self.traveler.next();
+            let interface = self.get_type()?;
             self.skip_whitespace();
 
             Some(interface)
@@ -845,6 +1620,7 @@ impl Parser {
             structure,
             interface,
             body,
+            position,
         })
     }
     
@@ -904,6 +1680,8 @@ impl Parser {
     }
     
     fn if_pattern(&mut self) -> Result<If, Response> {
+        let position = self.traveler.current().position;
+
         self.traveler.next();
 
         self.skip_whitespace();
@@ -980,6 +1758,7 @@ impl Parser {
                 condition,
                 body,
                 elses: Some(elses),
+                position,
             })
             
         } else {
@@ -987,6 +1766,7 @@ impl Parser {
                 condition,
                 body,
                 elses: None,
+                position,
             })
         }
     }
@@ -1010,7 +1790,51 @@ impl Parser {
         })
     }
 
-    fn function_type_def_(self: &mut Self) -> Result<Option<TypeDefinition>, Response> {
+    fn for_loop(&mut self) -> Result<For, Response> {
+        let position = self.traveler.current().position;
+
+        self.traveler.next();
+        self.skip_whitespace();
+
+        let mut name = self.traveler.expect(TokenType::Identifier)?;
+        self.traveler.next();
+        self.skip_whitespace();
+
+        // with two names, the first one is where the second is
+        let index = if self.traveler.current_content() == "," {
+            self.traveler.next();
+            self.skip_whitespace();
+
+            let index = mem::replace(&mut name, self.traveler.expect(TokenType::Identifier)?);
+            self.traveler.next();
+            self.skip_whitespace();
+
+            Some(index)
+        } else {
+            None
+        };
+
+        self.traveler.expect_content("in")?;
+        self.traveler.next();
+        self.skip_whitespace();
+
+        let iterable = self.expression()?;
+
+        self.skip_whitespace();
+        self.traveler.expect_content("{")?;
+
+        let body = self.block_of(&Self::statement_, ("{", "}"))?;
+
+        Ok(For {
+            index,
+            name,
+            iterable,
+            body,
+            position,
+        })
+    }
+
+    fn function_type_def_(&mut self) -> Result<Option<TypeDefinition>, Response> {
         if self.traveler.remaining() > 2 {
             let position = self.traveler.current().position;
             let d = self.type_definition()?;
@@ -1030,16 +1854,20 @@ impl Parser {
         self.traveler.next();
         self.skip_whitespace();
 
-        let name = self.traveler.expect(TokenType::Identifier)?;
+        let position = self.traveler.current().position;
+        let name     = self.traveler.expect(TokenType::Identifier)?;
         self.traveler.next();
-        
+
+        let generics = self.generics()?;
         self.skip_whitespace();
         
         let types = self.block_of(&Self::function_type_def_, ("{", "}"))?;
         
         Ok(Interface {
             name,
+            generics,
             types,
+            position,
         })
     }
 
@@ -1049,13 +1877,13 @@ impl Parser {
         match self.traveler.current().token_type {
             TokenType::Identifier => {
                 let backup = self.traveler.top;
-                
+
+                let position = self.traveler.current().position;
+
                 let a = self.traveler.current_content().clone();
                 self.traveler.next();
                 self.skip_whitespace();
                 
-                let position = self.traveler.current().position;
-                
                 let index_flag;
 
                 let a2 = if self.traveler.current_content() == "." || self.traveler.current_content() == "[" {
@@ -1065,7 +1893,7 @@ impl Parser {
                     b
                 } else {
                     index_flag = false;
-                    Expression::Identifier(a.clone(), self.traveler.current().position)
+                    Expression::Identifier(a.clone(), position)
                 };
 
                 let b = if self.traveler.current_content() == "=" {
@@ -1083,7 +1911,7 @@ impl Parser {
                     c
 
                 } else if self.traveler.current_content() == ":" && !index_flag {
-                    let c = self.definition(a)?;
+                    let c = self.definition(a, position)?;
                     
                     if self.traveler.remaining() > 1 {
                         if !self.traveler.current_content().chars().any(|x| x == '\n') {
@@ -1108,11 +1936,28 @@ impl Parser {
 
                     self.skip_whitespace_eol();
 
+                    if self.traveler.current_content() == "(" || self.traveler.current_content() == "{" {
+                        let mut destructure = self.destructure()?;
+                        destructure.t       = Some(Type::Mut(destructure.t.map(Rc::new)));
+
+                        if self.traveler.remaining() > 1 {
+                            if !self.traveler.current_content().chars().any(|x| x == '\n') {
+                                return Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, self.traveler.current_content().len())), format!("expected newline, found: {:?}", self.traveler.current_content())))
+                            } else {
+                                self.traveler.next();
+                            }
+                        }
+
+                        return Ok(Statement::Destructure(destructure))
+                    }
+
+                    let position = self.traveler.current().position;
+
                     let a = self.traveler.current_content().clone();
                     self.traveler.next();
                     self.skip_whitespace();
 
-                    let mut def = self.definition(a)?;
+                    let mut def = self.definition(a, position)?;
 
                     if let Some(t) = def.t {
                         def.t = Some(Type::Mut(Some(Rc::new(t))))
@@ -1134,6 +1979,7 @@ impl Parser {
                 "function"  => Ok(Statement::FunctionMatch(self.function_match(true)?)),
                 "fun"       => Ok(Statement::Fun(self.function(true)?)),
                 "struct"    => Ok(Statement::Struct(self.structure()?)),
+                "data"      => Ok(Statement::Data(self.data()?)),
                 "if"        => Ok(Statement::If(self.if_pattern()?)),
                 "unless"    => Ok(Statement::Unless(Unless { base: self.if_pattern()? } )),
                 "match"     => Ok(Statement::MatchPattern(self.match_pattern()?)),
@@ -1141,6 +1987,18 @@ impl Parser {
                 "implement" => Ok(Statement::Implementation(self.implementation()?)),
                 "import"    => Ok(Statement::Import(self.import()?)),
                 "while"     => Ok(Statement::While(self.while_loop()?)),
+                "for"       => Ok(Statement::For(self.for_loop()?)),
+
+                c @ "break" | c @ "continue" => {
+                    let position = self.traveler.current().position;
+                    self.traveler.next();
+
+                    if c == "break" {
+                        Ok(Statement::Break(position))
+                    } else {
+                        Ok(Statement::Continue(position))
+                    }
+                },
                 "extern"    => {
                     self.traveler.next();
                     self.skip_whitespace();
@@ -1176,6 +2034,29 @@ impl Parser {
                 _ => Ok(Statement::Expression(Rc::new(self.expression()?))),
             },
 
+            TokenType::Symbol if self.traveler.current_content() == "(" || self.traveler.current_content() == "{" => {
+                let b = match self.after_brackets().as_str() {
+                    ":" => Statement::Destructure(self.destructure()?),
+                    "=" if self.traveler.current_content() == "(" => {
+                        let left = Rc::new(self.atom()?);
+                        self.skip_whitespace();
+
+                        Statement::Assignment(self.assignment(left)?)
+                    },
+                    _ => return Ok(Statement::Expression(Rc::new(self.expression()?))),
+                };
+
+                if self.traveler.remaining() > 1 {
+                    if !self.traveler.current_content().chars().any(|x| x == '\n') {
+                        return Err(Response::error(Some(ErrorLocation::new(self.traveler.current().position, self.traveler.current_content().len())), format!("expected newline, found: {:?}", self.traveler.current_content())))
+                    } else {
+                        self.traveler.next();
+                    }
+                }
+
+                Ok(b)
+            },
+
             TokenType::EOL => {
                 if self.traveler.remaining() > 1 {
                     self.traveler.next();                    
@@ -1189,77 +2070,229 @@ impl Parser {
         }
     }
     
-    fn operation(&mut self, expression: Expression) -> Result<Expression, Response> {
-        let mut ex_stack = vec![expression];
-        let mut op_stack: Vec<(Operand, u8)> = Vec::new();
-        
+    fn binary_operator(&self) -> Result<Operand, Response> {
+        let content = self.traveler.current_content();
 
-        op_stack.push(Operand::from_str(&self.traveler.current_content()).unwrap());
-        self.traveler.next();
+        Operand::from_str(&content).ok_or_else(|| Response::error(Some(ErrorLocation::new(self.traveler.current().position, content.len())), format!("not a binary operator: {}", content)))
+    }
+}
 
-        ex_stack.push(self.atom()?);
+// the value of a number literal as the lexer found it
+fn number(literal: &str) -> Result<f64, String> {
+    let malformed = || Err(format!("malformed number literal: {}", literal));
 
-        let mut done = false;
+    let (radix, digits) = match literal.get(.. 2) {
+        Some("0x") => (16, &literal[2 ..]),
+        Some("0o") => (8, &literal[2 ..]),
+        Some("0b") => (2, &literal[2 ..]),
+        _          => (10, literal),
+    };
 
-        while ex_stack.len() > 1 {
-            if !done {
-                self.skip_whitespace();
-                if self.traveler.current().token_type != TokenType::Operator {
-                    done = true;
-                    continue
-                }
+    // underscores only go between digits
+    let chars: Vec<char> = digits.chars().collect();
 
-                let (op, precedence) = Operand::from_str(&self.traveler.current_content()).unwrap();
-                self.traveler.next();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '_' {
+            let digit = |c: Option<&char>| c.is_some_and(|c| c.is_digit(radix));
 
-                if precedence >= op_stack.last().unwrap().1 {
-                    let left  = ex_stack.pop().unwrap();
-                    let right = ex_stack.pop().unwrap();
-
-                    ex_stack.push(
-                        Expression::BinaryOp(
-                            BinaryOp {
-                                right: Rc::new(left),
-                                op:    op_stack.pop().unwrap().0,
-                                left:  Rc::new(right),
-                                position: self.traveler.current().position
-                            }
-                        )
-                    );
+            if i == 0 || !digit(chars.get(i - 1)) || !digit(chars.get(i + 1)) {
+                return malformed()
+            }
+        }
+    }
 
-                    let term = self.atom()?;
+    let digits = digits.replace('_', "");
 
-                    ex_stack.push(term);
-                    op_stack.push((op, precedence));
+    let value = if radix == 10 {
+        if digits.chars().any(|c| c.is_alphabetic() && c != 'e' && c != 'E') {
+            return malformed()
+        }
 
-                    continue
-                }
-                
-                let term = self.atom()?;
+        match digits.parse::<f64>() {
+            Ok(value) => value,
+            Err(_)    => return malformed(),
+        }
+    } else {
+        if digits.is_empty() {
+            return malformed()
+        }
 
-                ex_stack.push(term);
-                op_stack.push((op, precedence));
+        let mut value = 0.0;
+
+        for c in digits.chars() {
+            match c.to_digit(radix) {
+                Some(digit) => value = value * f64::from(radix) + f64::from(digit),
+                None        => return malformed(),
             }
+        }
 
-            let left  = ex_stack.pop().unwrap();
-            let right = ex_stack.pop().unwrap();
+        value
+    };
 
-            ex_stack.push(
-                Expression::BinaryOp(
-                    BinaryOp {
-                        right: Rc::new(left),
-                        op:    op_stack.pop().unwrap().0,
-                        left:  Rc::new(right),
-                        position: self.traveler.current().position,
-                    }
-                )
-            );
-            
-            if self.traveler.current_content() == "\n" {
-                break
+    if value.is_infinite() {
+        Err(format!("number literal doesn't fit in a float: {}", literal))
+    } else {
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Operands = [(Vec<UnaryOperand>, String)];
+
+    // xorshift, so the cases are the same on every run
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn parse(source: &str) -> Expression {
+        let tokens = make_lexer(source.chars().collect()).tokenize().unwrap_or_else(|_| panic!("failed to lex: {}", source));
+        let ast    = Parser::new(Traveler::new(tokens)).parse().unwrap_or_else(|_| panic!("failed to parse: {}", source));
+
+        match ast.as_slice() {
+            [Statement::Expression(e)] => (**e).clone(),
+            _                          => panic!("expected a single expression: {}", source),
+        }
+    }
+
+    fn parenthesise(e: &Expression) -> String {
+        match *e {
+            Expression::BinaryOp(ref op) => format!("({} {} {})", parenthesise(&op.left), op.op.symbol(), parenthesise(&op.right)),
+            Expression::UnaryOp(ref op)  => format!("({} {})", op.op.symbol(), parenthesise(&op.expr)),
+            Expression::Identifier(ref name, _) => name.clone(),
+            Expression::Number(n)        => n.to_string(),
+            ref e                        => panic!("unexpected in operation: {:?}", e),
+        }
+    }
+
+    fn flat(operands: &Operands, ops: &[Operand]) -> String {
+        let mut source = String::new();
+
+        for (i, (prefixes, atom)) in operands.iter().enumerate() {
+            if i > 0 {
+                source.push_str(&format!(" {} ", ops[i - 1].symbol()))
+            }
+
+            for prefix in prefixes {
+                source.push_str(prefix.symbol());
+
+                if *prefix == UnaryOperand::Not {
+                    source.push(' ')
+                }
             }
+
+            source.push_str(atom)
+        }
+
+        source
+    }
+
+    // splits at the loosest operator, the last one when it's left associative and the first one otherwise
+    fn reference(operands: &Operands, ops: &[Operand]) -> String {
+        let loosest = ops.iter().map(Operand::precedence).min();
+
+        match loosest {
+            Some(loosest) if loosest < UnaryOperand::PRECEDENCE || operands[0].0.is_empty() => {
+                let mut at = ops.iter().enumerate().filter(|(_, op)| op.precedence() == loosest).map(|(i, _)| i);
+
+                let at = match ops.iter().find(|op| op.precedence() == loosest).unwrap().associativity() {
+                    Associativity::Left  => at.next_back().unwrap(),
+                    Associativity::Right => at.next().unwrap(),
+                };
+
+                format!("({} {} {})", reference(&operands[.. at + 1], &ops[.. at]), ops[at].symbol(), reference(&operands[at + 1 ..], &ops[at + 1 ..]))
+            },
+
+            // a prefix takes everything binding tighter than itself
+            _ => {
+                let mut rest = operands.to_vec();
+
+                if rest[0].0.is_empty() {
+                    return rest[0].1.clone()
+                }
+
+                let prefix = rest[0].0.remove(0);
+
+                format!("({} {})", prefix.symbol(), reference(&rest, ops))
+            },
         }
+    }
+
+    #[test]
+    fn associativity() {
+        let cases = [
+            ("a ^ b ^ c",     "(a ^ (b ^ c))"),
+            ("a - b - c",     "((a - b) - c)"),
+            ("a |> f |> g",   "((a |> f) |> g)"),
+            ("f <| g <| a",   "(f <| (g <| a))"),
+            ("-a ^ b",        "(- (a ^ b))"),
+            ("-a * b",        "((- a) * b)"),
+            ("not a and b",   "((not a) and b)"),
+            ("a + b * c ^ d", "(a + (b * (c ^ d)))"),
+            ("a ?? b ?? c",   "(a ?? (b ?? c))"),
+            ("a ?? b or c",   "(a ?? (b or c))"),
+        ];
+
+        for (source, expected) in cases.iter() {
+            assert_eq!(parenthesise(&parse(source)), *expected, "{}", source)
+        }
+    }
 
-        Ok(ex_stack.pop().unwrap())
+    #[test]
+    fn comments() {
+        let source = "x := 1   # note\ny := #[ a #[ nested ]# one ]# 2\nz := 3 #[ end ]#  \n# a line of its own\nw := 4 # last";
+
+        let tokens = make_lexer(source.chars().collect()).tokenize().unwrap_or_else(|_| panic!("failed to lex: {}", source));
+        assert_eq!(tokens.iter().filter(|token| token.token_type == TokenType::Comment).count(), 5);
+
+        let ast = Parser::new(Traveler::new(tokens)).parse().unwrap_or_else(|_| panic!("failed to parse: {}", source));
+
+        let values: Vec<f64> = ast.iter().map(|statement| match *statement {
+            Statement::Definition(Definition {right: Some(ref right), ..}) => match **right {
+                Expression::Number(n) => n,
+                ref e                 => panic!("unexpected definition of: {:?}", e),
+            },
+
+            ref s => panic!("unexpected statement: {:?}", s),
+        }).collect();
+
+        assert_eq!(values, [1.0, 2.0, 3.0, 4.0]);
+
+        assert!(make_lexer("x := 1 #[ #[ ]#".chars().collect()).tokenize().is_err(), "unterminated block comment")
+    }
+
+    #[test]
+    fn matches_parenthesised_reference() {
+        let atoms    = ["a", "b", "c", "1", "2.5"];
+        let prefixes = [UnaryOperand::Neg, UnaryOperand::Not];
+
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0 .. 2000 {
+            let length = 1 + random.below(6);
+
+            let operands: Vec<_> = (0 .. length).map(|_| {
+                let prefixes = (0 .. random.below(3)).map(|_| prefixes[random.below(2)]).collect();
+                (prefixes, atoms[random.below(atoms.len())].to_string())
+            }).collect();
+
+            let ops: Vec<_> = (1 .. length).map(|_| OPERATORS[random.below(OPERATORS.len())].1).collect();
+
+            let source   = flat(&operands, &ops);
+            let expected = reference(&operands, &ops);
+
+            assert_eq!(parenthesise(&parse(&source)), expected, "{}", source);
+            assert_eq!(parenthesise(&parse(&expected)), expected, "{}", expected);
+        }
     }
 }
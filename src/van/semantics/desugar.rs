@@ -0,0 +1,298 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+
+use super::*;
+
+// what checking found out about a program that running it needs, by where it was found
+#[derive(Default)]
+pub struct Resolution {
+    pub names:        HashMap<TokenPosition, String>,      // the variant each use of an overloaded fun refers to
+    pub applications: HashMap<Application, Vec<usize>>,  // how many args each fun a call goes through takes, when it isn't given exactly that
+}
+
+// a call, or a pipe, that goes through funs taking other than what they're given. it's told apart by what
+// it is as well as where, as an interpolation's concatenations start where the code in it does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Application {
+    Call(TokenPosition, usize), // where the callee starts, and how deep in calls of calls it is
+    Pipe(TokenPosition),
+}
+
+impl Resolution {
+    pub fn extend(&mut self, other: Resolution) {
+        self.names.extend(other.names);
+        self.applications.extend(other.applications)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.applications.is_empty()
+    }
+}
+
+// a copy of `ast` the backends can run as is: overloaded funs go by the names of their variants,
+// funs given fewer args than they take become closures over them, and ones given more are called
+// again with the rest
+pub fn desugar(ast: &[Statement], resolution: &Resolution) -> Vec<Statement> {
+    if resolution.is_empty() {
+        return ast.to_vec()
+    }
+
+    Desugarer { resolution }.statements(ast)
+}
+
+struct Desugarer<'a> {
+    resolution: &'a Resolution,
+}
+
+impl<'a> Desugarer<'a> {
+    fn statements(&self, statements: &[Statement]) -> Vec<Statement> {
+        statements.iter().map(|statement| self.statement(statement)).collect()
+    }
+
+    fn statement(&self, statement: &Statement) -> Statement {
+        match *statement {
+            Statement::Expression(ref e) => Statement::Expression(self.rc(e)),
+
+            Statement::Definition(ref definition) => Statement::Definition(Definition {
+                right: definition.right.as_ref().map(|right| self.rc(right)),
+                ..definition.clone()
+            }),
+
+            Statement::Destructure(ref destructure) => Statement::Destructure(Destructure {
+                right: self.rc(&destructure.right),
+                ..destructure.clone()
+            }),
+
+            Statement::Assignment(ref assignment) => Statement::Assignment(self.assignment(assignment)),
+            Statement::FunctionMatch(ref function) => Statement::FunctionMatch(self.function_match(function)),
+            Statement::Fun(ref fun)                => Statement::Fun(self.fun(fun)),
+            Statement::If(ref base)                => Statement::If(self.if_base(base)),
+            Statement::Unless(ref unless)          => Statement::Unless(Unless { base: self.if_base(&unless.base) }),
+            Statement::MatchPattern(ref m)         => Statement::MatchPattern(self.match_pattern(m)),
+
+            Statement::Implementation(ref implementation) => Statement::Implementation(Implementation {
+                body: implementation.body.iter().map(|function| match *function {
+                    Function::Fun(ref fun)        => Function::Fun(self.fun(fun)),
+                    Function::Match(ref function) => Function::Match(self.function_match(function)),
+                }).collect(),
+
+                ..implementation.clone()
+            }),
+
+            Statement::Return(ref e) => Statement::Return(e.as_ref().map(|e| self.expression(e))),
+            Statement::Data(_)       => statement.clone(),
+            Statement::Extern(ref s) => Statement::Extern(Rc::new(self.statement(s))),
+
+            Statement::While(ref base) => Statement::While(While {
+                condition: self.expression(&base.condition),
+                body:      self.statements(&base.body),
+            }),
+
+            Statement::For(ref base) => Statement::For(For {
+                iterable: self.expression(&base.iterable),
+                body:     self.statements(&base.body),
+                ..base.clone()
+            }),
+
+            Statement::Break(_)     |
+            Statement::Continue(_)  |
+            Statement::Struct(_)    |
+            Statement::Interface(_) |
+            Statement::Import(_)    => statement.clone(),
+        }
+    }
+
+    fn rc(&self, e: &Expression) -> Rc<Expression> {
+        Rc::new(self.expression(e))
+    }
+
+    fn expression(&self, e: &Expression) -> Expression {
+        match *e {
+            Expression::Identifier(ref name, position) => match self.resolution.names.get(&position) {
+                Some(renamed) => Expression::Identifier(renamed.clone(), position),
+                None          => Expression::Identifier(name.clone(), position),
+            },
+
+            Expression::Block(ref statements) => Expression::Block(self.statements(statements)),
+
+            Expression::BinaryOp(ref op) => match (&op.op, self.resolution.applications.get(&Application::Pipe(op.position))) {
+                (&Operand::PipeRight, Some(arities)) => self.apply(self.expression(&op.right), vec![self.expression(&op.left)], arities, op.position),
+                (&Operand::PipeLeft, Some(arities))  => self.apply(self.expression(&op.left), vec![self.expression(&op.right)], arities, op.position),
+
+                _ => Expression::BinaryOp(BinaryOp {
+                    left:  self.rc(&op.left),
+                    right: self.rc(&op.right),
+                    ..op.clone()
+                }),
+            },
+
+            Expression::UnaryOp(ref op) => Expression::UnaryOp(UnaryOp {
+                expr: self.rc(&op.expr),
+                ..op.clone()
+            }),
+
+            Expression::MatchPattern(ref m) => Expression::MatchPattern(self.match_pattern(m)),
+
+            Expression::Call(ref call) => {
+                let callee = self.expression(&call.callee);
+                let args   = call.args.iter().map(|arg| self.expression(arg)).collect();
+
+                match self.resolution.applications.get(&Application::Call(call.position, call.nesting())) {
+                    Some(arities) => self.apply(callee, args, arities, call.position),
+                    None          => self.call(callee, args, call.position),
+                }
+            },
+
+            Expression::Index(ref index) => Expression::Index(Index {
+                id:    self.rc(&index.id),
+                index: self.rc(&index.index),
+                ..index.clone()
+            }),
+
+            Expression::Array(ref content, position) => Expression::Array(content.iter().map(|e| self.expression(e)).collect(), position),
+            Expression::Tuple(ref content, position) => Expression::Tuple(content.iter().map(|e| self.expression(e)).collect(), position),
+
+            Expression::If(ref base)     => Expression::If(Rc::new(self.if_base(base))),
+            Expression::Unless(ref base) => Expression::Unless(Rc::new(Unless { base: self.if_base(&base.base) })),
+
+            Expression::Initialization(ref a) => Expression::Initialization(Rc::new(Initialization {
+                id:       self.expression(&a.id),
+                values:   a.values.iter().map(|value| self.assignment(value)).collect(),
+                position: a.position,
+            })),
+
+            Expression::FunctionMatch(ref function) => Expression::FunctionMatch(Rc::new(self.function_match(function))),
+            Expression::Fun(ref fun)                => Expression::Fun(Rc::new(self.fun(fun))),
+
+            ref e => e.clone(),
+        }
+    }
+
+    fn call(&self, callee: Expression, args: Vec<Expression>, position: TokenPosition) -> Expression {
+        Expression::Call(Call {
+            callee: Rc::new(callee),
+            args:   args.into_iter().map(Rc::new).collect(),
+            position,
+        })
+    }
+
+    // `callee` given `args`, calling each fun in turn with as many as it takes
+    fn apply(&self, callee: Expression, mut args: Vec<Expression>, arities: &[usize], position: TokenPosition) -> Expression {
+        let mut callee = callee;
+
+        for &arity in arities {
+            if args.len() < arity {
+                return self.partial(callee, args, arity, position)
+            }
+
+            let rest = args.split_off(arity);
+
+            callee = self.call(callee, args, position);
+            args   = rest
+        }
+
+        callee
+    }
+
+    // a closure taking what `callee` still needs after `args`, which are evaluated right away like any others
+    fn partial(&self, callee: Expression, args: Vec<Expression>, arity: usize, position: TokenPosition) -> Expression {
+        let mut body = Vec::new();
+
+        let mut bind = |name: String, e: Expression| match e {
+            Expression::Number(_) |
+            Expression::Bool(_)   |
+            Expression::Str(_)    |
+            Expression::Char(_)   => e,
+
+            _ => {
+                body.push(Statement::Definition(Definition {
+                    t:     None,
+                    name:  name.clone(),
+                    right: Some(Rc::new(e)),
+                    position,
+                }));
+
+                Expression::Identifier(name, position)
+            },
+        };
+
+        let callee   = bind("$callee".to_owned(), callee);
+        let mut args = args.into_iter().enumerate().map(|(i, arg)| bind(format!("${}", i), arg)).collect::<Vec<_>>();
+
+        let params = (args.len() .. arity).map(|i| TypeDefinition {
+            name: format!("${}", i),
+            t:    Type::Undefined,
+        }).collect::<Vec<_>>();
+
+        args.extend(params.iter().map(|param| Expression::Identifier(param.name.clone(), position)));
+
+        let closure = Expression::Fun(Rc::new(Fun {
+            t:        None,
+            name:     None,
+            generics: Vec::new(),
+            params,
+            body:     vec![Statement::Expression(Rc::new(self.call(callee, args, position)))],
+            position,
+        }));
+
+        if body.is_empty() {
+            return closure
+        }
+
+        body.push(Statement::Expression(Rc::new(closure)));
+
+        Expression::Block(body)
+    }
+
+    fn assignment(&self, assignment: &Assignment) -> Assignment {
+        Assignment {
+            left:     self.rc(&assignment.left),
+            right:    self.rc(&assignment.right),
+            position: assignment.position,
+        }
+    }
+
+    fn fun(&self, fun: &Fun) -> Fun {
+        Fun {
+            name: fun.name.as_ref().map(|name| self.expression(name)),
+            body: self.statements(&fun.body),
+            ..fun.clone()
+        }
+    }
+
+    fn function_match(&self, function: &FunctionMatch) -> FunctionMatch {
+        FunctionMatch {
+            name: function.name.as_ref().map(|name| self.expression(name)),
+            arms: self.arms(&function.arms),
+            ..function.clone()
+        }
+    }
+
+    fn match_pattern(&self, m: &MatchPattern) -> MatchPattern {
+        MatchPattern {
+            matching: self.rc(&m.matching),
+            arms:     self.arms(&m.arms),
+            position: m.position,
+        }
+    }
+
+    fn arms(&self, arms: &[MatchArm]) -> Vec<MatchArm> {
+        arms.iter().map(|arm| MatchArm {
+            guard: arm.guard.as_ref().map(|guard| self.expression(guard)),
+            body:  self.rc(&arm.body),
+            ..arm.clone()
+        }).collect()
+    }
+
+    fn if_base(&self, base: &If) -> If {
+        If {
+            condition: self.expression(&base.condition),
+            body:      self.statements(&base.body),
+            elses:     base.elses.as_ref().map(|elses| elses.iter().map(|(condition, body)| {
+                (condition.as_ref().map(|condition| self.expression(condition)), self.statements(body))
+            }).collect()),
+
+            position: base.position,
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+use std::collections::HashMap;
+
+
+// what an arm matches, as far as telling which values are left goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Anything,
+    Literal(String),                 // one of infinitely many values
+    Constructor(String, Vec<Shape>), // one of the few forms a type has, with what's inside
+    Array(Vec<Shape>, bool),         // the first elements, and whether there may be more
+    Tuple(Vec<Shape>),               // built a single way, so only what's inside can be left out
+    Or(Vec<Shape>),
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Shape::Anything             => write!(f, "_"),
+            Shape::Literal(ref literal) => write!(f, "{}", literal),
+
+            Shape::Constructor(ref name, ref inner) if inner.is_empty() => write!(f, "{}", name),
+            Shape::Constructor(ref name, ref inner)                     => write!(f, "{} {}", name, describe(inner)),
+
+            Shape::Array(ref content, more) => {
+                let mut content: Vec<String> = content.iter().map(|shape| shape.to_string()).collect();
+
+                if more {
+                    content.push("...".to_string())
+                }
+
+                write!(f, "[{}]", content.join(", "))
+            },
+
+            Shape::Tuple(ref content) => {
+                let content: Vec<String> = content.iter().map(|shape| shape.to_string()).collect();
+                write!(f, "({})", content.join(", "))
+            },
+
+            Shape::Or(ref alternatives) => {
+                let alternatives: Vec<String> = alternatives.iter().map(|shape| shape.to_string()).collect();
+                write!(f, "{}", alternatives.join(" | "))
+            },
+        }
+    }
+}
+
+// shapes next to each other, as the arguments of a call
+pub fn describe(row: &[Shape]) -> String {
+    let shapes: Vec<String> = row.iter().map(|shape| match *shape {
+        Shape::Constructor(_, ref inner) if !inner.is_empty() => format!("({})", shape),
+        Shape::Or(_)                                          => format!("({})", shape),
+        _                                                     => shape.to_string(),
+    }).collect();
+
+    shapes.join(" ")
+}
+
+// a way to build a value of some type, which is what the columns get split by
+#[derive(Clone, PartialEq)]
+enum Constructor {
+    Named(String, usize),
+    Literal(String),
+    Length(usize, bool), // arrays of exactly this length, or of at least it
+    Tuple(usize),
+}
+
+impl Constructor {
+    fn arity(&self) -> usize {
+        match *self {
+            Constructor::Named(_, arity)  => arity,
+            Constructor::Literal(_)       => 0,
+            Constructor::Length(len, _)   => len,
+            Constructor::Tuple(len)       => len,
+        }
+    }
+
+    // the shape of `found` built with this, followed by whatever's after it
+    fn rebuild(&self, mut found: Vec<Shape>) -> Vec<Shape> {
+        let rest  = found.split_off(self.arity());
+
+        let shape = match *self {
+            Constructor::Named(ref name, _)   => Shape::Constructor(name.clone(), found),
+            Constructor::Literal(ref literal) => Shape::Literal(literal.clone()),
+            Constructor::Length(_, more)      => Shape::Array(found, more),
+            Constructor::Tuple(_)             => Shape::Tuple(found),
+        };
+
+        let mut rebuilt = vec![shape];
+        rebuilt.extend(rest);
+
+        rebuilt
+    }
+}
+
+// the arms as rows of shapes, one column per matched value
+pub struct Matrix {
+    rows:       Vec<Vec<Shape>>,
+    signatures: HashMap<String, Vec<(String, usize)>>, // every constructor of the type of each one
+}
+
+impl Matrix {
+    pub fn new() -> Matrix {
+        let mut matrix = Matrix {
+            rows:       Vec::new(),
+            signatures: HashMap::new(),
+        };
+
+        matrix.declare(&[("true".to_string(), 0), ("false".to_string(), 0)]);
+
+        matrix
+    }
+
+    // the constructors of a type, all of them
+    pub fn declare(&mut self, constructors: &[(String, usize)]) {
+        for (name, _) in constructors {
+            self.signatures.insert(name.clone(), constructors.to_vec());
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Shape>) {
+        self.rows.push(row)
+    }
+
+    // values matched by `row` and by none of the rows, if there are any
+    pub fn uncovered(&self, row: &[Shape]) -> Option<Vec<Shape>> {
+        self.witness(&self.rows, row)
+    }
+
+    fn witness(&self, rows: &[Vec<Shape>], row: &[Shape]) -> Option<Vec<Shape>> {
+        let rows = expand(rows);
+
+        let (head, rest) = match row.split_first() {
+            Some(split) => split,
+            None        => return if rows.is_empty() { Some(Vec::new()) } else { None },
+        };
+
+        let constructors = match *head {
+            Shape::Or(ref alternatives) => return alternatives.iter().find_map(|alternative| {
+                let mut row = vec![alternative.clone()];
+                row.extend_from_slice(rest);
+
+                self.witness(&rows, &row)
+            }),
+
+            Shape::Anything => return self.witness_anything(&rows, rest),
+
+            Shape::Constructor(ref name, ref inner) => vec![Constructor::Named(name.clone(), inner.len())],
+            Shape::Literal(ref literal)             => vec![Constructor::Literal(literal.clone())],
+            Shape::Tuple(ref content)               => vec![Constructor::Tuple(content.len())],
+
+            Shape::Array(ref content, false) => vec![Constructor::Length(content.len(), false)],
+            Shape::Array(ref content, true)  => {
+                let longest = longest(&rows).max(content.len());
+                (content.len() ..= longest).map(|len| Constructor::Length(len, len == longest)).collect()
+            },
+        };
+
+        constructors.iter().find_map(|constructor| self.witness_of(&rows, constructor, &specialize_row(head, constructor)?, rest))
+    }
+
+    // what's left when anything goes in the first column, trying every way it could be built
+    fn witness_anything(&self, rows: &[Vec<Shape>], rest: &[Shape]) -> Option<Vec<Shape>> {
+        let heads: Vec<&Shape> = rows.iter().map(|row| &row[0]).filter(|shape| **shape != Shape::Anything).collect();
+
+        let (all, missing) = match heads.first() {
+            Some(Shape::Array(..)) => {
+                let longest = longest(rows);
+                ((0 ..= longest).map(|len| Constructor::Length(len, len == longest)).collect(), Vec::new())
+            },
+
+            Some(Shape::Tuple(ref content)) => (vec![Constructor::Tuple(content.len())], Vec::new()),
+
+            Some(Shape::Constructor(ref name, _)) => match self.signatures.get(name) {
+                Some(signature) => {
+                    let all: Vec<_> = signature.iter().map(|(name, arity)| Constructor::Named(name.clone(), *arity)).collect();
+
+                    let missing = all.iter().filter(|constructor| !heads.iter().any(|shape| match (*shape, *constructor) {
+                        (Shape::Constructor(ref a, _), Constructor::Named(ref b, _)) => a == b,
+                        _                                                            => false,
+                    })).cloned().collect();
+
+                    (all, missing)
+                },
+
+                None => (Vec::new(), Vec::new()),
+            },
+
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        // every constructor shows up, so whatever's left is left inside one of them
+        if !all.is_empty() && missing.is_empty() {
+            return all.iter().find_map(|constructor| self.witness_of(rows, constructor, &vec![Shape::Anything; constructor.arity()], rest))
+        }
+
+        let defaults: Vec<_> = rows.iter().filter(|row| row[0] == Shape::Anything).map(|row| row[1 ..].to_vec()).collect();
+
+        let mut found = self.witness(&defaults, rest)?;
+
+        found.insert(0, match missing.first() {
+            Some(constructor) => constructor.rebuild(vec![Shape::Anything; constructor.arity()]).remove(0),
+            None              => Shape::Anything,
+        });
+
+        Some(found)
+    }
+
+    fn witness_of(&self, rows: &[Vec<Shape>], constructor: &Constructor, inner: &[Shape], rest: &[Shape]) -> Option<Vec<Shape>> {
+        let specialized: Vec<_> = rows.iter().filter_map(|row| {
+            let mut specialized = specialize_row(&row[0], constructor)?;
+            specialized.extend_from_slice(&row[1 ..]);
+
+            Some(specialized)
+        }).collect();
+
+        let mut row = inner.to_vec();
+        row.extend_from_slice(rest);
+
+        Some(constructor.rebuild(self.witness(&specialized, &row)?))
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::new()
+    }
+}
+
+// one row per alternative of the or-patterns in the first column
+fn expand(rows: &[Vec<Shape>]) -> Vec<Vec<Shape>> {
+    let mut expanded = Vec::new();
+
+    for row in rows {
+        match row.first() {
+            Some(Shape::Or(ref alternatives)) => for alternative in alternatives {
+                let mut alternative_row = vec![alternative.clone()];
+                alternative_row.extend_from_slice(&row[1 ..]);
+
+                expanded.extend(expand(&[alternative_row]))
+            },
+
+            _ => expanded.push(row.clone()),
+        }
+    }
+
+    expanded
+}
+
+// arrays at least this long all match the same rows in the first column
+fn longest(rows: &[Vec<Shape>]) -> usize {
+    rows.iter().map(|row| match row[0] {
+        Shape::Array(ref content, false) => content.len() + 1,
+        Shape::Array(ref content, true)  => content.len(),
+        _                                => 0,
+    }).max().unwrap_or(0)
+}
+
+// what's inside `shape` when its value was built with `constructor`, or `None` if it can't have been
+fn specialize_row(shape: &Shape, constructor: &Constructor) -> Option<Vec<Shape>> {
+    match (shape, constructor) {
+        (Shape::Anything, c) => Some(vec![Shape::Anything; c.arity()]),
+
+        (Shape::Constructor(ref a, ref inner), Constructor::Named(ref b, _)) if a == b => Some(inner.clone()),
+        (Shape::Literal(ref a), Constructor::Literal(ref b)) if a == b                 => Some(Vec::new()),
+        (Shape::Tuple(ref content), Constructor::Tuple(len)) if content.len() == *len  => Some(content.clone()),
+
+        (Shape::Array(ref content, false), Constructor::Length(len, false)) if content.len() == *len => Some(content.clone()),
+
+        (Shape::Array(ref content, true), Constructor::Length(len, _)) if content.len() <= *len => {
+            let mut inner = content.clone();
+            inner.resize(*len, Shape::Anything);
+
+            Some(inner)
+        },
+
+        _ => None,
+    }
+}
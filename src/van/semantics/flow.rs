@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+// a name's slot, by how deep its scope is and where it is in it
+pub type Slot = (usize, usize);
+
+// names known not to be nil, with what they are then
+pub type Narrowed = Vec<(Slot, Type)>;
+
+// what's known at some point of a program about names that are optional, and when they aren't nil
+#[derive(Clone, Default)]
+pub struct Flow {
+    pub narrowed: HashMap<Slot, Type>, // the names known not to be nil, with what they are then
+    pub escaped:  HashSet<Slot>,       // mutable names a fun gives a value, which can happen at any call
+    pub captured: usize,               // how deep the scopes outside the fun being checked go
+    pub ended:    bool,                // after a `return`, `break` or `continue`, where nothing runs
+}
+
+impl Flow {
+    // what's known after either this or `other` ran
+    pub fn join(&mut self, other: &Flow) {
+        self.escaped.extend(other.escaped.iter().cloned());
+
+        if other.ended {
+            return
+        }
+
+        if self.ended {
+            self.narrowed = other.narrowed.clone();
+            self.ended    = false;
+        } else {
+            self.narrowed.retain(|slot, _| other.narrowed.contains_key(slot))
+        }
+
+        let escaped = &self.escaped;
+        self.narrowed.retain(|slot, _| !escaped.contains(slot))
+    }
+
+    pub fn narrow(&mut self, narrowed: &[(Slot, Type)]) {
+        for (slot, t) in narrowed {
+            self.narrowed.insert(*slot, t.clone());
+        }
+    }
+
+    pub fn escape(&mut self, slot: Slot) {
+        self.narrowed.remove(&slot);
+        self.escaped.insert(slot);
+    }
+
+    // what the body of a fun starts out knowing: a mutable name could be given nil before it's called
+    pub fn inside_fun(&self, depth: usize) -> Flow {
+        Flow {
+            narrowed: self.narrowed.iter().filter(|(_, t)| !t.is_mut()).map(|(slot, t)| (*slot, t.clone())).collect(),
+            escaped:  self.escaped.clone(),
+            captured: depth,
+            ended:    false,
+        }
+    }
+
+    // forgets the names of scopes deeper than `depth`, once they're left
+    pub fn leave(&mut self, depth: usize) {
+        self.narrowed.retain(|slot, _| slot.0 <= depth);
+        self.escaped.retain(|slot| slot.0 <= depth)
+    }
+
+    // whether going around a loop again would know the same
+    pub fn settled(&self, other: &Flow) -> bool {
+        self.narrowed.len() == other.narrowed.len() && self.escaped == other.escaped && self.narrowed.keys().all(|slot| other.narrowed.contains_key(slot))
+    }
+}
+
+// how the statements that were being run were left
+#[derive(Clone, Copy, PartialEq)]
+pub enum Exit {
+    Break,
+    Continue,
+    Return,
+}
@@ -0,0 +1,340 @@
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+// why two types couldn't be made the same
+pub enum Mismatch {
+    Types(Rc<Type>, Rc<Type>), // (expected, found)
+    Infinite(usize, Rc<Type>), // the variable occurs in what it would be bound to
+}
+
+// type variables and what unification has bound them to so far, shared by every visitor of a program
+#[derive(Default)]
+pub struct Inference {
+    substitution: RefCell<HashMap<usize, Type>>,
+    generic:      RefCell<HashSet<usize>>, // generalized variables, replaced by fresh ones on every use
+    next:         Cell<usize>,
+}
+
+impl Inference {
+    pub fn new() -> Inference {
+        Inference::default()
+    }
+
+    pub fn fresh(&self) -> Type {
+        let var = self.next.get();
+        self.next.set(var + 1);
+
+        Type::Var(var)
+    }
+
+    // applies everything known so far, all the way down
+    pub fn resolve(&self, t: &Type) -> Type {
+        match *t {
+            Type::Var(var) => {
+                let bound = self.substitution.borrow().get(&var).cloned();
+
+                match bound {
+                    Some(bound) => self.resolve(&bound),
+                    None        => t.clone(),
+                }
+            },
+
+            Type::Mut(Some(ref t))       => Type::Mut(Some(Rc::new(self.resolve(t)))),
+            Type::Array(ref t, ref len)  => Type::Array(Rc::new(self.resolve(t)), len.clone()),
+            Type::Optional(ref t)        => Type::Optional(Rc::new(self.resolve(t))),
+            Type::Tuple(ref content)     => Type::Tuple(content.iter().map(|t| self.resolve(t)).collect()),
+            Type::Fun(ref params, ref retty) => Type::Fun(
+                params.iter().map(|param| self.resolve(param)).collect(),
+                retty.as_ref().map(|retty| Rc::new(self.resolve(retty))),
+            ),
+
+            Type::Struct(ref fields)      => Type::Struct(self.resolve_fields(fields)),
+            Type::Interface(ref fields)   => Type::Interface(self.resolve_fields(fields)),
+            Type::Generic(ref name, ref args) => Type::Generic(name.clone(), args.iter().map(|arg| self.resolve(arg)).collect()),
+            Type::Data(ref name, ref variants) => Type::Data(name.clone(), variants.iter().map(|(variant, fields)| {
+                (variant.clone(), fields.iter().map(|field| self.resolve(field)).collect())
+            }).collect()),
+
+            ref t => t.clone(),
+        }
+    }
+
+    fn resolve_fields(&self, fields: &HashMap<String, Rc<Type>>) -> HashMap<String, Rc<Type>> {
+        fields.iter().map(|(name, t)| (name.clone(), Rc::new(self.resolve(t)))).collect()
+    }
+
+    // binds variables so that `expected` and `found` become the same type. `alias` looks up
+    // what a named type stands for given its type arguments, so `Point` and the struct it names can be unified
+    pub fn unify(&self, expected: &Type, found: &Type, alias: &dyn Fn(&str, &[Type]) -> Option<Type>) -> Result<(), Mismatch> {
+        let a = self.resolve(expected);
+        let b = self.resolve(found);
+
+        let mismatch = || Err(Mismatch::Types(Rc::new(a.clone()), Rc::new(b.clone())));
+
+        // a mismatch somewhere inside is reported as one of the whole types
+        let widen = |e: Mismatch| match e {
+            Mismatch::Types(..) => Mismatch::Types(Rc::new(a.clone()), Rc::new(b.clone())),
+            e                   => e,
+        };
+
+        match (&a, &b) {
+            // mutability is where a value is kept, not part of its type
+            (Type::Mut(Some(a)), b) => self.unify(a, b, alias),
+            (a, Type::Mut(Some(b))) => self.unify(a, b, alias),
+
+            (Type::Var(x), Type::Var(y)) if x == y => Ok(()),
+
+            (Type::Var(var), t) |
+            (t, Type::Var(var)) => self.bind(*var, t),
+
+            // a value that's there, or nothing at all, fits where one might be
+            (Type::Optional(a), Type::Optional(b)) => self.unify(a, b, alias).map_err(&widen),
+            (Type::Optional(_), Type::Nil)         => Ok(()),
+            (Type::Optional(a), t)                 => self.unify(a, t, alias).map_err(&widen),
+
+            (Type::Identifier(x), Type::Identifier(y)) if x == y => Ok(()),
+
+            (Type::Generic(x, x_args), Type::Generic(y, y_args)) if x == y && x_args.len() == y_args.len() => {
+                for (a, b) in x_args.iter().zip(y_args.iter()) {
+                    self.unify(a, b, alias).map_err(&widen)?
+                }
+
+                Ok(())
+            },
+
+            (Type::Identifier(name), t) => match alias(name, &[]) {
+                Some(a) => self.unify(&a, t, alias),
+                None    => mismatch(),
+            },
+
+            (t, Type::Identifier(name)) => match alias(name, &[]) {
+                Some(b) => self.unify(t, &b, alias),
+                None    => mismatch(),
+            },
+
+            (Type::Generic(name, args), t) => match alias(name, args) {
+                Some(a) => self.unify(&a, t, alias).map_err(&widen),
+                None    => mismatch(),
+            },
+
+            (t, Type::Generic(name, args)) => match alias(name, args) {
+                Some(b) => self.unify(t, &b, alias).map_err(&widen),
+                None    => mismatch(),
+            },
+
+            (Type::Fun(a_params, a_retty), Type::Fun(b_params, b_retty)) => {
+                if a_params.len() != b_params.len() {
+                    return mismatch()
+                }
+
+                for (a, b) in a_params.iter().zip(b_params.iter()) {
+                    self.unify(a, b, alias).map_err(&widen)?
+                }
+
+                let nil = Rc::new(Type::Nil);
+
+                self.unify(a_retty.as_ref().unwrap_or(&nil), b_retty.as_ref().unwrap_or(&nil), alias).map_err(&widen)
+            },
+
+            (Type::Array(a_t, a_len), Type::Array(b_t, b_len)) => {
+                if let (Some(a_len), Some(b_len)) = (a_len, b_len) {
+                    if a_len != b_len {
+                        return mismatch()
+                    }
+                }
+
+                self.unify(a_t, b_t, alias).map_err(&widen)
+            },
+
+            (Type::Tuple(a_content), Type::Tuple(b_content)) => {
+                if a_content.len() != b_content.len() {
+                    return mismatch()
+                }
+
+                for (a, b) in a_content.iter().zip(b_content.iter()) {
+                    self.unify(a, b, alias).map_err(&widen)?
+                }
+
+                Ok(())
+            },
+
+            (Type::Data(a_name, a_variants), Type::Data(b_name, b_variants)) => {
+                if a_name != b_name {
+                    return mismatch()
+                }
+
+                for ((_, a_fields), (_, b_fields)) in a_variants.iter().zip(b_variants.iter()) {
+                    for (a, b) in a_fields.iter().zip(b_fields.iter()) {
+                        self.unify(a, b, alias).map_err(&widen)?
+                    }
+                }
+
+                Ok(())
+            },
+
+            (Type::Struct(a_fields), Type::Struct(b_fields)) |
+            (Type::Interface(a_fields), Type::Interface(b_fields)) => {
+                if a_fields.len() != b_fields.len() {
+                    return mismatch()
+                }
+
+                for (name, a) in a_fields {
+                    match b_fields.get(name) {
+                        Some(b) => self.unify(a, b, alias).map_err(&widen)?,
+                        None    => return mismatch(),
+                    }
+                }
+
+                Ok(())
+            },
+
+            (a, b) => if a == b {
+                Ok(())
+            } else {
+                mismatch()
+            },
+        }
+    }
+
+    // whether `expected` and `found` could be unified, leaving every variable as it was
+    pub fn fits(&self, expected: &Type, found: &Type, alias: &dyn Fn(&str, &[Type]) -> Option<Type>) -> bool {
+        let substitution = self.substitution.borrow().clone();
+        let fits         = self.unify(expected, found, alias).is_ok();
+
+        *self.substitution.borrow_mut() = substitution;
+
+        fits
+    }
+
+    fn bind(&self, var: usize, t: &Type) -> Result<(), Mismatch> {
+        let mut vars = HashSet::new();
+        self.free_vars(t, &mut vars);
+
+        if vars.contains(&var) {
+            return Err(Mismatch::Infinite(var, Rc::new(t.clone())))
+        }
+
+        self.substitution.borrow_mut().insert(var, t.clone());
+
+        Ok(())
+    }
+
+    pub fn free_vars(&self, t: &Type, vars: &mut HashSet<usize>) {
+        match self.resolve(t) {
+            Type::Var(var)              => { vars.insert(var); },
+            Type::Mut(Some(ref t))      => self.free_vars(t, vars),
+            Type::Array(ref t, _)       => self.free_vars(t, vars),
+            Type::Optional(ref t)       => self.free_vars(t, vars),
+            Type::Fun(ref params, ref retty) => {
+                for param in params {
+                    self.free_vars(param, vars)
+                }
+
+                if let Some(retty) = retty {
+                    self.free_vars(retty, vars)
+                }
+            },
+
+            Type::Struct(ref fields) |
+            Type::Interface(ref fields) => for t in fields.values() {
+                self.free_vars(t, vars)
+            },
+
+            Type::Generic(_, ref args) |
+            Type::Tuple(ref args)      => for arg in args {
+                self.free_vars(arg, vars)
+            },
+
+            Type::Data(_, ref variants) => for t in variants.iter().flat_map(|variant| variant.1.iter()) {
+                self.free_vars(t, vars)
+            },
+
+            _ => (),
+        }
+    }
+
+    // makes every variable of `t` that the surrounding scope doesn't know about generic
+    pub fn generalize(&self, t: &Type, environment: &HashSet<usize>) {
+        let mut vars = HashSet::new();
+        self.free_vars(t, &mut vars);
+
+        let mut generic = self.generic.borrow_mut();
+
+        for var in vars.difference(environment) {
+            generic.insert(*var);
+        }
+    }
+
+    // a copy of `t` with fresh variables in place of the generic ones
+    pub fn instantiate(&self, t: &Type) -> Type {
+        let t = self.resolve(t);
+
+        let mut vars = HashSet::new();
+        self.free_vars(&t, &mut vars);
+
+        let fresh: HashMap<usize, Type> = vars.into_iter()
+            .filter(|var| self.generic.borrow().contains(var))
+            .map(|var| (var, self.fresh()))
+            .collect();
+
+        if fresh.is_empty() {
+            t
+        } else {
+            t.substitute(&fresh)
+        }
+    }
+}
+
+impl Type {
+    fn substitute(&self, vars: &HashMap<usize, Type>) -> Type {
+        match *self {
+            Type::Var(var) => vars.get(&var).cloned().unwrap_or(Type::Var(var)),
+
+            Type::Mut(Some(ref t))      => Type::Mut(Some(Rc::new(t.substitute(vars)))),
+            Type::Array(ref t, ref len) => Type::Array(Rc::new(t.substitute(vars)), len.clone()),
+            Type::Optional(ref t)       => Type::Optional(Rc::new(t.substitute(vars))),
+            Type::Tuple(ref content)    => Type::Tuple(content.iter().map(|t| t.substitute(vars)).collect()),
+            Type::Fun(ref params, ref retty) => Type::Fun(
+                params.iter().map(|param| param.substitute(vars)).collect(),
+                retty.as_ref().map(|retty| Rc::new(retty.substitute(vars))),
+            ),
+
+            Type::Struct(ref fields)    => Type::Struct(fields.iter().map(|(name, t)| (name.clone(), Rc::new(t.substitute(vars)))).collect()),
+            Type::Interface(ref fields) => Type::Interface(fields.iter().map(|(name, t)| (name.clone(), Rc::new(t.substitute(vars)))).collect()),
+            Type::Generic(ref name, ref args) => Type::Generic(name.clone(), args.iter().map(|arg| arg.substitute(vars)).collect()),
+            Type::Data(ref name, ref variants) => Type::Data(name.clone(), variants.iter().map(|(variant, fields)| {
+                (variant.clone(), fields.iter().map(|field| field.substitute(vars)).collect())
+            }).collect()),
+
+            ref t => t.clone(),
+        }
+    }
+
+    // `self` with the type parameters named in `args` standing for what they're given there
+    pub fn replace(&self, args: &HashMap<String, Type>) -> Type {
+        match *self {
+            Type::Identifier(ref name) => args.get(name).cloned().unwrap_or_else(|| self.clone()),
+
+            Type::Mut(Some(ref t))      => Type::Mut(Some(Rc::new(t.replace(args)))),
+            Type::Array(ref t, ref len) => Type::Array(Rc::new(t.replace(args)), len.clone()),
+            Type::Optional(ref t)       => Type::Optional(Rc::new(t.replace(args))),
+            Type::Tuple(ref content)    => Type::Tuple(content.iter().map(|t| t.replace(args)).collect()),
+            Type::Fun(ref params, ref retty) => Type::Fun(
+                params.iter().map(|param| param.replace(args)).collect(),
+                retty.as_ref().map(|retty| Rc::new(retty.replace(args))),
+            ),
+
+            Type::Struct(ref fields)    => Type::Struct(fields.iter().map(|(name, t)| (name.clone(), Rc::new(t.replace(args)))).collect()),
+            Type::Interface(ref fields) => Type::Interface(fields.iter().map(|(name, t)| (name.clone(), Rc::new(t.replace(args)))).collect()),
+            Type::Generic(ref name, ref types) => Type::Generic(name.clone(), types.iter().map(|t| t.replace(args)).collect()),
+            Type::Data(ref name, ref variants) => Type::Data(name.clone(), variants.iter().map(|(variant, fields)| {
+                (variant.clone(), fields.iter().map(|field| field.replace(args)).collect())
+            }).collect()),
+
+            ref t => t.clone(),
+        }
+    }
+}
@@ -1,9 +1,17 @@
 pub mod symtab;
 pub mod typetab;
 pub mod visitor;
+pub mod inference;
+pub mod exhaustiveness;
+pub mod flow;
+pub mod desugar;
 
 pub use super::*;
 
 pub use self::symtab::*;
 pub use self::typetab::*;
 pub use self::visitor::*;
+pub use self::inference::*;
+pub use self::exhaustiveness::*;
+pub use self::flow::*;
+pub use self::desugar::*;
@@ -1,13 +1,19 @@
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 
 use std::fmt;
 
+use super::TokenPosition;
+
 #[derive(Clone)]
 pub struct SymTab {
-    pub parent:  Option<Rc<SymTab>>,
-    pub names:   RefCell<HashMap<String, usize>>,
+    pub parent:    Option<Rc<SymTab>>,
+    pub names:     RefCell<HashMap<String, usize>>,
+    pub positions: RefCell<HashMap<String, TokenPosition>>, // where names were declared, when known
+    pub overloads: RefCell<HashMap<String, Vec<String>>>,   // the names every variant of a fun goes by
+    pub slots:     Cell<usize>,                             // how many slots have been handed out
+    pub shared:    bool,                                    // whether its names take slots of its parent's instead
 }
 
 impl SymTab {
@@ -19,25 +25,129 @@ impl SymTab {
         }
 
         SymTab {
-            parent:  Some(parent),
-            names:   RefCell::new(hash_names),
+            parent:    Some(parent),
+            names:     RefCell::new(hash_names),
+            positions: RefCell::new(HashMap::new()),
+            overloads: RefCell::new(HashMap::new()),
+            slots:     Cell::new(names.len()),
+            shared:    false,
+        }
+    }
+
+    // a scope with no values of its own at runtime, its names living in the same place as its parent's
+    pub fn new_shared(parent: Rc<SymTab>) -> SymTab {
+        SymTab {
+            shared: true,
+            ..SymTab::new(parent, &[])
         }
     }
 
     pub fn new_global() -> SymTab {
         SymTab {
-            parent:  None,
-            names:   RefCell::new(HashMap::new()),
+            parent:    None,
+            names:     RefCell::new(HashMap::new()),
+            positions: RefCell::new(HashMap::new()),
+            overloads: RefCell::new(HashMap::new()),
+            slots:     Cell::new(0),
+            shared:    false,
+        }
+    }
+
+    // the names visible from here as they are now, which later definitions leave alone
+    pub fn snapshot(&self) -> SymTab {
+        SymTab {
+            parent: self.parent.as_ref().map(|parent| Rc::new(parent.snapshot())),
+            ..self.clone()
+        }
+    }
+
+    // how many scopes this one is in
+    pub fn depth(&self) -> usize {
+        match self.parent {
+            Some(ref parent) => parent.depth() + 1,
+            None             => 0,
+        }
+    }
+
+    // the scope whose values the names of this one are among
+    pub fn owner(&self) -> &SymTab {
+        match self.parent {
+            Some(ref parent) if self.shared => parent.owner(),
+            _                               => self,
         }
     }
 
     pub fn add_name(&self, name: &str) -> usize {
-        let new_index = self.names.borrow().len();
+        // a redefinition shadows the name with a slot of its own, leaving whatever already took the old
+        // one to keep seeing it
+        let slots     = &self.owner().slots;
+        let new_index = slots.get();
+
+        slots.set(new_index + 1);
         self.names.borrow_mut().insert(name.to_string(), new_index);
 
         new_index
     }
 
+    pub fn add_name_at(&self, name: &str, position: TokenPosition) -> usize {
+        self.positions.borrow_mut().insert(name.to_owned(), position);
+        self.add_name(name)
+    }
+
+    // a named fun, which later funs of the same name in this scope can overload
+    pub fn add_function(&self, name: &str, position: TokenPosition) -> usize {
+        self.overloads.borrow_mut().insert(name.to_owned(), vec![name.to_owned()]);
+        self.add_name_at(name, position)
+    }
+
+    // another variant of the funs named `name` in this scope, going by a name of its own that no
+    // identifier can spell. `None` when `name` isn't a fun of this scope
+    pub fn add_variant(&self, name: &str, position: TokenPosition) -> Option<(String, usize)> {
+        let variant = match self.overloads.borrow_mut().get_mut(name) {
+            Some(variants) => {
+                let variant = format!("{}${}", name, variants.len());
+                variants.push(variant.clone());
+
+                variant
+            },
+
+            None => return None,
+        };
+
+        let index = self.add_name_at(&variant, position);
+
+        Some((variant, index))
+    }
+
+    pub fn remove_variant(&self, name: &str, variant: &str) {
+        if let Some(variants) = self.overloads.borrow_mut().get_mut(name) {
+            variants.retain(|v| v != variant)
+        }
+    }
+
+    // the variants `name` stands for, when it's overloaded where it's visible from here
+    pub fn get_overloads(&self, name: &str) -> Option<Vec<String>> {
+        if self.names.borrow().contains_key(name) {
+            return self.overloads.borrow().get(name).filter(|variants| variants.len() > 1).cloned()
+        }
+
+        match self.parent {
+            Some(ref parent) => parent.get_overloads(name),
+            None             => None,
+        }
+    }
+
+    pub fn get_position(&self, name: &str) -> Option<TokenPosition> {
+        if self.names.borrow().contains_key(name) {
+            return self.positions.borrow().get(name).cloned()
+        }
+
+        match self.parent {
+            Some(ref parent) => parent.get_position(name),
+            None             => None,
+        }
+    }
+
     pub fn get_name(&self, name: &str) -> Option<(usize, usize)> {
         self.get_name_internal(name, 0)
     }
@@ -48,11 +158,13 @@ impl SymTab {
         }
 
         match self.parent {
-            Some(ref parent) => parent.get_name_internal(name, env_index + 1),
-            None => None,
+            Some(ref parent) if self.shared => parent.get_name_internal(name, env_index),
+            Some(ref parent)                => parent.get_name_internal(name, env_index + 1),
+            None                            => None,
         }
     }
 
+    #[allow(dead_code)]
     pub fn visualize(&self, env_index: usize) {
         if env_index > 0 {
             if let Some(ref p) = self.parent {
@@ -69,13 +181,13 @@ impl SymTab {
     fn dump(&self, f: &mut fmt::Formatter, env_index: usize) -> fmt::Result {
         if env_index > 0 {
             if let Some(ref p) = self.parent {
-                try!(p.dump(f, env_index - 1));
-                try!(writeln!(f, "------------------------------"));
+                p.dump(f, env_index - 1)?;
+                writeln!(f, "------------------------------")?;
             }
         }
 
         for (i, v) in self.names.borrow().iter().enumerate() {
-            try!(writeln!(f, "({} : {}) = {:?}", i, env_index, v))
+            writeln!(f, "({} : {}) = {:?}", i, env_index, v)?
         }
 
         Ok(())
@@ -84,7 +196,7 @@ impl SymTab {
 
 impl fmt::Debug for SymTab {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        try!(self.dump(f, 0));
+        self.dump(f, 0)?;
         Ok(())
     }
 }
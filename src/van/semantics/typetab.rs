@@ -13,10 +13,10 @@ pub struct TypeTab {
 }
 
 impl TypeTab {
-    pub fn new(parent: Rc<TypeTab>, types: &Vec<Type>, aliases: &HashMap<String, Type>) -> TypeTab {
+    pub fn new(parent: Rc<TypeTab>, types: &[Type], aliases: &HashMap<String, Type>) -> TypeTab {
         TypeTab {
             parent:  Some(parent),
-            types:   RefCell::new(types.clone()),
+            types:   RefCell::new(types.to_vec()),
             aliases: RefCell::new(aliases.clone()),
         }
     }
@@ -80,23 +80,19 @@ impl TypeTab {
         }
     }
     
-    pub fn get_alias(&self, name: &str, env_index: usize) -> Result<Type, Response> {
-        if env_index == 0 {
-            match self.aliases.borrow().get(&name.to_owned()) {
-                Some(v) => Ok(v.clone()),
-                None => Err(Response::error(None, format!("invalid type: {}", name))),
-            }
-        } else {
-            match self.parent {
-                Some(ref p) => p.get_alias(name, env_index - 1),
-                None        => match self.aliases.borrow().get(&name.to_owned()) {
-                    Some(v) => Ok(v.clone()),
-                    None => Err(Response::error(None, format!("invalid type: {}", name))),
-                }
-            }
+    // the alias visible from here, however far up it was declared
+    pub fn find_alias(&self, name: &str) -> Option<Type> {
+        if let Some(t) = self.aliases.borrow().get(name) {
+            return Some(t.clone())
+        }
+
+        match self.parent {
+            Some(ref p) => p.find_alias(name),
+            None        => None,
         }
     }
 
+    #[allow(dead_code)]
     pub fn visualize(&self, env_index: usize) {
         if env_index > 0 {
             if let Some(ref p) = self.parent {
@@ -113,13 +109,13 @@ impl TypeTab {
     fn dump(&self, f: &mut fmt::Formatter, env_index: usize) -> fmt::Result {
         if env_index > 0 {
             if let Some(ref p) = self.parent {
-                try!(p.dump(f, env_index - 1));
-                try!(writeln!(f, "------------------------------"));
+                p.dump(f, env_index - 1)?;
+                writeln!(f, "------------------------------")?;
             }
         }
 
         for (i, v) in self.types.borrow().iter().enumerate() {
-            try!(writeln!(f, "({} : {}) = {:?}", i, env_index, v))
+            writeln!(f, "({} : {}) = {:?}", i, env_index, v)?
         }
 
         Ok(())
@@ -136,7 +132,7 @@ impl TypeTab {
 
 impl fmt::Debug for TypeTab {
     fn fmt(&self, f : &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        try!(self.dump(f, 0));
+        self.dump(f, 0)?;
         Ok(())
     }
 }
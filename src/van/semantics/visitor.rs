@@ -1,30 +1,220 @@
 use std::rc::Rc;
-use std::collections::HashMap;
+use std::mem;
+use std::slice;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 
 use super::*;
 
 pub struct Visitor {
-    pub typetab: TypeTab,
-    pub symtab:  SymTab,
+    pub typetab:   TypeTab,
+    pub symtab:    SymTab,
+    pub warnings:  Vec<Response>,
+    pub modules:   HashMap<String, Rc<Module>>, // what imports can refer to
+    pub inference: Rc<Inference>,               // shared with every nested visitor
+    pub resolved:  Resolution,                  // what the backends need to know of what was checked
+    pub imported:  HashSet<usize>,              // the slots of the top level that imports took
+    unloaded:      HashSet<Slot>,               // names from modules that couldn't be loaded
+    incomplete:    bool,                        // whether everything was imported from one of those
+    loops:         usize,                       // how many loops the statements are in, within the fun they're in
+    flow:          Flow,                        // what's known here about optional names
+    exits:         Vec<(Exit, Flow)>,           // the ways out of what's being checked, and what's known at each
 }
 
 impl Visitor {
     pub fn new() -> Visitor {
         Visitor {
-            typetab: TypeTab::new_global(),
-            symtab:  SymTab::new_global(),
+            typetab:    TypeTab::new_global(),
+            symtab:     SymTab::new_global(),
+            warnings:   Vec::new(),
+            modules:    HashMap::new(),
+            inference:  Rc::new(Inference::new()),
+            resolved:   Resolution::default(),
+            imported:   HashSet::new(),
+            unloaded:   HashSet::new(),
+            incomplete: false,
+            loops:      0,
+            flow:       Flow::default(),
+            exits:      Vec::new(),
         }
     }
 
-    pub fn from(symtab: SymTab, typetab: TypeTab) -> Visitor {
+    // a visitor for a nested scope, declaring `names` with `types`
+    fn local(&self, names: &[String], types: &[Type]) -> Visitor {
         Visitor {
-            symtab,
-            typetab,
+            symtab:     SymTab::new(Rc::new(self.symtab.clone()), names),
+            typetab:    TypeTab::new(Rc::new(self.typetab.clone()), types, &HashMap::new()),
+            warnings:   Vec::new(),
+            modules:    self.modules.clone(),
+            inference:  self.inference.clone(),
+            resolved:   Resolution::default(),
+            imported:   HashSet::new(),
+            unloaded:   self.unloaded.clone(),
+            incomplete: self.incomplete,
+            loops:      self.loops,
+            flow:       self.flow.clone(),
+            exits:      Vec::new(),
         }
     }
 
-    fn alias_type(&self, t: &Type) -> Result<Type, Response> {
-        let mut acc_t = t.clone();
+    // checks every statement, even after errors, returning everything found as one group
+    pub fn visit(&mut self, ast: &[Statement]) -> Response {
+        let mut responses = Vec::new();
+
+        if let Err(e) = self.visit_statements(ast) {
+            responses.push(e)
+        }
+
+        responses.append(&mut self.warnings);
+
+        Response::group(responses)
+    }
+
+    fn visit_statements(&mut self, statements: &[Statement]) -> Result<(), Response> {
+        let mut errors = Vec::new();
+
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                errors.push(e)
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Response::group(errors))
+        }
+    }
+
+    // an error for reusing `name`, noting where it was first defined
+    fn already_in_use(&self, what: &str, name: &str, position: TokenPosition) -> Response {
+        let error = Response::error(at(position, name.len()), format!("{} already in use: {}", what, name));
+        self.with_definition(error, name, "was first defined here")
+    }
+
+    // attaches a note pointing at the declaration of `name`, when it is known
+    fn with_definition(&self, error: Response, name: &str, note: &str) -> Response {
+        match self.symtab.get_position(name) {
+            Some(position) => Response::group(vec![
+                error,
+                Response::note(at(position, name.len()), format!("{} {}", name, note)),
+            ]),
+
+            None => error,
+        }
+    }
+
+    // types an expression with a nested visitor, keeping its warnings and what it resolved
+    fn type_local(&mut self, local_visitor: &mut Visitor, e: &Expression) -> Result<Type, Response> {
+        let result = local_visitor.type_expression(e);
+        self.absorb(local_visitor);
+
+        result
+    }
+
+    // types the body of a fun, loop or branch with a nested visitor, as part of what it's in rather
+    // than a block of its own
+    fn type_local_body(&mut self, local_visitor: &mut Visitor, body: &[Statement]) -> Result<Type, Response> {
+        let result = local_visitor.type_block(body);
+        self.absorb(local_visitor);
+
+        result
+    }
+
+    // also going on from what's known where it left off, which it can still go on from itself
+    fn absorb(&mut self, local_visitor: &mut Visitor) {
+        self.warnings.append(&mut local_visitor.warnings);
+        self.resolved.extend(mem::take(&mut local_visitor.resolved));
+
+        let depth = self.symtab.depth();
+
+        self.flow = local_visitor.flow.clone();
+        self.flow.leave(depth);
+
+        for (exit, mut flow) in local_visitor.exits.drain(..) {
+            flow.leave(depth);
+            self.exits.push((exit, flow))
+        }
+    }
+
+    // what's known outside of a fun is as it was before it, except for the names it might give nil,
+    // and what's left from it isn't
+    fn leave_fun(&mut self, outside: Flow, exits: usize) {
+        let escaped = mem::replace(&mut self.flow, outside).escaped;
+
+        for slot in escaped {
+            self.flow.escape(slot)
+        }
+
+        self.exits.truncate(exits)
+    }
+
+    fn exit(&mut self, exit: Exit) {
+        self.exits.push((exit, self.flow.clone()));
+        self.flow.ended = true
+    }
+
+    // where a name is, for what's known about it
+    fn slot(&self, index: usize, env_index: usize) -> Slot {
+        (self.symtab.depth() - env_index, index)
+    }
+
+    // a name a module that couldn't be loaded might have given isn't anything in particular, so
+    // what uses it is left unchecked, with nothing more to say than what was said about the module
+    fn unloaded(&self, name: &str) -> Option<Response> {
+        let unloaded = match self.symtab.get_name(name) {
+            Some((index, env_index)) => self.unloaded.contains(&self.slot(index, env_index)),
+            None                     => self.incomplete,
+        };
+
+        if unloaded {
+            Some(Response::group(Vec::new()))
+        } else {
+            None
+        }
+    }
+
+    // a fresh variable for each of the type parameters of a fun
+    fn generics(&self, generics: &[String]) -> HashMap<String, Type> {
+        generics.iter().map(|name| (name.clone(), self.inference.fresh())).collect()
+    }
+
+    // a nested visitor where the type parameters in `generics` stand for their variables, for the
+    // body of a fun, which no loop outside of it can be left from
+    fn generic_local(&self, names: &[String], types: &[Type], generics: &HashMap<String, Type>) -> Result<Visitor, Response> {
+        let mut local_visitor = self.local(names, types);
+        local_visitor.loops     = 0;
+        local_visitor.flow      = self.flow.inside_fun(local_visitor.symtab.depth());
+
+        for (name, t) in generics {
+            local_visitor.typetab.set_alias(0, name, t.clone())?
+        }
+
+        Ok(local_visitor)
+    }
+
+    // every type parameter has to be left as general as it was declared, rather than be taken
+    // to be a type by the body
+    fn check_generics(&self, names: &[String], generics: &HashMap<String, Type>, what: &str, position: TokenPosition, span: usize) -> Result<(), Response> {
+        let mut seen: Vec<(usize, &String)> = Vec::new();
+
+        for name in names {
+            match self.resolve(&generics[name]) {
+                Type::Var(var) => match seen.iter().find(|other| other.0 == var) {
+                    Some(other) => return Err(Response::error(at(position, span), format!("{} isn't generic over both {} and {}, which are taken to be the same", what, other.1, name))),
+                    None        => seen.push((var, name)),
+                },
+
+                t => return Err(Response::error(at(position, span), format!("{} isn't generic over {}, which is taken to be: {}", what, name, t))),
+            }
+        }
+
+        Ok(())
+    }
+
+    // errors point at `position`, where the type was written or used
+    fn alias_type(&self, t: &Type, position: TokenPosition) -> Result<Type, Response> {
+        let mut acc_t = self.inference.resolve(t);
         let mut acc   = 0;
         loop {
             match acc_t {
@@ -35,690 +225,1712 @@ impl Visitor {
 
                 ref t => {
                     let t = match *t {
-                        Type::Identifier(ref name) => self.typetab.get_alias(name, 1)?,
-                        _                             => t.clone(),
+                        Type::Identifier(ref name) => match self.find_alias(name, &[]) {
+                            Some(t) => t,
+                            None    => return Err(Response::error(at(position, 1), format!("invalid type: {}", name))),
+                        },
+
+                        Type::Generic(ref name, ref args) => match self.typetab.find_alias(name) {
+                            Some(Type::Params(ref params, _)) if params.len() != args.len() => {
+                                return Err(Response::error(at(position, 1), format!("{} takes {} type arguments, given: {}", name, params.len(), args.len())))
+                            },
+
+                            Some(Type::Params(..)) => self.find_alias(name, args).unwrap(),
+                            Some(_)                => return Err(Response::error(at(position, 1), format!("type arguments given to non-generic: {}", name))),
+                            None                   => return Err(Response::error(at(position, 1), format!("invalid type: {}", name))),
+                        },
+
+                        _ => t.clone(),
+                    };
+
+                    // data types are told apart by their name, so they're kept as written
+                    let t = match t {
+                        Type::Data(..) => acc_t.clone(),
+                        t              => t,
                     };
 
                     let mut new_t = t;
-                    
+
                     for _ in 0 .. acc {
                         new_t = Type::Mut(Some(Rc::new(new_t.clone())))
                     }
-                    
+
                     return Ok(new_t)
                 },
             }
         }
     }
 
+    // what the named type stands for, with its type parameters given `args`, or fresh variables
+    // when it's used without them
+    fn find_alias(&self, name: &str, args: &[Type]) -> Option<Type> {
+        match self.typetab.find_alias(name)? {
+            Type::Params(ref params, ref t) => {
+                if !args.is_empty() && args.len() != params.len() {
+                    return None
+                }
+
+                let args = params.iter().enumerate().map(|(i, param)| {
+                    (param.clone(), args.get(i).cloned().unwrap_or_else(|| self.inference.fresh()))
+                }).collect();
+
+                Some(t.replace(&args))
+            },
+
+            t => if args.is_empty() {
+                Some(t)
+            } else {
+                None
+            },
+        }
+    }
+
+    // makes `found` fit `expected`, or tells `what` didn't and why
+    fn unify(&self, expected: &Type, found: &Type, location: Option<ErrorLocation>, what: &str) -> Result<(), Response> {
+        let alias = |name: &str, args: &[Type]| self.find_alias(name, args);
+
+        match self.inference.unify(expected, found, &alias) {
+            Ok(()) => Ok(()),
+
+            Err(Mismatch::Types(ref expected, ref found)) if unchecked(expected, found) => {
+                Err(Response::error(location, format!("{}, expected \"{}\", found: {} which might be nil, and has to be checked first", what, expected, found)))
+            },
+
+            Err(Mismatch::Types(expected, found)) => Err(Response::error(location, format!("{}, expected \"{}\", found: {}", what, expected, found))),
+            Err(Mismatch::Infinite(var, t))       => Err(Response::error(location, format!("{}, infinite type: {} = {}", what, Type::Var(var), t))),
+        }
+    }
+
+    fn resolve(&self, t: &Type) -> Type {
+        (*self.inference.resolve(t).unmut().unwrap()).clone()
+    }
+
+    // the variables of every type in scope except `slot`, which stay shared rather than generic
+    fn environment(&self, slot: usize) -> HashSet<usize> {
+        let mut vars = HashSet::new();
+
+        for (index, t) in self.typetab.types.borrow().iter().enumerate() {
+            if index != slot {
+                self.inference.free_vars(t, &mut vars)
+            }
+        }
+
+        let mut parent = self.typetab.parent.clone();
+
+        while let Some(typetab) = parent {
+            for t in typetab.types.borrow().iter() {
+                self.inference.free_vars(t, &mut vars)
+            }
+
+            parent = typetab.parent.clone()
+        }
+
+        vars
+    }
+
+    fn generalize(&self, index: usize) -> Result<(), Response> {
+        let t = self.typetab.get_type(index, 0)?;
+        self.inference.generalize(&t, &self.environment(index));
+
+        Ok(())
+    }
+
     pub fn visit_expression(&mut self, e: &Expression) -> Result<(), Response> {
         match *e {
+            Expression::Identifier(ref n, _) if self.unloaded(n).is_some() => Err(self.unloaded(n).unwrap()),
+
             Expression::Identifier(ref n, ref position) => {
-                match self.symtab.get_name(&*n) {
+                match self.symtab.get_name(n) {
                     Some(_) => Ok(()),
                     None    => Err(Response::error(Some(ErrorLocation::new(*position, n.len())), format!("unexpected use of: {}", n)))
                 }
             },
 
-            Expression::Block(ref statements) => {
-                for statement in statements {
-                    self.visit_statement(statement)?
-                }
+            _ => self.type_expression(e).map(|_| ()),
+        }
+    }
 
-                Ok(())
+    pub fn type_expression(&mut self, e: &Expression) -> Result<Type, Response> {
+        match *e {
+            Expression::Number(_) => Ok(Type::Number),
+            Expression::Str(_)    => Ok(Type::Str),
+            Expression::Bool(_)   => Ok(Type::Bool),
+            Expression::Identifier(ref n, _) if self.unloaded(n).is_some() => Err(self.unloaded(n).unwrap()),
+            Expression::Identifier(ref n, ref position) => match self.symtab.get_name(n) {
+                // which variant is meant only shows when it's called
+                Some(_) if self.symtab.get_overloads(n).is_some() => {
+                    let variants = self.symtab.get_overloads(n).unwrap();
+                    let error    = Response::error(at(*position, n.len()), format!("ambiguous use of overloaded fun: {}", n));
+
+                    Err(self.with_variants(error, n, &variants))
+                },
+
+                Some((i, env_index)) => {
+                    if let Some(t) = self.flow.narrowed.get(&self.slot(i, env_index)) {
+                        return Ok(t.clone())
+                    }
+
+                    let t = self.typetab.get_type(i, env_index)?;
+                    Ok(self.inference.instantiate(&t))
+                },
+
+                None => Err(Response::error(Some(ErrorLocation::new(*position, n.len())), format!("undefined type of: {}", n)))
             },
-            
-            Expression::Array(ref content) => {
+
+            Expression::Initialization(ref a) => {
+                let Initialization {ref id, ref values, ref position} = **a;
+                let id_t = self.type_expression(id)?;
+                let a    = self.alias_type(&id_t, *position)?;
+                if let Type::Struct(ref hash) = a {
+                    for def in values {
+                        let name = match *def.left {
+                            Expression::Identifier(ref n, _) => n,
+                            ref c                            => return Err(Response::error(at(def.position, 1), format!("can't set invalid key: {:?}", c))),
+                        };
+
+                        match hash.get(name) {
+                            Some(t) => {
+                                let right_t = self.type_expression(&def.right)?;
+                                self.unify(t, &right_t, at(def.position, name.len()), &format!("mismatched types of {}", name))?
+                            },
+
+                            None => return Err(Response::error(at(def.position, name.len()), format!("invalid initialization of: {}", name))),
+                        }
+                    }
+
+                    Ok(a)
+                } else {
+                    Err(Response::error(at(*position, 1), format!("can't initialize: {}", a)))
+                }
+            }
+
+            Expression::Array(ref content, ref position) => {
+                let element_t = self.inference.fresh();
+
                 for expression in content {
-                    self.visit_expression(expression)?
+                    let t = self.type_expression(expression)?;
+                    self.unify(&element_t, &t, at(expression.position().unwrap_or(*position), 1), "mismatching array elements")?
                 }
-                Ok(())
+
+                Ok(Type::Array(Rc::new(self.inference.resolve(&element_t)), Some(Expression::Number(content.len() as f64))))
             },
-            
-            Expression::Unless(ref a) => match **a {
-                Unless {ref base} => self.visit_expression(&Expression::If(Rc::new(base.clone()))),
+
+            Expression::Tuple(ref content, _) => {
+                let mut types = Vec::new();
+
+                for expression in content {
+                    types.push(self.type_expression(expression)?)
+                }
+
+                Ok(Type::Tuple(types))
             },
-            
-            Expression::MatchPattern(MatchPattern {ref matching, ref arms}) => {
-                self.visit_expression(&matching)?;
 
-                let mut arm_t = Type::Nil;
-                let mut flag  = false;
+            Expression::Index(Index {ref id, ref position, ref index, field}) => {
+                let a = self.type_expression(id)?;
 
-                for arm in arms {
-                    self.visit_arm(arm)?;
-                    
-                    if !self.type_expression(&*arm.param)?.equals(&self.type_expression(matching)?) {
-                        return Err(Response::error(None, format!("[location] mismatching arm parameter of match expression")))
-                    }
+                match *self.alias_type(&a, *position)?.unmut().unwrap() {
+                    Type::Array(ref t, _) => {
+                        if !field {
+                            let index_t = self.type_expression(index)?;
+                            self.unify(&Type::Number, &index_t, at(index.position().unwrap_or(*position), 1), "invalid array index")?
+                        }
 
-                    if !flag {
-                        let a = self.type_arm(arm)?;
+                        Ok((**t).clone())
+                    },
 
-                        arm_t = self.alias_type(&a)?;
-                        flag = true
-                    } else {
-                        if arm_t != self.type_arm(&arm)? {
-                            return Err(Response::error(None, format!("[location] mismatching arms of match expression")))
+                    Type::Struct(ref defs) => {
+                        if let Expression::Identifier(ref name, _) = **index {
+                            if let Some(a) = defs.get(name) {
+                                self.alias_type(a, *position)
+                            } else {
+                                Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("invalid key: {}", name)))
+                            }
+                        } else {
+                            Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't access struct with: {}", self.type_expression(index)?)))
                         }
                     }
+
+                    Type::Optional(_) => Err(Response::error(at(id.position().unwrap_or(*position), 1), format!("can't index {} which might be nil, it has to be checked first", self.resolve(&a)))),
+
+                    // nothing is known about it yet, but indexing makes it an array
+                    Type::Var(_) if !field => {
+                        let index_t = self.type_expression(index)?;
+                        self.unify(&Type::Number, &index_t, at(index.position().unwrap_or(*position), 1), "invalid array index")?;
+
+                        let element_t = self.inference.fresh();
+                        self.unify(&Type::Array(Rc::new(element_t.clone()), None), &a, at(*position, 1), "can't index")?;
+
+                        Ok(element_t)
+                    },
+
+                    Type::Var(_) => Err(Response::error(at(id.position().unwrap_or(*position), 1), "can't access fields of a value of unknown type, it needs an annotation".to_string())),
+
+                    ref t => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't index non-indexable: {}", t)))
                 }
+            },
 
-                Ok(())
-            }
+            Expression::Unless(ref a) => {
+                let Unless {ref base} = **a;
+                self.type_if(base, false, true)
+            },
 
-            Expression::If(ref a) => match **a {
-                If {ref condition, ref body, ref elses} => {
-                    self.visit_expression(condition)?;
+            Expression::If(ref a)            => self.type_if(a, false, false),
+            Expression::MatchPattern(ref m)  => self.type_match(m, false),
+            Expression::BinaryOp(ref op)     => self.type_binary_op(op),
+            Expression::UnaryOp(ref op)      => self.type_unary_op(op),
 
-                    if self.type_expression(condition)? != Type::Bool {
-                        return Err(Response::error(None, format!("[location] invalid non-bool if condition")))
-                    }
-                    
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &[]);
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
-
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
-                    
-                    local_visitor.visit_expression(&Expression::Block(body.clone()))?;
-
-                    if let &Some(ref elses) = elses {
-                        for arm in elses { 
-                            if let Some(ref condition) = arm.0 {
-                                local_visitor.visit_expression(&condition)?
-                            }
-                        }
+            Expression::Call(ref call) => {
+                let mut arg_types = Vec::new();
+
+                for arg in &call.args {
+                    arg_types.push(self.type_expression(arg)?)
+                }
+
+                let callee_t  = self.type_function(&call.callee, &arg_types)?;
+                let positions = call.args.iter().map(|arg| arg.position().unwrap_or(call.position)).collect::<Vec<_>>();
+
+                self.type_application(&callee_t, &arg_types, &positions, call.position, Application::Call(call.position, call.nesting()))
+            },
+
+            Expression::Fun(ref a)           => self.type_fun(a, None),
+            Expression::FunctionMatch(ref a) => self.type_function_match(a, None),
+            Expression::Block(ref statements) => self.type_block_expression(statements),
+
+            _ => Ok(Type::Nil),
+        }
+    }
+
+    fn type_binary_op(&mut self, op: &BinaryOp) -> Result<Type, Response> {
+        // a piped overloaded fun is resolved by what's piped into it
+        let (left_t, right_t) = match op.op {
+            Operand::PipeRight => {
+                let left_t = self.type_expression(&op.left)?;
+                (left_t.clone(), self.type_function(&op.right, &[left_t])?)
+            },
+
+            Operand::PipeLeft => {
+                let right_t = self.type_expression(&op.right)?;
+                (self.type_function(&op.left, slice::from_ref(&right_t))?, right_t)
+            },
+
+            // the right side of `and` only runs when the left one holds, and that of `or` when it doesn't
+            Operand::And | Operand::Or => {
+                let left_t = self.type_expression(&op.left)?;
+
+                let (holds, fails) = self.narrowing(&op.left);
+                let skipped        = self.flow.clone();
+
+                self.flow.narrow(if op.op == Operand::And { &holds } else { &fails });
+
+                let right_t = self.type_expression(&op.right);
+                self.flow.join(&skipped);
+
+                (left_t, right_t?)
+            },
+
+            _ => (self.type_expression(&op.left)?, self.type_expression(&op.right)?),
+        };
+
+        use self::Type::*;
+        use self::Operand::*;
+
+        let location = at(op.position, 1);
+
+        match op.op {
+            Add | Sub | Mul | Div | Mod | Pow => {
+                let numbers = self.unify(&Number, &left_t, None, "").and_then(|_| self.unify(&Number, &right_t, None, ""));
+
+                if numbers.is_ok() {
+                    return Ok(Number)
+                }
+
+                let (a, b) = (self.resolve(&left_t), self.resolve(&right_t));
+
+                Err(Response::error(location, match op.op {
+                    Add => format!("can't add {} and {}", a, b),
+                    Sub => format!("can't subtract {} and {}", a, b),
+                    Mul => format!("can't multiply {} and {}", a, b),
+                    Div => format!("can't divide {} and {}", a, b),
+                    Mod => format!("can't take {} modulo {}", a, b),
+                    _   => format!("can't put {} to the power of {}", a, b),
+                }))
+            },
+
+            Operand::Range => {
+                let numbers = self.unify(&Number, &left_t, None, "").and_then(|_| self.unify(&Number, &right_t, None, ""));
+
+                if numbers.is_ok() {
+                    return Ok(Type::Range)
+                }
+
+                Err(Response::error(location, format!("can't make a range from {} to {}", self.resolve(&left_t), self.resolve(&right_t))))
+            },
+
+            Equal | NEqual | Lt | Gt | LtEqual | GtEqual => match (self.resolve(&left_t), self.resolve(&right_t)) {
+                (Nil, Optional(_)) |
+                (Optional(_), Nil) if op.op == Equal || op.op == NEqual => Ok(Bool),
+
+                // one known not to be nil can still be checked again
+                (Nil, _) |
+                (_, Nil) if (op.op == Equal || op.op == NEqual) && self.nil_check(op).is_some() => Ok(Bool),
+
+                (Nil, a) |
+                (a, Nil) => Err(Response::error(location, format!("can't compare {} to nothing", a))),
+
+                (a, b) if op.op != Equal && op.op != NEqual && (matches!(a, Optional(_)) || matches!(b, Optional(_))) => {
+                    Err(Response::error(location, format!("can't order {} and {}, as nil has no order", a, b)))
+                },
+
+                // either side can be the one that might be nil
+                (a, b) => if self.unify(&left_t, &right_t, None, "").or_else(|_| self.unify(&right_t, &left_t, None, "")).is_ok() {
+                    Ok(Bool)
+                } else {
+                    Err(Response::error(location, format!("can't compare {} to {}", a, b)))
+                },
+            },
+
+            Concat => match (self.resolve(&left_t), self.resolve(&right_t)) {
+                (Str, Str)    |
+                (Number, Str) |
+                (Str, Bool)   |
+                (Str, Number) => Ok(Str),
+
+                // anything unknown is left to be whatever it turns out to be
+                (Var(_), _) |
+                (_, Var(_)) => Ok(Str),
+
+                (a, b) => Err(Response::error(location, format!("can't concat {} and {}", a, b))),
+            },
+
+            And | Xor | Or => {
+                let booleans = self.unify(&Bool, &left_t, None, "").and_then(|_| self.unify(&Bool, &right_t, None, ""));
+
+                if booleans.is_ok() {
+                    return Ok(Bool)
+                }
+
+                Err(Response::error(location, format!("`{}` takes booleans, found {} and {}", op.op.symbol(), self.resolve(&left_t), self.resolve(&right_t))))
+            },
+
+            // the left side without nil, or the right side when it's nil
+            Default => {
+                let t = self.inference.fresh();
+                self.unify(&Optional(Rc::new(t.clone())), &left_t, location, "`??` takes an optional value")?;
+
+                let result_t = match self.resolve(&right_t) {
+                    Optional(_) | Nil => Optional(Rc::new(t)),
+                    _                 => t,
+                };
+
+                self.unify(&result_t, &right_t, at(op.right.position().unwrap_or(op.position), 1), "mismatching default of `??`")?;
+
+                Ok(self.inference.resolve(&result_t))
+            },
+
+            PipeRight => self.type_application(&right_t, &[left_t], &[op.position], op.position, Application::Pipe(op.position)),
+            PipeLeft  => self.type_application(&left_t, &[right_t], &[op.position], op.position, Application::Pipe(op.position)),
+        }
+    }
+
+    // the type of what's called with `arg_types`, picking the variant if it's overloaded
+    fn type_function(&mut self, function: &Expression, arg_types: &[Type]) -> Result<Type, Response> {
+        match self.overloaded(function) {
+            Some((name, position, variants)) => self.resolve_overload(&name, position, &variants, arg_types),
+            None                             => self.type_expression(function),
+        }
+    }
+
+    fn overloaded(&self, e: &Expression) -> Option<(String, TokenPosition, Vec<String>)> {
+        match *e {
+            Expression::Identifier(ref name, position) => self.symtab.get_overloads(name).map(|variants| (name.clone(), position, variants)),
+            _                                          => None,
+        }
+    }
+
+    fn variant_type(&self, variant: &str) -> Result<Type, Response> {
+        let (index, env_index) = self.symtab.get_name(variant).unwrap();
+        let t = self.typetab.get_type(index, env_index)?;
+
+        Ok(self.inference.instantiate(&t))
+    }
+
+    // picks the one variant of `name` that takes `arg_types`, remembering it for codegen. variants
+    // taking just those are preferred to ones that would only be partly applied
+    fn resolve_overload(&mut self, name: &str, position: TokenPosition, variants: &[String], arg_types: &[Type]) -> Result<Type, Response> {
+        let alias = |name: &str, args: &[Type]| self.find_alias(name, args);
+        let args  = Type::Fun(arg_types.to_vec(), None);
+
+        let mut candidates = Vec::new();
+
+        for &exact in &[true, false] {
+            for variant in variants {
+                let t = self.variant_type(variant)?;
+
+                if let Type::Fun(ref params, _) = self.resolve(&t) {
+                    let takes = if exact { params.len() == arg_types.len() } else { params.len() > arg_types.len() };
+
+                    if takes && self.inference.fits(&Type::Fun(params[.. arg_types.len()].to_vec(), None), &args, &alias) {
+                        candidates.push((variant.clone(), t.clone()))
                     }
+                }
+            }
+
+            if !candidates.is_empty() {
+                break
+            }
+        }
+
+        if candidates.len() == 1 {
+            let (variant, t) = candidates.pop().unwrap();
+            self.resolved.names.insert(position, variant);
+
+            return Ok(t)
+        }
+
+        let found: Vec<String> = arg_types.iter().map(|t| self.resolve(t).to_string()).collect();
+
+        let (message, variants) = if candidates.is_empty() {
+            (format!("no variant of {} takes: {}", name, found.join(" ")), variants.to_vec())
+        } else {
+            (format!("ambiguous call of {} with: {}", name, found.join(" ")), candidates.into_iter().map(|candidate| candidate.0).collect())
+        };
+
+        Err(self.with_variants(Response::error(at(position, name.len()), message), name, &variants))
+    }
+
+    // notes where each of the variants of `name` is, and what it takes
+    fn with_variants(&self, error: Response, name: &str, variants: &[String]) -> Response {
+        let mut responses = vec![error];
+
+        for variant in variants {
+            let t = self.variant_type(variant).map(|t| self.resolve(&t).to_string()).unwrap_or_default();
+            responses.push(Response::note(locate(self.symtab.get_position(variant), name.len()), format!("could be {}", t)))
+        }
+
+        Response::group(responses)
+    }
+
+    // what calling a fun of `function_t` with `arg_types` gives, one fun at a time: given fewer args than
+    // it takes, it's a fun taking the rest, and given more, what it returns is called with the rest.
+    // how many each of those funs took is remembered when it's more than a plain call
+    fn type_application(&mut self, function_t: &Type, arg_types: &[Type], positions: &[TokenPosition], position: TokenPosition, site: Application) -> Result<Type, Response> {
+        use self::Type::*;
+
+        let mut function_t = function_t.clone();
+        let mut arities    = Vec::new();
+        let mut given      = 0;
+
+        let t = loop {
+            let rest = &arg_types[given ..];
+
+            match (*self.alias_type(&function_t, position)?.unmut().unwrap()).clone() {
+                Fun(ref params, _) if params.is_empty() && !rest.is_empty() => {
+                    return Err(Response::error(at(position, 1), format!("function given {} arguments, expected: 0", rest.len())))
+                },
+
+                Fun(ref params, ref retty) => {
+                    let taken = params.len().min(rest.len());
+
+                    for (param, (t, position)) in params.iter().zip(rest.iter().zip(&positions[given ..])) {
+                        self.unify(param, t, at(*position, 1), "mismatching argument")?
+                    }
+
+                    arities.push(params.len());
+                    given += taken;
+
+                    let retty = match *retty {
+                        Some(ref retty) => self.inference.resolve(retty),
+                        None            => Nil,
+                    };
+
+                    if taken < params.len() {
+                        let remaining = params[taken ..].iter().map(|param| self.inference.resolve(param)).collect();
+                        break Fun(remaining, Some(Rc::new(retty)))
+                    }
+
+                    if given == arg_types.len() {
+                        break retty
+                    }
+
+                    function_t = retty
+                },
+
+                // calling it is what tells it's a fun
+                Var(_) => {
+                    let retty = self.inference.fresh();
+                    self.unify(&function_t, &Fun(rest.to_vec(), Some(Rc::new(retty.clone()))), at(position, 1), "mismatching call")?;
+
+                    arities.push(rest.len());
+
+                    break self.inference.resolve(&retty)
+                },
+
+                // what was called before returned something that can't take the rest
+                _ if given > 0 => {
+                    let takes: usize = arities.iter().sum();
+                    return Err(Response::error(at(position, 1), format!("function given {} arguments, expected: {}", arg_types.len(), takes)))
+                },
+
+                ref c => return Err(Response::error(at(position, 1), format!("can't call non-fun: {}", c)))
+            }
+        };
+
+        if arities != [arg_types.len()] {
+            self.resolved.applications.insert(site, arities);
+        }
+
+        Ok(t)
+    }
+
+    fn type_unary_op(&mut self, op: &UnaryOp) -> Result<Type, Response> {
+        let t = self.type_expression(&op.expr)?;
+
+        let (expected, message) = match op.op {
+            UnaryOperand::Neg => (Type::Number, "can't negate"),
+            UnaryOperand::Not => (Type::Bool, "can't take `not` of"),
+        };
+
+        if self.unify(&expected, &t, None, "").is_ok() {
+            Ok(expected)
+        } else {
+            Err(Response::error(at(op.position, 1), format!("{} {}", message, self.resolve(&t))))
+        }
+    }
+
+    // as a statement, the branches of an if don't have to agree. `negated` is for unless
+    fn type_if(&mut self, base: &If, statement: bool, negated: bool) -> Result<Type, Response> {
+        let If {ref condition, ref body, ref elses, ref position} = *base;
+
+        self.condition(condition, Some(*position), "if")?;
+
+        // names checked against nil aren't nil in the branches that run when they aren't, nor after
+        // the if when the branches where they could be all end with a `return`, `break` or `continue`
+        let (mut holds, mut fails) = self.narrowing(condition);
+
+        if negated {
+            mem::swap(&mut holds, &mut fails)
+        }
+
+        let (body_t, mut after) = self.type_branch(body, &holds)?;
+        self.flow.narrow(&fails);
+
+        if let Some(elses) = elses {
+            for arm in elses {
+                let (holds, fails) = match arm.0 {
+                    Some(ref condition) => {
+                        self.condition(condition, Some(*position), "elif")?;
+                        self.narrowing(condition)
+                    },
+
+                    None => (Vec::new(), Vec::new()),
+                };
+
+                let (arm_t, end) = self.type_branch(&arm.1, &holds)?;
+                after.join(&end);
+
+                // nothing goes past an else
+                if arm.0.is_some() {
+                    self.flow.narrow(&fails)
+                } else {
+                    self.flow.ended = true
+                }
+
+                if !statement {
+                    self.unify(&body_t, &arm_t, at(*position, 2), "mismatching branches of if expression")?
+                }
+            }
+        }
+
+        after.join(&self.flow);
+        self.flow = after;
+
+        if statement {
+            Ok(Type::Nil)
+        } else {
+            Ok(self.inference.resolve(&body_t))
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression, fallback: Option<TokenPosition>, what: &str) -> Result<(), Response> {
+        let t = self.type_expression(condition)?;
+        self.unify(&Type::Bool, &t, locate(condition.position().or(fallback), 1), &format!("invalid non-bool {} condition", what))
+    }
+
+    // the type of a branch where the names in `narrowed` aren't nil, and what's known once it's run
+    fn type_branch(&mut self, body: &[Statement], narrowed: &[(Slot, Type)]) -> Result<(Type, Flow), Response> {
+        let outside = self.flow.clone();
+        self.flow.narrow(narrowed);
+
+        let mut local_visitor = self.local(&[], &[]);
+        let t = self.type_local_body(&mut local_visitor, body);
+
+        Ok((t?, mem::replace(&mut self.flow, outside)))
+    }
+
+    // the names `condition` checks against nil, with what they are when they aren't, for when it
+    // holds and for when it doesn't
+    fn narrowing(&self, condition: &Expression) -> (Narrowed, Narrowed) {
+        let op = match *condition {
+            Expression::BinaryOp(ref op) => op,
+
+            Expression::UnaryOp(ref op) => return match op.op {
+                UnaryOperand::Not => {
+                    let (holds, fails) = self.narrowing(&op.expr);
+                    (fails, holds)
+                },
+
+                _ => (Vec::new(), Vec::new()),
+            },
+
+            _ => return (Vec::new(), Vec::new()),
+        };
+
+        match op.op {
+            Operand::And => {
+                let (mut holds, _) = self.narrowing(&op.left);
+                holds.extend(self.narrowing(&op.right).0);
+
+                (holds, Vec::new())
+            },
+
+            Operand::Or => {
+                let (_, mut fails) = self.narrowing(&op.left);
+                fails.extend(self.narrowing(&op.right).1);
+
+                (Vec::new(), fails)
+            },
+
+            Operand::NEqual | Operand::Equal => {
+                let checked = self.nil_check(op).into_iter().collect();
+
+                if op.op == Operand::NEqual {
+                    (checked, Vec::new())
+                } else {
+                    (Vec::new(), checked)
+                }
+            },
+
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    // where the name `op` compares to nil is, and what it is when it isn't nil, if it's an optional
+    // that can be checked. a mutable one that funs might give nil can't be
+    fn nil_check(&self, op: &BinaryOp) -> Option<(Slot, Type)> {
+        let name = match (&*op.left, &*op.right) {
+            (Expression::Identifier(name, _), Expression::Nil) |
+            (Expression::Nil, Expression::Identifier(name, _)) => name,
+            _                                                  => return None,
+        };
+
+        let (index, env_index) = self.symtab.get_name(name)?;
+
+        let slot = self.slot(index, env_index);
+        let t    = self.inference.resolve(&self.typetab.get_type(index, env_index).ok()?);
+
+        match *t.unmut().unwrap() {
+            Type::Optional(_) if t.is_mut() && self.flow.escaped.contains(&slot) => None,
+            Type::Optional(ref inner) if t.is_mut()                             => Some((slot, Type::Mut(Some(inner.clone())))),
+            Type::Optional(ref inner)                                           => Some((slot, (**inner).clone())),
+            _                                                                   => None,
+        }
+    }
+
+    // a mutable optional given something that can't be nil isn't nil until it's given something that
+    // can be. a fun that gives it something that can might be called anywhere, so then it never is
+    fn assigned(&mut self, slot: Slot, t: &Type, right_t: &Type) {
+        let t = self.resolve(t);
+
+        let inner = match *t.unmut().unwrap() {
+            Type::Optional(ref inner) => inner.clone(),
+            _                         => return,
+        };
+
+        match *self.resolve(right_t).unmut().unwrap() {
+            Type::Optional(_) | Type::Nil | Type::Var(_) if slot.0 < self.flow.captured => self.flow.escape(slot),
+
+            Type::Optional(_) | Type::Nil | Type::Var(_) => {
+                self.flow.narrowed.remove(&slot);
+            },
+
+            _ if self.flow.escaped.contains(&slot) => (),
+            _                                      => {
+                self.flow.narrowed.insert(slot, Type::Mut(Some(inner)));
+            },
+        }
+    }
+
+    fn type_match(&mut self, m: &MatchPattern, statement: bool) -> Result<Type, Response> {
+        let MatchPattern {ref matching, ref arms, ..} = *m;
+
+        let matching_t = self.type_expression(matching)?;
+        let arm_t      = self.inference.fresh();
+
+        // any of the arms might run, or none of them
+        let outside   = self.flow.clone();
+        let mut after = self.flow.clone();
+
+        for arm in arms {
+            let t = self.type_arm(arm, slice::from_ref(&matching_t));
+
+            after.join(&mem::replace(&mut self.flow, outside.clone()));
+            let t = t?;
+
+            if !statement {
+                self.unify(&arm_t, &t, at(arm.position, 1), "mismatching arms of match expression")?
+            }
+        }
+
+        self.flow = after;
+
+        // a match statement that matches nothing does nothing, an expression would have no value
+        self.check_arms(arms, 1, m.position, "match", !statement)?;
+
+        if statement {
+            Ok(Type::Nil)
+        } else {
+            Ok(self.inference.resolve(&arm_t))
+        }
+    }
+
+    // warns about arms the ones above already cover, and tells what no arm covers
+    fn check_arms(&mut self, arms: &[MatchArm], arity: usize, position: TokenPosition, what: &str, exhaustive: bool) -> Result<(), Response> {
+        let mut matrix = Matrix::new();
+
+        for arm in arms {
+            let row: Vec<Shape> = arm.params.iter().map(|param| self.shape(param, &mut matrix)).collect();
+
+            if matrix.uncovered(&row).is_none() {
+                self.warnings.push(Response::warning(at(arm.position, 1), "unreachable arm, the ones above already cover it".to_string()))
+            }
+
+            // a guard might not hold, so the arm can't be counted on
+            if arm.guard.is_none() {
+                matrix.push(row)
+            }
+        }
+
+        if let Some(uncovered) = matrix.uncovered(&vec![Shape::Anything; arity]) {
+            let message = format!("non-exhaustive {}, not covered: {}", what, describe(&uncovered));
+
+            if exhaustive {
+                return Err(Response::error(at(position, what.len()), message))
+            }
+
+            self.warnings.push(Response::warning(at(position, what.len()), message))
+        }
+
+        Ok(())
+    }
+
+    fn shape(&self, pattern: &Pattern, matrix: &mut Matrix) -> Shape {
+        match *pattern {
+            Pattern::Wildcard(_) | Pattern::Binding(..) => Shape::Anything,
+
+            Pattern::Literal(ref literal, _) => match *literal {
+                Expression::Bool(b)    => Shape::Constructor(b.to_string(), Vec::new()),
+                Expression::Number(n)  => Shape::Literal(n.to_string()),
+                Expression::Str(ref s) => Shape::Literal(format!("{:?}", s)),
+                ref c                  => Shape::Literal(format!("{:?}", c)),
+            },
+
+            Pattern::Array(ref content, ref rest, _) => Shape::Array(content.iter().map(|pattern| self.shape(pattern, matrix)).collect(), rest.is_some()),
+
+            // a struct has a single way to be built, with every field in a fixed order
+            Pattern::Struct(ref name, ref fields, _) => {
+                let mut names: Vec<String> = match self.find_alias(name, &[]) {
+                    Some(Type::Struct(struct_fields)) => struct_fields.keys().cloned().collect(),
+                    _                                 => fields.iter().map(|field| field.0.clone()).collect(),
+                };
+
+                names.sort();
+                matrix.declare(&[(name.clone(), names.len())]);
+
+                let inner = names.iter().map(|field| match fields.iter().find(|f| f.0 == *field) {
+                    Some((_, pattern)) => self.shape(pattern, matrix),
+                    None               => Shape::Anything,
+                }).collect();
+
+                Shape::Constructor(name.clone(), inner)
+            },
+
+            Pattern::Variant(ref name, ref fields, _) => {
+                if let Some((_, variants)) = self.variants_of(name) {
+                    matrix.declare(&variants)
+                }
+
+                Shape::Constructor(name.clone(), fields.iter().map(|pattern| self.shape(pattern, matrix)).collect())
+            },
+
+            Pattern::Tuple(ref content, _) => Shape::Tuple(content.iter().map(|pattern| self.shape(pattern, matrix)).collect()),
+
+            Pattern::Or(ref alternatives) => Shape::Or(alternatives.iter().map(|pattern| self.shape(pattern, matrix)).collect()),
+        }
+    }
+
+    // the type of the constructor named `variant`, with every variant of its data type and how much each carries
+    fn variants_of(&self, variant: &str) -> Option<(Type, Vec<(String, usize)>)> {
+        let (index, env_index) = self.symtab.get_name(variant)?;
+        let t = self.inference.instantiate(&self.typetab.get_type(index, env_index).ok()?);
+
+        let data_t = match self.resolve(&t) {
+            Type::Fun(_, Some(retty)) => (*retty).clone(),
+            t                         => t,
+        };
+
+        let data = match data_t {
+            Type::Identifier(ref name)        => self.find_alias(name, &[])?,
+            Type::Generic(ref name, ref args) => self.find_alias(name, args)?,
+            _                                 => return None,
+        };
+
+        match data {
+            Type::Data(_, ref variants) if variants.iter().any(|v| v.0 == variant) => {
+                Some((t, variants.iter().map(|(name, fields)| (name.clone(), fields.len())).collect()))
+            },
+
+            _ => None,
+        }
+    }
+
+    pub fn type_arm(&mut self, arm: &MatchArm, param_types: &[Type]) -> Result<Type, Response> {
+        if arm.params.len() != param_types.len() {
+            return Err(Response::error(at(arm.position, 1), format!("wrong number of patterns in arm, expected: {}, found: {}", param_types.len(), arm.params.len())))
+        }
+
+        let mut bindings = Vec::new();
+
+        for (param, t) in arm.params.iter().zip(param_types) {
+            self.type_pattern(param, t, &mut bindings)?
+        }
+
+        let (names, types): (Vec<String>, Vec<Type>) = bindings.into_iter().unzip();
+        let mut local_visitor = self.local(&names, &types);
+
+        if let Some(ref guard) = arm.guard {
+            let guard_t = self.type_local(&mut local_visitor, guard)?;
+            self.unify(&Type::Bool, &guard_t, locate(guard.position().or(Some(arm.position)), 1), "invalid non-bool guard")?
+        }
+
+        self.type_local(&mut local_visitor, &arm.body)
+    }
+
+    // checks that `pattern` can match a `t`, adding the names it binds and their types
+    fn type_pattern(&mut self, pattern: &Pattern, t: &Type, bindings: &mut Vec<(String, Type)>) -> Result<(), Response> {
+        match *pattern {
+            Pattern::Wildcard(_) => Ok(()),
+
+            Pattern::Binding(ref name, position) => {
+                if bindings.iter().any(|binding| binding.0 == *name) {
+                    return Err(Response::error(at(position, name.len()), format!("bound more than once in the same pattern: {}", name)))
+                }
+
+                bindings.push((name.clone(), t.clone()));
+
+                Ok(())
+            },
+
+            Pattern::Literal(ref literal, position) => {
+                let literal_t = self.type_expression(literal)?;
+                self.unify(t, &literal_t, at(position, 1), "mismatching pattern")
+            },
+
+            Pattern::Array(ref content, ref rest, position) => {
+                let element_t = self.inference.fresh();
+                self.unify(t, &Type::Array(Rc::new(element_t.clone()), None), at(position, 1), "mismatching pattern")?;
+
+                for pattern in content {
+                    self.type_pattern(pattern, &element_t, bindings)?
+                }
+
+                match *rest {
+                    Some(Some(ref rest)) => self.type_pattern(&Pattern::Binding(rest.clone(), position), &Type::Array(Rc::new(element_t), None), bindings),
+                    _                    => Ok(()),
+                }
+            },
+
+            // without a name, the fields are the ones of whatever struct is being matched
+            Pattern::Struct(ref name, ref fields, position) if name.is_empty() => {
+                let struct_fields = match *self.alias_type(&self.resolve(t), position)?.unmut().unwrap() {
+                    Type::Struct(ref struct_fields) => struct_fields.clone(),
+                    ref c                           => return Err(Response::error(at(position, 1), format!("can't take fields out of non-struct: {}", c))),
+                };
+
+                for (field, pattern) in fields {
+                    match struct_fields.get(field) {
+                        Some(field_t) => self.type_pattern(pattern, field_t, bindings)?,
+                        None          => return Err(Response::error(at(pattern.position(), field.len()), format!("no such field in {}: {}", t, field))),
+                    }
+                }
+
+                Ok(())
+            },
+
+            Pattern::Struct(ref name, ref fields, position) => {
+                let struct_fields = match self.find_alias(name, &[]) {
+                    Some(Type::Struct(struct_fields)) => struct_fields,
+                    _                                 => return Err(Response::error(at(position, name.len()), format!("can't match on non-struct: {}", name))),
+                };
+
+                self.unify(t, &Type::Identifier(name.clone()), at(position, name.len()), "mismatching pattern")?;
+
+                for (field, pattern) in fields {
+                    match struct_fields.get(field) {
+                        Some(field_t) => self.type_pattern(pattern, field_t, bindings)?,
+                        None          => return Err(Response::error(at(pattern.position(), field.len()), format!("no such field in {}: {}", name, field))),
+                    }
+                }
+
+                Ok(())
+            },
+
+            Pattern::Variant(ref name, ref fields, position) => {
+                let constructor_t = match self.variants_of(name) {
+                    Some((t, _)) => t,
+                    None         => return Err(Response::error(at(position, name.len()), format!("no variant named: {}", name))),
+                };
+
+                let (field_types, data_t) = match self.resolve(&constructor_t) {
+                    Type::Fun(params, Some(retty)) => (params, (*retty).clone()),
+                    t                              => (Vec::new(), t),
+                };
+
+                if fields.len() != field_types.len() {
+                    return Err(Response::error(at(position, name.len()), format!("{} carries {} values, matched with: {}", name, field_types.len(), fields.len())))
+                }
+
+                self.unify(t, &data_t, at(position, name.len()), "mismatching pattern")?;
+
+                for (pattern, field_t) in fields.iter().zip(&field_types) {
+                    self.type_pattern(pattern, field_t, bindings)?
+                }
+
+                Ok(())
+            },
+
+            Pattern::Tuple(ref content, position) => {
+                let types: Vec<Type> = content.iter().map(|_| self.inference.fresh()).collect();
+                self.unify(t, &Type::Tuple(types.clone()), at(position, 1), "mismatching pattern")?;
+
+                for (pattern, t) in content.iter().zip(&types) {
+                    self.type_pattern(pattern, t, bindings)?
+                }
+
+                Ok(())
+            },
+
+            // every alternative binds the same names, to the same types
+            Pattern::Or(ref alternatives) => {
+                let mut first: Option<Vec<(String, Type)>> = None;
+
+                for alternative in alternatives {
+                    let mut alternative_bindings = Vec::new();
+                    self.type_pattern(alternative, t, &mut alternative_bindings)?;
+
+                    match first {
+                        Some(ref first) => {
+                            let same = first.len() == alternative_bindings.len() && first.iter().all(|a| alternative_bindings.iter().any(|b| a.0 == b.0));
+
+                            if !same {
+                                return Err(Response::error(at(alternative.position(), 1), "alternatives of a pattern have to bind the same names".to_string()))
+                            }
+
+                            for (name, t) in &alternative_bindings {
+                                let first_t = &first.iter().find(|a| a.0 == *name).unwrap().1;
+                                self.unify(first_t, t, at(alternative.position(), 1), &format!("mismatching types of {} in alternatives", name))?
+                            }
+                        },
+
+                        None => first = Some(alternative_bindings),
+                    }
+                }
+
+                for (name, t) in first.unwrap_or_default() {
+                    self.type_pattern(&Pattern::Binding(name, pattern.position()), &t, bindings)?
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    // the type of the last expression or of what's returned, which all have to agree
+    fn type_block(&mut self, statements: &[Statement]) -> Result<Type, Response> {
+        let mut block_t: Option<Type> = None;
+        let mut errors = Vec::new();
+
+        for (acc, statement) in statements.iter().enumerate() {
+            let result = match *statement {
+                Statement::Expression(ref expr) if acc + 1 == statements.len() => self.type_expression(expr).map(|t| Some((t, expr.position()))),
+
+                Statement::Return(ref expr) => {
+                    let result = match *expr {
+                        Some(ref expr) => self.type_expression(expr).map(|t| Some((t, expr.position()))),
+                        None           => Ok(Some((Type::Nil, None))),
+                    };
+
+                    self.exit(Exit::Return);
+                    result
+                },
+
+                ref statement => self.visit_statement(statement).map(|_| None),
+            };
+
+            match result {
+                Ok(Some((t, position))) => match block_t {
+                    Some(ref block_t) => if let Err(e) = self.unify(block_t, &t, locate(position, 1), "mismatching return types of block") {
+                        errors.push(e)
+                    },
+
+                    None => block_t = Some(t),
+                },
+
+                Ok(None) => (),
+                Err(e)   => errors.push(e),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Response::group(errors))
+        }
+
+        match block_t {
+            Some(t) => Ok(self.inference.resolve(&t)),
+            None    => Ok(Type::Nil),
+        }
+    }
+
+    // parameters without a type get a variable each. a named fun's own slot is typed before
+    // its body is, through `recursive`, so it can call itself
+    fn type_fun(&mut self, fun: &Fun, recursive: Option<usize>) -> Result<Type, Response> {
+        let Fun {ref t, ref name, ref generics, ref params, ref body, ref position} = *fun;
+
+        let generic_types = self.generics(generics);
+
+        let mut param_names = Vec::new();
+        let mut param_types = Vec::new();
+
+        for param in params {
+            param_names.push(param.name.clone());
+            param_types.push(match param.t {
+                Type::Undefined => self.inference.fresh(),
+                ref t           => t.replace(&generic_types),
+            })
+        }
+
+        let retty = match *t {
+            Some(ref t) => t.replace(&generic_types),
+            None        => self.inference.fresh(),
+        };
+
+        let fun_t = Type::Fun(param_types.clone(), Some(Rc::new(retty.clone())));
+
+        if let Some(index) = recursive {
+            self.typetab.set_type(index, 0, fun_t.clone())?
+        }
+
+        let (outside, exits) = (self.flow.clone(), self.exits.len());
+
+        let mut local_visitor = self.generic_local(&param_names, &param_types, &generic_types)?;
+        let body_t = self.type_local_body(&mut local_visitor, body);
+
+        self.leave_fun(outside, exits);
+        let body_t = body_t?;
+
+        match *name {
+            Some(Expression::Identifier(ref name, position)) => {
+                self.unify(&retty, &body_t, at(position, name.len()), &format!("mismatching return types of fun: {}", name))?;
+                self.check_generics(generics, &generic_types, &format!("fun {}", name), position, name.len())?
+            },
+
+            _ => {
+                self.unify(&retty, &body_t, at(*position, 3), "mismatching return types of fun expression")?;
+                self.check_generics(generics, &generic_types, "fun expression", *position, 3)?
+            },
+        }
+
+        Ok(self.inference.resolve(&fun_t))
+    }
+
+    // one parameter, matched by every arm
+    fn type_function_match(&mut self, function: &FunctionMatch, recursive: Option<usize>) -> Result<Type, Response> {
+        let FunctionMatch {ref t, ref name, ref generics, ref arms, ..} = *function;
+
+        let generic_types = self.generics(generics);
+
+        let param_types: Vec<Type> = (0 .. function.arity()).map(|_| self.inference.fresh()).collect();
+        let retty = match *t {
+            Some(ref t) => t.replace(&generic_types),
+            None        => self.inference.fresh(),
+        };
+
+        let fun_t = Type::Fun(param_types.clone(), Some(Rc::new(retty.clone())));
+
+        if let Some(index) = recursive {
+            self.typetab.set_type(index, 0, fun_t.clone())?
+        }
+
+        let (what, function_name, position, span) = match *name {
+            Some(Expression::Identifier(ref name, position)) => (format!("mismatching return types of function: {}", name), format!("function {}", name), position, name.len()),
+            _                                                => ("mismatching return types of function expression".to_string(), "function expression".to_string(), function.position, 8),
+        };
+
+        let (outside, exits) = (self.flow.clone(), self.exits.len());
+        let mut local_visitor = self.generic_local(&[], &[], &generic_types)?;
+
+        let result = arms.iter().map(|arm| {
+            let arm_t = local_visitor.type_arm(arm, &param_types)?;
+            local_visitor.unify(&retty, &arm_t, at(arm.position, 1), &what)
+        }).collect::<Result<Vec<_>, _>>().and_then(|_| local_visitor.check_arms(arms, function.arity(), function.position, "function", true));
+
+        self.absorb(&mut local_visitor);
+        self.leave_fun(outside, exits);
+        result?;
+
+        self.check_generics(generics, &generic_types, &function_name, position, span)?;
+
+        Ok(self.inference.resolve(&fun_t))
+    }
+
+    fn type_method(&mut self, method: &Function) -> Result<(String, Type, TokenPosition), Response> {
+        let (name, t, at_method) = match *method {
+            Function::Fun(ref fun)        => (&fun.name, self.type_fun(fun, None)?, fun.position),
+            Function::Match(ref function) => (&function.name, self.type_function_match(function, None)?, function.position),
+        };
+
+        let (name, position) = match *name {
+            Some(Expression::Identifier(ref name, position)) => (name.clone(), position),
+            ref c                                            => return Err(Response::error(at(at_method, 1), format!("invalid method name: {:?}", c))),
+        };
+
+        Ok((name, t, position))
+    }
+
+    // a named fun, or another variant of the funs already named so in this scope when it takes
+    // something none of them do
+    fn visit_function(&mut self, name: &str, position: TokenPosition, type_function: &mut dyn FnMut(&mut Visitor, usize) -> Result<Type, Response>) -> Result<(), Response> {
+        let (variant, index) = match self.symtab.get_name(name) {
+            Some(_) => match self.symtab.add_variant(name, position) {
+                Some(declared) => declared,
+                None           => return Err(self.already_in_use("name", name, position)),
+            },
+
+            None => (name.to_owned(), self.symtab.add_function(name, position)),
+        };
+
+        if index >= self.typetab.size() {
+            self.typetab.grow()
+        }
+
+        if variant != name {
+            self.resolved.names.insert(position, variant.clone());
+        }
+
+        type_function(self, index)?;
+        self.generalize(index)?;
+
+        let params = |t: &Type| match *t {
+            Type::Fun(ref params, _) => Type::Fun(params.clone(), None),
+            ref t                    => t.clone(),
+        };
+
+        let t = params(&self.resolve(&self.variant_type(&variant)?));
+
+        for other in self.symtab.get_overloads(name).unwrap_or_default() {
+            if other == variant {
+                continue
+            }
+
+            let alias   = |name: &str, args: &[Type]| self.find_alias(name, args);
+            let other_t = params(&self.resolve(&self.variant_type(&other)?));
+
+            if self.inference.fits(&other_t, &t, &alias) {
+                self.symtab.remove_variant(name, &variant);
+
+                return Err(Response::group(vec![
+                    Response::error(at(position, name.len()), format!("overload of {} takes the same as another one: {}", name, self.resolve(&self.variant_type(&variant)?))),
+                    Response::note(locate(self.symtab.get_position(&other), name.len()), format!("which is this one: {}", self.resolve(&self.variant_type(&other)?))),
+                ]))
+            }
+        }
+
+        Ok(())
+    }
+
+    // funs without a return type return nil, so `fun number` and `fun number -> nil` are the same method
+    fn same_method(&self, a: &Type, b: &Type) -> bool {
+        self.unify(a, b, None, "").is_ok()
+    }
+
+    // brings the exposed names of an already loaded module into scope
+    fn visit_import(&mut self, import: &Import) -> Result<(), Response> {
+        let name     = module_name(&import.from)?;
+        let position = module_position(&import.from);
+
+        let module = match self.modules.get(&name) {
+            Some(module) => module.clone(),
+            None         => return self.visit_unloaded(import, &name, position),
+        };
+
+        // types of what's exposed might refer to any of the module's structs
+        for (alias, t) in &module.aliases {
+            self.typetab.set_alias(0, alias, t.clone())?
+        }
+
+        let names = match import.expose {
+            Expose::Nothing => vec!((last_segment(&name).to_owned(), module.namespace())),
+            Expose::Everything => module.exports.clone(),
+
+            Expose::Specifically(ref names) => {
+                let mut exposed = Vec::new();
+
+                for exposed_name in names {
+                    if module.export(exposed_name).is_none() {
+                        return Err(Response::error(at(position, name.len()), format!("module {} has nothing named: {}", name, exposed_name)))
+                    }
+
+                    for variant in module.exported_with_variants(exposed_name) {
+                        let t = module.export(&variant).cloned().unwrap_or(Type::Undefined);
+                        exposed.push((variant, t))
+                    }
+                }
+
+                exposed
+            },
+        };
+
+        // an overloaded fun comes with all of its variants, which calls pick from as usual
+        for (overloaded, variants) in &module.overloads {
+            if import.expose != Expose::Nothing && names.iter().any(|exposed| exposed.0 == *overloaded) {
+                self.symtab.overloads.borrow_mut().insert(overloaded.clone(), variants.clone());
+            }
+        }
+
+        for (exposed_name, t) in names {
+            if self.symtab.get_name(&exposed_name).is_some() {
+                return Err(self.already_in_use("imported name", &exposed_name, position))
+            }
+
+            let index = self.symtab.add_name_at(&exposed_name, position);
+            if index >= self.typetab.size() {
+                self.typetab.grow()
+            }
+
+            self.imported.insert(index);
+            self.typetab.set_type(index, 0, t)?
+        }
+
+        Ok(())
+    }
 
-                    Ok(())
-                }
-            }
+    // the module couldn't be loaded, which the loader already said, so the names it would give are
+    // there but unknown, and when what it gives isn't known either, so is every name that isn't there
+    fn visit_unloaded(&mut self, import: &Import, name: &str, position: TokenPosition) -> Result<(), Response> {
+        let names = match import.expose {
+            Expose::Nothing                 => vec![last_segment(name).to_owned()],
+            Expose::Specifically(ref names) => names.clone(),
+            Expose::Everything              => {
+                self.incomplete = true;
+                Vec::new()
+            },
+        };
 
-            Expression::BinaryOp(ref op) => {
-                let left_t  = (*self.type_expression(&op.left)?.unmut().unwrap()).clone();
-                let right_t = (*self.type_expression(&op.right)?.unmut().unwrap()).clone();
+        for name in names {
+            let index = self.symtab.add_name_at(&name, position);
+            if index >= self.typetab.size() {
+                self.typetab.grow()
+            }
 
-                use self::Type::*;
-                use self::Operand::*;
+            self.imported.insert(index);
+            self.unloaded.insert(self.slot(index, 0));
+        }
 
-                match (left_t, &op.op, right_t) {
-                    (a, &Add, b) => match (a, b) {
-                        (Number, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't add {} and {}", a, b))),
-                    },
+        Ok(())
+    }
 
-                    (a, &Sub, b) => match (a, b) {
-                        (Number, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't subtract {} and {}", a, b))),
-                    },
+    // checks the methods against the interface, then makes them fields of the struct
+    fn visit_implementation(&mut self, implementation: &Implementation) -> Result<(), Response> {
+        let Implementation {ref structure, ref interface, ref body, ref position} = *implementation;
 
-                    (a, &Mul, b) => match (a, b) {
-                        (Number, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't multiply {} and {}", a, b))),
-                    },
+        // the methods of a generic struct are added to it as declared, parameters and all
+        let (generics, declared_t) = match self.typetab.find_alias(structure) {
+            Some(Type::Params(generics, t)) => (generics, (*t).clone()),
+            Some(t)                         => (Vec::new(), t),
+            None                            => return Err(Response::error(at(*position, structure.len()), format!("can't implement for undefined struct: {}", structure))),
+        };
 
-                    (a, &Div, b) => match (a, b) {
-                        (Number, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't divide {} and {}", a, b))),
-                    },
+        let mut fields = match declared_t {
+            Type::Struct(fields) => fields,
+            t                    => return Err(Response::error(at(*position, structure.len()), format!("can't implement for non-struct: {}", t))),
+        };
 
-                    (a, &Pow, b) => match (a, b) {
-                        (Number, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't put {} to the power of {}", a, b))),
-                    },
+        let mut methods: Vec<(String, Type, TokenPosition)> = Vec::new();
+        let mut errors = Vec::new();
 
-                    (a, &Equal, b)   |
-                    (a, &NEqual, b)  |
-                    (a, &Lt, b)      |
-                    (a, &Gt, b)      |
-                    (a, &LtEqual, b) |
-                    (a, &GtEqual, b) => match (a, b) {
-                        (Nil, a) |
-                        (a, Nil) => Err(Response::error(None, format!("[location] can't compare {} to nothing", a))),
-                        _ => Ok(()),
-                    },
+        for method in body {
+            let (name, t, method_position) = self.type_method(method)?;
 
-                    (a, &Concat, b) => match (a, b) {
-                        (Str, Str)    |
-                        (Number, Str) |
-                        (Str, Bool)   |
-                        (Str, Number) => Ok(()),
-                        (a, b) => Err(Response::error(None, format!("[location] can't concat {} and {}", a, b))),
-                    },
+            if methods.iter().any(|method| method.0 == name) {
+                errors.push(Response::error(at(method_position, name.len()), format!("method implemented twice: {}", name)))
+            } else {
+                methods.push((name, t, method_position))
+            }
+        }
 
-                    (a, &PipeRight, b) => match (self.alias_type(&a)?, self.alias_type(&b)?) {
-                        (a, b @ Fun(_, _)) => match b {
-                            Type::Fun(ref params, _) => {
-                                if params.len() != 1 {
-                                    return Err(Response::error(None, format!("[location] function given {} arguments, expected: 1", params.len())))
-                                }
+        if let Some(ref interface) = *interface {
+            let expected = match self.alias_type(interface, *position) {
+                Ok(Type::Interface(expected)) => expected,
+                Ok(_)                         => return Err(Response::error(at(*position, structure.len()), format!("can't implement non-interface: {}", interface))),
+                Err(_)                        => return Err(Response::error(at(*position, structure.len()), format!("can't implement invalid interface: {}", interface))),
+            };
 
-                                if self.alias_type(params.get(0).unwrap())? != a {
-                                    return Err(Response::error(None, format!("[location] mismatching argument: {:?}", params.get(0).unwrap())))
-                                }
+            let mut names: Vec<&String> = expected.keys().collect();
+            names.sort();
 
-                                Ok(())
-                            },
+            for name in names {
+                let t = &expected[name];
 
-                            ref c => Err(Response::error(None, format!("[location] can't call non-fun: {:?} of {:?}", b, c)))
-                        },
-                        
-                        _ => panic!(),
+                match methods.iter().find(|method| method.0 == *name) {
+                    Some(&(_, ref found, method_position)) => if !self.same_method(t, found) {
+                        let found = self.inference.resolve(found);
+                        errors.push(Response::error(at(method_position, name.len()), format!("mismatching method {} of {}, expected \"{}\", found: {}", name, interface, t, found)))
                     },
 
-                    (a, o, b) => Err(Response::error(None, format!("[location] unimplemented operation: {} {:?} {}", a, o, b))),
-                }
-            },
-
-            Expression::Initialization(ref a) => match **a {
-                Initialization {ref id, ref values} => {
-                    let id_t = self.type_expression(id)?;
-                    let a    = self.alias_type(&id_t)?;
-                    if let Type::Struct(ref hash) = a {
-                        for def in values {
-                            let mut found = false;
-                            for (name, t) in hash.iter() {
-                                match *def.left {
-                                    Expression::Identifier(ref n, _) =>
-                                        if n == name {
-                                            let right_t = self.type_expression(&def.right)?;
-                                            
-                                            if self.alias_type(t)?.equals(&self.alias_type(&right_t)?) {
-                                                found = true
-                                            } else {
-                                                return Err(Response::error(None, format!("[location] {} expected \"{}\", found: {}", name, **t, right_t)))
-                                            }
-                                        } else {
-                                            continue
-                                        },
-
-                                    ref c => return Err(Response::error(None, format!("[location] can't set invalid key: {:?}", c)))
-                                }
-                            }
+                    None => errors.push(Response::error(at(*position, structure.len()), format!("missing method {} of {}: {}", name, interface, t))),
+                }
+            }
 
-                            if !found {
-                                return Err(Response::error(None, format!("[location] invalid initialization of: {:?}", def.left)))
-                            }
-                        }
-                        
-                        Ok(())
-                    } else {
-                        Err(Response::error(None, format!("[location] can't initialize: {}", a)))
-                    }
+            for &(ref name, _, method_position) in &methods {
+                if !expected.contains_key(name) {
+                    errors.push(Response::error(at(method_position, name.len()), format!("method {} is not part of {}", name, interface)))
                 }
-            },
+            }
+        }
 
-            Expression::Call(Call {ref callee, ref args}) => {
-                let callee_t = self.type_expression(callee)?;
-                match self.alias_type(&callee_t)? {
-                    Type::Fun(ref params, _) => {
-                        let mut acc = 0;
-                        
-                        if params.len() != args.len() {
-                            return Err(Response::error(None, format!("[location] function given {} arguments, expected: {}", params.len(), args.len())))
-                        }
-                        
-                        for param in params {
-                            let arg = &*self.type_expression(&*args[acc])?.unmut().unwrap();
-                            if self.alias_type(param)? != self.alias_type(arg)? {
-                                return Err(Response::error(None, format!("[location] mismatching argument: {:?}", param)))
-                            }
+        for (name, t, method_position) in methods {
+            match fields.entry(name) {
+                Entry::Occupied(field) => errors.push(Response::error(at(method_position, field.key().len()), format!("{} already has a field named {}", structure, field.key()))),
+                Entry::Vacant(field)   => { field.insert(Rc::new(self.inference.resolve(&t))); },
+            }
+        }
 
-                            acc += 1
-                        }
+        if !errors.is_empty() {
+            return Err(Response::group(errors))
+        }
 
-                        Ok(())
-                    },
+        self.typetab.set_alias(0, structure, declared(&generics, Type::Struct(fields)))
+    }
 
-                    ref c => Err(Response::error(None, format!("[location] can't call non-fun: {:?} of {:?}", callee, c)))
-                }
-            },
+    // checks that `left` can be set to a `right_t`
+    fn assign(&mut self, left: &Expression, right_t: &Type) -> Result<(), Response> {
+        match *left {
+            Expression::Identifier(ref name, ref position) => {
+                self.visit_expression(left)?;
 
-            Expression::FunctionMatch(ref a) => match **a {
-                FunctionMatch {ref t, ref arms, ..} => {
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &[]);
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
+                // what it was declared as, even where it's known not to be nil
+                let (index, env_index) = self.symtab.get_name(name).unwrap();
 
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
+                let a = self.inference.instantiate(&self.typetab.get_type(index, env_index)?);
+                let t = self.alias_type(&a, *position)?;
 
-                    let mut arm_t = Type::Nil;
-                    let mut flag  = false;
+                match t {
+                    Type::Mut(_) => (),
+                    _            => {
+                        let error = Response::error(Some(ErrorLocation::new(*position, name.len())), format!("reassignment of immutable: {}", name));
+                        return Err(self.with_definition(error, name, "was defined here, without mut"))
+                    },
+                }
 
-                    for arm in arms {
-                        local_visitor.visit_arm(arm)?;    
-                        if !flag {
-                            arm_t = self.alias_type(&local_visitor.type_arm(arm)?)?;
-                            flag = true
-                        } else {
-                            if arm_t != local_visitor.type_arm(&arm)? {
-                                return Err(Response::error(None, format!("[location] mismatching arms of match function expression")))
-                            }
-                        }
-                    }
+                self.unify(&t, right_t, Some(ErrorLocation::new(*position, name.len())), "mismatched types")?;
+                self.assigned(self.slot(index, env_index), &t, right_t);
 
-                    if let &Some(ref t) = t {
-                        let t = self.alias_type(t)?;
-                        if t != arm_t {
-                            Err(Response::error(None, format!("[location] mismatching return types of function expression")))
-                        } else {
-                            Ok(())
-                        }
-                    } else {
-                        Ok(())
-                    }
-                }
+                Ok(())
             },
 
-            Expression::Fun(ref a) => match **a {
-                Fun {ref t, ref params, ref body, ..} => {
-                    let mut param_names = Vec::new();
-                    let mut param_types = Vec::new();
+            Expression::Index(Index {ref id, ref index, ref position, ..}) => {
+                let t = self.type_expression(id)?;
 
-                    for param in params {
-                        param_names.push(param.name.clone());
-                        param_types.push(param.t.clone())
-                    }
+                match self.alias_type(&t, *position)? {
+                    Type::Mut(ref t) => match self.alias_type(t.as_ref().unwrap(), *position)? {
+                        Type::Array(ref t, _) => {
+                            if let Expression::Identifier(ref name, _) = **index {
+                                Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("trying to index array with identifier: {}", name)))
+                            } else {
+                                self.unify(t, right_t, Some(ErrorLocation::new(*position, 1)), "mismatched types")
+                            }
+                        },
 
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &param_names.as_slice());
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &param_types, &HashMap::new());
+                        Type::Struct(ref defs) => {
+                            if let Expression::Identifier(ref name, _) = **index {
+                                let t = match defs.get(name) {
+                                    Some(t) => self.alias_type(t, *position)?,
+                                    None    => return Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("invalid key: {}", name))),
+                                };
 
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
+                                self.unify(&t, right_t, Some(ErrorLocation::new(*position, 1)), "mismatched types")
+                            } else {
+                                Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't access struct with: {}", self.type_expression(index)?)))
+                            }
+                        },
 
-                    let body_expression = Expression::Block(body.clone());
+                        c => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't index: {}", c))),
+                    },
 
-                    local_visitor.visit_expression(&body_expression)?;
+                    c => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("assigning immutable index: {}", c))),
+                }
+            }
 
-                    if let &Some(ref t) = t {
-                        let t      = self.alias_type(&t)?;
-                        let body_t = self.alias_type(&local_visitor.type_expression(&body_expression)?)?;
+            // every element of the tuple takes its part of the right side
+            Expression::Tuple(ref content, position) => {
+                let types: Vec<Type> = content.iter().map(|_| self.inference.fresh()).collect();
+                self.unify(&Type::Tuple(types.clone()), right_t, at(position, 1), "mismatched types")?;
 
-                        if !t.equals(&body_t) {
-                            Err(Response::error(None, format!("[location] mismatching return types of fun expression")))
-                        } else {
-                            Ok(())
-                        }
-                    } else {
-                        Ok(())
-                    }
+                for (left, t) in content.iter().zip(&types) {
+                    let t = self.inference.resolve(t);
+                    self.assign(left, &t)?
                 }
+
+                Ok(())
             },
 
-            _ => Ok(())
+            _ => {
+                self.warnings.push(Response::warning(None, "potential unsafe assignment".to_string()));
+                Ok(())
+            }
         }
     }
 
-    pub fn type_expression(&mut self, e: &Expression) -> Result<Type, Response> {
-        match *e {
-            Expression::Number(_) => Ok(Type::Number),
-            Expression::Str(_)    => Ok(Type::Str),
-            Expression::Bool(_)   => Ok(Type::Bool),
-            Expression::Identifier(ref n, ref position) => match self.symtab.get_name(&*n) {
-                Some((i, env_index)) => self.typetab.get_type(i, env_index),
-                None                 => Err(Response::error(Some(ErrorLocation::new(*position, n.len())), format!("undefined type of: {}", n)))
-            },
+    // the body sees each element as `name`, and where it is as `index`
+    fn visit_for(&mut self, base: &For) -> Result<(), Response> {
+        let For {ref index, ref name, ref iterable, ref body, position} = *base;
 
-            Expression::Initialization(ref a) => match **a {
-                Initialization {ref id, ..} => {
-                    let a = self.type_expression(id)?;
-                    self.alias_type(&a)
-                }
-            }
+        let t = self.type_expression(iterable)?;
 
-            Expression::Array(ref content) => {
-                let mut array_t = Type::Nil;
-                let mut flag    = false;
-                
-                for expression in content {
-                    if !flag {
-                        array_t = self.type_expression(expression)?;
-                        flag    = true
-                    } else {
-                        if !array_t.equals(&self.type_expression(expression)?) {
-                            return Err(Response::error(None, format!("[location] mismatching array elements")))
-                        }
-                    }
-                }
+        let element_t = match *self.alias_type(&self.resolve(&t), position)?.unmut().unwrap() {
+            Type::Array(ref t, _) => (**t).clone(),
+            Type::Range           => Type::Number,
 
-                Ok(Type::Array(Rc::new(array_t), Some(Expression::Number(content.len() as f64))))
-            },
+            // anything that isn't known yet is taken to be an array
+            Type::Var(_) => {
+                let element_t = self.inference.fresh();
+                self.unify(&Type::Array(Rc::new(element_t.clone()), None), &t, locate(iterable.position().or(Some(position)), 1), "can't iterate over")?;
 
-            Expression::Index(Index {ref id, ref position, ref index}) => {
-                let a = self.type_expression(id)?;
+                element_t
+            },
 
-                match *self.alias_type(&a)?.unmut().unwrap() {
-                    Type::Array(ref t, _) => {
-                        Ok((**t).clone())
-                    },
+            ref c => return Err(Response::error(locate(iterable.position().or(Some(position)), 1), format!("can't iterate over: {}", c))),
+        };
 
-                    Type::Struct(ref defs) => {
-                        if let Expression::Identifier(ref name, _) = **index {
-                            if let Some(a) = defs.get(name) {
-                                self.alias_type(a)
-                            } else {
-                                Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("invalid key: {}", name)))
-                            }
-                        } else {
-                            Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't access struct with: {}", self.type_expression(&*index)?)))
-                        }
-                    }
+        let mut names = vec![name.clone()];
+        let mut types = vec![element_t];
 
-                    _ => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't index non-indexable: {:?}", id)))
-                }
-            },
+        if let Some(ref index) = *index {
+            if index == name {
+                return Err(Response::error(at(position, 3), format!("bound more than once in the same loop: {}", name)))
+            }
 
-            Expression::Unless(ref a) => match **a {
-                Unless {ref base} => self.type_expression(&Expression::If(Rc::new(base.clone()))),
-            },
+            names.push(index.clone());
+            types.push(Type::Number)
+        }
 
-            Expression::If(ref a) => match **a {
-                If {ref body, ref elses, ..} => {
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &[]);
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
+        self.type_loop(&mut |visitor| {
+            let mut local_visitor = visitor.local(&names, &types);
+            local_visitor.loops  += 1;
 
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
-                    let mut arm_t         = local_visitor.type_expression(&Expression::Block(body.clone()))?;
-                    
-                    if let &Some(ref elses) = elses {
-                        for arm in elses { 
-                            if arm_t != local_visitor.type_expression(&Expression::Block(arm.1.clone()))? {
-                                return Err(Response::error(None, format!("[location] mismatching branches of if expression")))
-                            }
-                        }
-                    }
+            visitor.type_local_body(&mut local_visitor, body).map(|_| ())
+        })
+    }
 
-                    Ok(arm_t)
-                }
-            }
-            
-            Expression::MatchPattern(MatchPattern {ref matching, ref arms}) => {
-                self.visit_expression(&matching)?;
+    // a loop's body can run again after itself, so it's checked knowing only what's still known when
+    // it gets back around, which takes checking it again for as long as that's less than before
+    fn type_loop(&mut self, type_body: &mut dyn FnMut(&mut Visitor) -> Result<(), Response>) -> Result<(), Response> {
+        let (exits, warnings) = (self.exits.len(), self.warnings.len());
 
-                let mut arm_t = Type::Nil;
-                let mut flag  = false;
+        loop {
+            let start  = self.flow.clone();
+            let result = type_body(self);
 
-                for arm in arms {
-                    self.visit_arm(arm)?;
-                    
-                    if !self.type_expression(&*arm.param)?.equals(&self.type_expression(matching)?) {
-                        return Err(Response::error(None, format!("[location] mismatching arm parameter of match expression")))
-                    }
+            let mut again = start.clone();
+            let mut after = start.clone();
 
-                    if !flag {
-                        let a = self.type_arm(arm)?;
+            again.join(&self.flow);
+            after.join(&self.flow);
 
-                        arm_t = self.alias_type(&a)?;
-                        flag = true
-                    } else {
-                        if arm_t != self.type_arm(&arm)? {
-                            return Err(Response::error(None, format!("[location] mismatching arms of match expression")))
-                        }
-                    }
+            for (exit, flow) in &self.exits[exits ..] {
+                match *exit {
+                    Exit::Continue => again.join(flow),
+                    Exit::Break    => after.join(flow),
+                    Exit::Return   => (),
                 }
-
-                Ok(arm_t)
             }
-            
-            Expression::BinaryOp(ref op) => {
-                let left_t  = (*self.type_expression(&op.left)?.unmut().unwrap()).clone();
-                let right_t = (*self.type_expression(&op.right)?.unmut().unwrap()).clone();
 
-                use self::Type::*;
-                use self::Operand::*;
+            if again.settled(&start) {
+                let returns: Vec<_> = self.exits.drain(exits ..).filter(|(exit, _)| *exit == Exit::Return).collect();
 
-                match (left_t, &op.op, right_t) {
-                    (a, &Add, b) => match (a, b) {
-                        (Number, Number) => Ok(Number),
-                        (a, b) => Err(Response::error(None, format!("[location] can't add {} and {}", a, b))),
-                    },
+                self.exits.extend(returns);
+                self.flow = after;
 
-                    (a, &Sub, b) => match (a, b) {
-                        (Number, Number) => Ok(Number),
-                        (a, b) => Err(Response::error(None, format!("[location] can't subtract {} and {}", a, b))),
-                    },
+                return result
+            }
 
-                    (a, &Mul, b) => match (a, b) {
-                        (Number, Number) => Ok(Number),
-                        (a, b) => Err(Response::error(None, format!("[location] can't multiply {} and {}", a, b))),
-                    },
+            self.exits.truncate(exits);
+            self.warnings.truncate(warnings);
+            self.flow = again;
+        }
+    }
 
-                    (a, &Div, b) => match (a, b) {
-                        (Number, Number) => Ok(Number),
-                        (a, b) => Err(Response::error(None, format!("[location] can't divide {} and {}", a, b))),
-                    },
+    // a `return` in a block leaves only the block
+    fn type_block_expression(&mut self, statements: &[Statement]) -> Result<Type, Response> {
+        let exits  = self.exits.len();
+        let result = self.type_block(statements);
 
-                    (a, &Pow, b) => match (a, b) {
-                        (Number, Number) => Ok(Number),
-                        (a, b) => Err(Response::error(None, format!("[location] can't put {} to the power of {}", a, b))),
-                    },
+        for (exit, flow) in self.exits.split_off(exits) {
+            if exit == Exit::Return {
+                self.flow.join(&flow)
+            } else {
+                self.exits.push((exit, flow))
+            }
+        }
 
-                    (a, &Equal, b)   |
-                    (a, &NEqual, b)  |
-                    (a, &Lt, b)      |
-                    (a, &Gt, b)      |
-                    (a, &LtEqual, b) |
-                    (a, &GtEqual, b) => match (a, b) {
-                        (Nil, a) |
-                        (a, Nil) => Err(Response::error(None, format!("[location] can't compare {} to nothing", a))),
-                        _ => Ok(Bool),
-                    },
+        result
+    }
 
-                    (a, &Concat, b) => match (a, b) {
-                        (Str, Str) |
-                        (Number, Str) |
-                        (Str, Bool)   |
-                        (Str, Number) => Ok(Str),
-                        (a, b) => Err(Response::error(None, format!("[location] can't concat {} and {}", a, b))),
-                    },
+    fn in_loop(&mut self, exit: Exit, what: &str, position: TokenPosition) -> Result<(), Response> {
+        if self.loops == 0 {
+            return Err(Response::error(at(position, what.len()), format!("{} outside of a loop", what)))
+        }
 
-                    (a, &PipeRight, b) => match (self.alias_type(&a)?, self.alias_type(&b)?) {
-                        (a, b @ Fun(_, _)) => match b {
-                            Type::Fun(_, ref retty) => {
-                                if let &Some(ref retty) = retty {
-                                    Ok(retty.as_ref().clone())
-                                } else {
-                                    Ok(Type::Nil)
-                                }
-                            },
+        self.exit(exit);
+        Ok(())
+    }
 
-                            ref c => Err(Response::error(None, format!("[location] can't call non-fun: {:?} of {:?}", b, c)))
-                        },
-                        
-                        _ => panic!(),
-                    },
+    // every name the pattern binds gets its own definition, with the type of what it takes
+    fn visit_destructure(&mut self, destructure: &Destructure) -> Result<(), Response> {
+        let Destructure {ref t, ref pattern, ref right, position} = *destructure;
 
-                    (a, o, b) => Err(Response::error(None, format!("[location] unimplemented operation: {} {:?} {}", a, o, b))),
-                }
-            },
-            
-            Expression::Call(Call {ref callee, ..}) => {
-                let a = self.type_expression(callee)?;
+        let right_t = match self.type_expression(right) {
+            Ok(a)  => self.alias_type(&a, position)?,
+            Err(e) => {
+                // the names are left unknown, rather than erroring everywhere they're used
+                for name in pattern.bindings() {
+                    let index = self.symtab.add_name_at(&name, position);
+                    if index >= self.typetab.size() {
+                        self.typetab.grow()
+                    }
 
-                match self.alias_type(&a)? {
-                    Type::Fun(_, ref retty) => {
-                        if let &Some(ref retty) = retty {
-                            Ok(retty.as_ref().clone())
-                        } else {
-                            Ok(Type::Nil)
-                        }
-                    },
-                    
-                    ref c => Err(Response::error(None, format!("[location] can't call non-fun: {:?} of {:?}", callee, c)))
+                    self.typetab.set_type(index, 0, self.inference.fresh())?
                 }
-            },
-            
-            Expression::Fun(ref a) => match **a {
-                Fun {ref t, ref params, ref body, ..} => {
-                    let mut param_names = Vec::new();
-                    let mut param_types = Vec::new();
 
-                    for param in params {
-                        param_names.push(param.name.clone());
-                        param_types.push(param.t.clone())
-                    }
+                return Err(e)
+            },
+        };
 
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &param_names.as_slice());
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &param_types, &HashMap::new());
+        let (t, mutable) = match *t {
+            Some(Type::Mut(None))        => (right_t.clone(), true),
+            Some(Type::Mut(Some(ref t))) => (self.alias_type(t, position)?, true),
+            Some(ref t)                  => (self.alias_type(t, position)?, false),
+            None                         => (right_t.clone(), false),
+        };
 
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
+        self.unify(&t, &right_t, at(position, 1), "mismatched types")?;
 
-                    let body_expression = Expression::Block(body.clone());
+        let mut bindings = Vec::new();
+        self.type_pattern(pattern, &t, &mut bindings)?;
 
-                    local_visitor.visit_expression(&body_expression)?;
+        // there's no other arm to fall back on
+        let mut matrix = Matrix::new();
+        let shape      = self.shape(pattern, &mut matrix);
+        matrix.push(vec![shape]);
 
-                    let body_t = self.alias_type(&local_visitor.type_expression(&body_expression)?)?;
-                    
-                    if let &Some(ref t) = t {
-                        let t = self.alias_type(t)?;
+        if let Some(uncovered) = matrix.uncovered(&[Shape::Anything]) {
+            return Err(Response::error(at(position, 1), format!("pattern in definition doesn't cover: {}", describe(&uncovered))))
+        }
 
-                        if !t.equals(&body_t) {
-                            Err(Response::error(None, format!("[location] mismatching return types of fun expression")))
-                        } else {
-                            let t = Type::Fun(param_types, Some(Rc::new(t.clone())));
-                            Ok(t.clone())
-                        }
-                    } else {
-                        let t = Type::Fun(param_types, Some(Rc::new(self.alias_type(&body_t)?)));
-                        
-                        Ok(t.clone())
-                    }
-                }
-            },
-            
-            Expression::FunctionMatch(ref a) => match **a {
-                FunctionMatch {ref t, ref arms, ..} => {
-                    let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &[]);
-                    let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
+        for (name, t) in bindings {
+            let index = self.symtab.add_name_at(&name, position);
+            if index >= self.typetab.size() {
+                self.typetab.grow()
+            }
 
-                    let mut local_visitor = Visitor::from(local_symtab, local_typetab);
+            let t = self.inference.resolve(&t);
 
-                    let mut arm_t   = Type::Nil;
-                    let mut param_t = Type::Nil;
+            if mutable {
+                self.typetab.set_type(index, 0, Type::Mut(Some(Rc::new(t))))?
+            } else {
+                self.typetab.set_type(index, 0, t)?
+            }
+        }
 
-                    let mut flag    = false;
+        Ok(())
+    }
 
-                    for arm in arms {
-                        if !flag {
-                            arm_t   = self.alias_type(&local_visitor.type_arm(arm)?)?;
-                            param_t = self.alias_type(&local_visitor.type_expression(&*arm.param)?)?;
-                            flag = true
-                        } else {
-                            if arm_t != local_visitor.type_arm(&arm)? {
-                                return Err(Response::error(None, format!("[location] mismatching arms of match function expression")))
-                            }
-                            
-                            if param_t != local_visitor.type_expression(&*arm.param)? {
-                                return Err(Response::error(None, format!("[location] mismatching arm parameters of match function expression")))
-                            }
-                        }
-                    }
+    // the type, then each variant as a value of it when it carries nothing, and as a fun making one otherwise
+    fn visit_data(&mut self, data: &Data) -> Result<(), Response> {
+        let Data {ref name, ref generics, ref variants, ref position} = *data;
 
-                    if let &Some(ref t) = t {
-                        let t = self.alias_type(t)?;
-                        if t != arm_t {
-                            Err(Response::error(None, format!("[location] mismatching return types of function expression")))
-                        } else {
-                            Ok(Type::Fun(vec!(param_t), Some(Rc::new(t.clone()))))
-                        }
-                    } else {
-                        Ok(Type::Fun(vec!(param_t), Some(Rc::new(arm_t.clone()))))
-                    }
-                }
-            },
+        if self.typetab.find_alias(name).is_some() {
+            return Err(Response::error(at(*position, name.len()), format!("type already in use: {}", name)))
+        }
 
-            Expression::Block(ref statements) => {
-                let mut block_t = Type::Nil;
-                let mut flag    = false;
+        let declared_variants = variants.iter().map(|variant| (variant.name.clone(), variant.fields.clone())).collect();
+        self.typetab.set_alias(0, name, declared(generics, Type::Data(name.clone(), declared_variants)))?;
 
-                let mut acc     = 1;
+        let data_t = if generics.is_empty() {
+            Type::Identifier(name.clone())
+        } else {
+            Type::Generic(name.clone(), generics.iter().map(|generic| Type::Identifier(generic.clone())).collect())
+        };
 
-                for statement in statements {
-                    if acc == statements.len() {
-                        match *statement {
-                            Statement::Expression(ref expr) => {
-                                if !flag {
-                                    block_t = self.type_expression(expr)?;
-                                    flag = true
-                                } else {
-                                    return Err(Response::error(None, format!("[location] mismatching return types of block")))
-                                }
-                            }
-                            Statement::Return(ref expr) => {
-                                if !flag {
-                                    block_t = if let &Some(ref expr) = expr {
-                                        self.type_expression(expr)?
-                                    } else {
-                                        Type::Nil
-                                    };
-
-                                    flag = true
-                                } else {
-                                    return Err(Response::error(None, format!("[location] mismatching return types of block")))
-                                }
-                            },
-                            _ => {
-                                if !flag {
-                                    block_t = Type::Nil;
-                                    flag = true
-                                }
-                            }
-                        }
-                    } else {
-                        match *statement {
-                            Statement::Return(ref expr) => {
-                                if !flag {
-                                    block_t = if let &Some(ref expr) = expr {
-                                        self.type_expression(expr)?
-                                    } else {
-                                        Type::Nil
-                                    };
-
-                                    flag = true
-                                } else {
-                                    return Err(Response::error(None, format!("[location] mismatching return types of block")))
-                                }
-                            },
+        let mut errors = Vec::new();
 
-                            _ => (),
-                        }
-                    }
-                    
-                    acc += 1
-                }
+        for variant in variants {
+            if self.symtab.get_name(&variant.name).is_some() {
+                errors.push(self.already_in_use("variant's name", &variant.name, variant.position));
+                continue
+            }
 
-                Ok(block_t)
-            },
+            let index = self.symtab.add_name_at(&variant.name, variant.position);
+            if index >= self.typetab.size() {
+                self.typetab.grow()
+            }
 
-            _ => Ok(Type::Nil),
-        }
-    }
+            let generic_types = self.generics(generics);
+            let result_t      = data_t.replace(&generic_types);
 
-    pub fn type_arm(&mut self, arm: &MatchArm) -> Result<Type, Response> {
-        let mut param: Vec<String> = Vec::new();
+            let t = if variant.fields.is_empty() {
+                result_t
+            } else {
+                Type::Fun(variant.fields.iter().map(|field| field.replace(&generic_types)).collect(), Some(Rc::new(result_t)))
+            };
 
-        if let Expression::Identifier(ref name, _) = *arm.param {
-            param.push(name.to_owned())
+            self.typetab.set_type(index, 0, t)?;
+            self.generalize(index)?
         }
 
-        let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &param.as_slice());
-        let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
-
-        let mut local_visitor = Visitor::from(local_symtab, local_typetab);
-
-        local_visitor.type_expression(&*arm.body)
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Response::group(errors))
+        }
     }
 
-    pub fn visit_arm(&mut self, arm: &MatchArm) -> Result<(), Response> {
-        self.visit_expression(&*arm.body)
+    // the type a definition is annotated with, resolved
+    fn annotation(&mut self, t: &Type, position: TokenPosition) -> Result<Type, Response> {
+        if t.is_mut() {
+            Ok(Type::Mut(Some(Rc::new(self.alias_type(&t.unmut().unwrap(), position)?))))
+        } else {
+            self.alias_type(t, position)
+        }
     }
 
     pub fn visit_statement(&mut self, s: &Statement) -> Result<(), Response> {
         match *s {
             Statement::Extern(ref statement) => self.visit_statement(statement),
             Statement::Expression(ref e)     => self.visit_expression(e),
-            Statement::Struct(Struct {ref name, ref body}) => match self.symtab.get_name(&*name) {
-                Some(_) => Err(Response::error(None, format!("[location] struct's name already in use: {}", name))),
+            Statement::Struct(Struct {ref name, ref generics, ref body, ref position}) => match self.symtab.get_name(name) {
+                Some(_) => Err(self.already_in_use("struct's name", name, *position)),
                 None    => {
-                    let index = self.symtab.add_name(&name);
+                    let index = self.symtab.add_name_at(name, *position);
                     if index >= self.typetab.size() {
                         self.typetab.grow()
                     }
@@ -729,257 +1941,174 @@ impl Visitor {
                         types.insert(def.name.clone(), Rc::new(Type::Mut(Some(Rc::new(def.t.clone())))));
                     }
 
-                    self.typetab.set_alias(0, &name, Type::Struct(types.clone()))?;
+                    self.typetab.set_alias(0, name, declared(generics, Type::Struct(types)))?;
                     self.typetab.set_type(index, 0, Type::Identifier(name.clone()))
                 },
             },
 
+            Statement::Data(ref data) => self.visit_data(data),
+
             Statement::Definition(Definition {ref t, ref name, ref right, ref position}) => {
-                let index = self.symtab.add_name(&name);
+                let index = self.symtab.add_name_at(name, *position);
                 if index >= self.typetab.size() {
                     self.typetab.grow()
                 }
 
-                if let &Some(ref right) = right {
-                    self.visit_expression(&*right)?;
+                if let Some(right) = right {
+                    // a name that failed to type keeps the type it was declared with, or is left unknown,
+                    // rather than erroring everywhere it's used
+                    let a = match self.type_expression(right) {
+                        Ok(a)  => a,
+                        Err(e) => {
+                            let declared = match *t {
+                                Some(ref t) if !t.is_empty_mut() => self.annotation(t, *position).ok(),
+                                _                                => None,
+                            };
+
+                            let fallback = declared.unwrap_or_else(|| self.inference.fresh());
+
+                            self.typetab.set_type(index, 0, fallback)?;
+                            return Err(e)
+                        },
+                    };
 
-                    let a = self.type_expression(&*right)?;
-                    let right_t = self.alias_type(&a)?;
+                    let right_t = self.alias_type(&a, *position)?;
 
-                    if let &Some(ref t) = t {
+                    if let Some(t) = t {
                         let t = if !t.is_empty_mut() {
-                            if t.is_mut() {
-                                Type::Mut(Some(Rc::new(self.alias_type(&t.unmut().unwrap())?)))
-                            } else {
-                                self.alias_type(&t)?
-                            }
+                            self.annotation(t, *position)?
                         } else {
                             if t.is_mut() {
-                                Type::Mut(Some(Rc::new(self.alias_type(&right_t.clone())?)))
+                                Type::Mut(Some(Rc::new(self.alias_type(&right_t.clone(), *position)?)))
                             } else {
-                                self.alias_type(&right_t)?
+                                self.alias_type(&right_t, *position)?
                             }
                         };
 
-                        if !right_t.equals(&t.unmut().unwrap()) {
-                            Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("mismatched types, expected \"{}\", found: {}", t, right_t)))
-                        } else {
-                            self.typetab.set_type(index, 0, t.clone())
-                        }
+                        let unified = self.unify(&t, &right_t, Some(ErrorLocation::new(*position, name.len())), "mismatched types");
+
+                        self.typetab.set_type(index, 0, t.clone())?;
+                        unified
                     } else {
-                        self.typetab.set_type(index, 0, right_t.clone())
+                        self.typetab.set_type(index, 0, right_t.clone())?;
+
+                        // only funs are generalized, a generic array could be filled with anything
+                        match **right {
+                            Expression::Fun(_) | Expression::FunctionMatch(_) => self.generalize(index),
+                            _                                                 => Ok(()),
+                        }
                     }
                 } else {
-                    if let &Some(ref t) = t {
+                    if let Some(t) = t {
                         self.typetab.set_type(index, 0, t.clone())
                     } else {
                         unreachable!()
                     }
                 }
             },
+            Statement::Destructure(ref destructure) => self.visit_destructure(destructure),
+
             Statement::Assignment(Assignment {ref left, ref right, ..}) => {
-                match **left {
-                    Expression::Identifier(ref name, ref position) => {
-                        self.visit_expression(left)?;
+                let right_t = self.type_expression(right)?;
+                self.assign(left, &right_t)
+            },
 
-                        // hmm
-                        let a = self.type_expression(left)?;
-                        let t = self.alias_type(&a)?;
+            Statement::Unless(ref unless) => self.type_if(&unless.base, true, true).map(|_| ()),
+            Statement::If(ref base)       => self.type_if(base, true, false).map(|_| ()),
 
-                        match t {
-                            Type::Mut(_) => (),
-                            _            => return Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("reassignment of immutable: {}", name)))
-                        }
+            Statement::MatchPattern(ref m) => self.type_match(m, true).map(|_| ()),
 
-                        self.visit_expression(&right)?;
+            // the body runs where the names the condition checks against nil aren't
+            Statement::While(ref base) => self.type_loop(&mut |visitor| {
+                visitor.condition(&base.condition, None, "while")?;
 
-                        let right_t = self.type_expression(right)?;
+                let mut local_visitor = visitor.local(&[], &[]);
+                local_visitor.loops  += 1;
+                local_visitor.flow.narrow(&visitor.narrowing(&base.condition).0);
 
-                        if !self.alias_type(&right_t)?.equals(&t) {
-                            Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("mismatched types, expected: {}", t)))
-                        } else {
-                            Ok(())
-                        }
-                    },
+                visitor.type_local_body(&mut local_visitor, &base.body).map(|_| ())
+            }),
 
-                    Expression::Index(Index {ref id, ref index, ref position}) => {
-                        let t = self.type_expression(id)?;
-                        
-                        match self.alias_type(&t)? {
-                            Type::Mut(ref t) => match self.alias_type(&*t.as_ref().unwrap())? {
-                                Type::Array(ref t, _) => {
-                                    if let Expression::Identifier(ref name, _) = **index {
-                                        Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("trying to index array with identifier: {}", name)))
-                                    } else {
-                                        if !self.type_expression(right)?.equals(&t) {
-                                            Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("mismatched types, expected: {}", t)))
-                                        } else {
-                                            Ok(())
-                                        }
-                                    }
-                                },
-
-                                Type::Struct(ref defs) => {
-                                    if let Expression::Identifier(ref name, _) = **index {
-                                        let t = self.alias_type(defs.get(name).unwrap())?;
-
-                                        let a = self.type_expression(right)?;
-                                        if !self.alias_type(&a)?.equals(&t) {
-                                            Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("mismatched types, expected '{}', found: {}", t, a)))
-                                        } else {
-                                            Ok(())
-                                        }
-                                    } else {
-                                        Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't access struct with: {}", self.type_expression(&*index)?)))
-                                    }
-                                },
-
-                                c => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("can't index: {}", c))),
-                            },
+            Statement::For(ref base) => self.visit_for(base),
+
+            Statement::Break(position)    => self.in_loop(Exit::Break, "break", position),
+            Statement::Continue(position) => self.in_loop(Exit::Continue, "continue", position),
+
+            Statement::FunctionMatch(ref function) => {
+                match *function.name.as_ref().unwrap() {
+                    Expression::Identifier(ref name, position) => self.visit_function(name, position, &mut |visitor, index| visitor.type_function_match(function, Some(index))),
 
-                            c => Err(Response::error(Some(ErrorLocation::new(*position, 1)), format!("assigning immutable index: {}", c))),
-                        }
-                    }
-                    
                     _ => {
-                        Response::warning(None, format!("potential unsafe assignment")).display(None);
+                        self.warnings.push(Response::warning(None, "potential unsafe match function".to_string()));
                         Ok(())
                     }
                 }
             },
-            
-            Statement::Unless(ref unless) => self.visit_expression(&Expression::If(Rc::new(unless.base.clone()))),
-            Statement::If(ref base)       => self.visit_expression(&Expression::If(Rc::new(base.clone()))),
-            
-            Statement::While(ref base) => {
-                self.visit_expression(&base.condition)?;
-
-                if self.type_expression(&base.condition)? != Type::Bool {
-                    return Err(Response::error(None, format!("[location] invalid non-bool while condition")))
-                }
-                
-                self.visit_expression(&Expression::Block(base.body.clone()))
-            } 
-            
-            Statement::FunctionMatch(FunctionMatch {ref t, ref name, ref arms}) => {
-                match *name.as_ref().unwrap() {
-                    Expression::Identifier(ref name, ref position) => match self.symtab.get_name(&*name) {
-                        // [todo] check if function and handle function variants
-                        Some(_) => Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("name already in use: {}", name))),
-                        None    => {
-                            let index = self.symtab.add_name(&name);
-                            if index >= self.typetab.size() {
-                                self.typetab.grow()
-                            }
-
-                            let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &[]);
-                            let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &Vec::new(), &HashMap::new());
-
-                            let mut local_visitor = Visitor::from(local_symtab, local_typetab);
-
-                            let mut arm_t   = Type::Nil;
-                            let mut param_t = Type::Nil;
-                            let mut flag    = false;
-
-                            for arm in arms {
-                                if !flag {
-                                    arm_t   = self.alias_type(&local_visitor.type_arm(arm)?)?;
-                                    param_t = self.alias_type(&local_visitor.type_expression(&*arm.param)?)?;
-                                    flag = true
-                                } else {
-                                    if arm_t != local_visitor.type_arm(&arm)? {
-                                        return Err(Response::error(None, format!("[location] mismatching arms of match function expression")))
-                                    }
-                                    
-                                    if param_t != local_visitor.type_expression(&*arm.param)? {
-                                        return Err(Response::error(None, format!("[location] mismatching arm parameters of match function expression")))
-                                    }
-                                }
-                            }
+            Statement::Fun(ref fun) => {
+                match *fun.name.as_ref().unwrap() {
+                    Expression::Identifier(ref name, position) => self.visit_function(name, position, &mut |visitor, index| visitor.type_fun(fun, Some(index))),
 
-                            if let &Some(ref t) = t {
-                                let t = self.alias_type(t)?;
-                                if t != arm_t {
-                                    Err(Response::error(None, format!("[location] mismatching return types of function: {}", name)))
-                                } else {
-                                    local_visitor.typetab.set_type(index, 0, Type::Fun(vec!(param_t), Some(Rc::new(t.clone()))))
-                                }
-                            } else {
-                                local_visitor.typetab.set_type(index, 0, Type::Fun(vec!(param_t), Some(Rc::new(arm_t.clone()))))
-                            }
-                        },
-                    },
-                    
                     _ => {
-                        Response::warning(None, format!("potential unsafe match function")).display(None);
+                        self.warnings.push(Response::warning(None, "potential unsafe function".to_string()));
                         Ok(())
                     }
                 }
             },
-            Statement::Fun(Fun {ref t, ref name, ref params, ref body}) => {
-                match *name.as_ref().unwrap() {
-                    Expression::Identifier(ref name, ref position) => match self.symtab.get_name(&*name) {
-                        // [todo] check if function and handle function variants
-                        Some(_) => Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("name already in use: {}", name))),
-                        None    => {
-                            let index = self.symtab.add_name(&name);
-                            if index >= self.typetab.size() {
-                                self.typetab.grow()
-                            }
-
-                            self.typetab.set_type(index, 0, Type::Nil)?;
-
-                            let mut param_names = Vec::new();
-                            let mut param_types = Vec::new();
+            Statement::Interface(Interface {ref name, ref generics, ref types, ref position}) => match self.symtab.get_name(name) {
+                Some(_) => Err(self.already_in_use("interface's name", name, *position)),
+                None    => {
+                    let index = self.symtab.add_name_at(name, *position);
+                    if index >= self.typetab.size() {
+                        self.typetab.grow()
+                    }
 
-                            for param in params {
-                                param_names.push(param.name.clone());
-                                param_types.push(param.t.clone())
-                            }
+                    let mut methods = HashMap::new();
 
-                            let local_symtab  = SymTab::new(Rc::new(self.symtab.clone()), &param_names.as_slice());
-                            let local_typetab = TypeTab::new(Rc::new(self.typetab.clone()), &param_types, &HashMap::new());
+                    for def in types {
+                        if methods.insert(def.name.clone(), Rc::new(def.t.clone())).is_some() {
+                            return Err(Response::error(at(*position, name.len()), format!("interface {} declares {} twice", name, def.name)))
+                        }
+                    }
 
-                            let mut local_visitor = Visitor::from(local_symtab, local_typetab);
+                    self.typetab.set_alias(0, name, declared(generics, Type::Interface(methods)))?;
+                    self.typetab.set_type(index, 0, Type::Identifier(name.clone()))
+                },
+            },
 
-                            let body_expression = Expression::Block(body.clone());
+            Statement::Implementation(ref implementation) => self.visit_implementation(implementation),
+            Statement::Import(ref import)                 => self.visit_import(import),
 
-                            local_visitor.visit_expression(&body_expression)?;
+            Statement::Return(ref expr) => {
+                let result = match *expr {
+                    Some(ref expr) => self.visit_expression(expr),
+                    None           => Ok(()),
+                };
 
-                            let body_t = self.alias_type(&local_visitor.type_expression(&body_expression)?)?;
+                self.exit(Exit::Return);
+                result
+            },
+        }
+    }
+}
 
-                            if let &Some(ref t) = t {
-                                let t = self.alias_type(t)?;
+// whether what was found is only off by possibly being nil
+fn unchecked(expected: &Type, found: &Type) -> bool {
+    matches!(*found.unmut().unwrap(), Type::Optional(_)) && !matches!(*expected.unmut().unwrap(), Type::Optional(_))
+}
 
-                                if !t.equals(&body_t) {
-                                    Err(Response::error(None, format!("[location] mismatching return types of fun: {} of {:?}\n\t:: {:?}", name, t, body_t)))
-                                } else {                                    
-                                    let t = Type::Fun(param_types, Some(Rc::new(t.clone())));
+// what a struct or interface is declared as, keeping its type parameters when it has any
+fn declared(generics: &[String], t: Type) -> Type {
+    if generics.is_empty() {
+        t
+    } else {
+        Type::Params(generics.to_vec(), Rc::new(t))
+    }
+}
 
-                                    local_visitor.typetab.set_type(index, 1, t.clone())?;
-                                    self.typetab.set_type(index, 0, t.clone())
-                                }
-                            } else {
-                                let t = Type::Fun(param_types, Some(Rc::new(body_t.clone())));
-                                
-                                local_visitor.typetab.set_type(index, 1, t.clone())?;
-                                self.typetab.set_type(index, 0, t.clone())
-                            }
-                        },
-                    },
+fn at(position: TokenPosition, span: usize) -> Option<ErrorLocation> {
+    Some(ErrorLocation::new(position, span))
+}
 
-                    _ => {
-                        Response::warning(None, format!("potential unsafe function")).display(None);
-                        Ok(())
-                    }
-                }
-            },
-            Statement::Return(ref expr) => if let &Some(ref expr) = expr {
-                self.visit_expression(&expr)
-            } else {
-                Ok(())
-            }
-            _ => Ok(())
-        }
-    }
+fn locate(position: Option<TokenPosition>, span: usize) -> Option<ErrorLocation> {
+    position.map(|position| ErrorLocation::new(position, span))
 }
@@ -0,0 +1,169 @@
+use std::rc::Rc;
+use std::fmt::{self, Write};
+
+use super::*;
+
+// slots are the (index, env_index) pairs `SymTab::get_name` hands out, jumps are absolute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(u32),
+    Nil,
+    True,
+    False,
+    Pop,
+    Dup,
+    Get(u32, u32),
+    Set(u32, u32),
+    Define(u32),
+    Array(u32),
+    Tuple(u32),
+    Struct(u32),
+    Inherit,
+    GetField(u32),
+    SetField(u32),
+    GetIndex,
+    SetIndex,
+    Binary(Operand),
+    Unary(UnaryOperand),
+    Match(u32),
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    Next(u32),
+    Call(u32),
+    Return,
+    Closure(u32),
+    EnterScope,
+    ExitScope,
+    Mark,
+    Unmark,
+    Unwind(u32),
+    UnwindKeep(u32),
+}
+
+#[derive(Default)]
+pub struct Chunk {
+    pub code:       Vec<Op>,
+    pub positions:  Vec<Option<TokenPosition>>,
+    pub constants:  Vec<Value>,
+    pub patterns:   Vec<Pattern>,
+    pub structs:    Vec<FieldNames>, // the fields of each struct made, in the order their values are pushed
+    pub prototypes: Vec<Rc<Prototype>>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk::default()
+    }
+}
+
+// a compiled function, closed over an environment at runtime
+pub struct Prototype {
+    pub name:  String,
+    pub arity: usize,
+    pub chunk: Chunk,
+}
+
+impl Prototype {
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        self.disassemble_into(&mut out).unwrap();
+
+        out
+    }
+
+    fn disassemble_into(&self, out: &mut String) -> fmt::Result {
+        let chunk = &self.chunk;
+
+        writeln!(out, "fun {} ({} params, {} constants)", self.name, self.arity, chunk.constants.len())?;
+
+        let mut line = 0;
+
+        for (i, op) in chunk.code.iter().enumerate() {
+            let position = match chunk.positions[i] {
+                Some(p) if p.line != line => {
+                    line = p.line;
+                    format!("{:>4}", line)
+                },
+
+                Some(_) => "   |".to_owned(),
+                None    => "   -".to_owned(),
+            };
+
+            let comment = match *op {
+                Op::Constant(c) |
+                Op::GetField(c) |
+                Op::SetField(c) => format!("; {}", describe(&chunk.constants[c as usize])),
+                Op::Closure(p)  => format!("; {}", chunk.prototypes[p as usize].name),
+                Op::Struct(s)   => match chunk.structs[s as usize].iter().map(|name| name.as_str()).collect::<Vec<&str>>() {
+                    ref names if names.is_empty() => String::new(),
+                    names                         => format!("; {}", names.join(", ")),
+                },
+                Op::Match(p)    => match chunk.patterns[p as usize].bindings() {
+                    ref names if names.is_empty() => String::new(),
+                    names                         => format!("; binds {}", names.join(", ")),
+                },
+                _               => String::new(),
+            };
+
+            let line = format!("{:04} {}  {:<24}{}", i, position, op.to_string(), comment);
+            writeln!(out, "{}", line.trim_end())?
+        }
+
+        for prototype in &chunk.prototypes {
+            writeln!(out)?;
+            prototype.disassemble_into(out)?
+        }
+
+        Ok(())
+    }
+}
+
+// constants with strings quoted, so they can be told apart from names
+fn describe(value: &Value) -> String {
+    match *value {
+        Value::Str(ref s) => format!("{:?}", s),
+        Value::Char(c)    => format!("{:?}", c),
+        ref v             => v.to_string(),
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Op::Constant(a)      => write!(f, "{:<12} {}", "constant", a),
+            Op::Nil              => write!(f, "nil"),
+            Op::True             => write!(f, "true"),
+            Op::False            => write!(f, "false"),
+            Op::Pop              => write!(f, "pop"),
+            Op::Dup              => write!(f, "dup"),
+            Op::Get(a, b)        => write!(f, "{:<12} {} {}", "get", a, b),
+            Op::Set(a, b)        => write!(f, "{:<12} {} {}", "set", a, b),
+            Op::Define(a)        => write!(f, "{:<12} {}", "define", a),
+            Op::Array(a)         => write!(f, "{:<12} {}", "array", a),
+            Op::Tuple(a)         => write!(f, "{:<12} {}", "tuple", a),
+            Op::Struct(a)        => write!(f, "{:<12} {}", "struct", a),
+            Op::Inherit          => write!(f, "inherit"),
+            Op::GetField(a)      => write!(f, "{:<12} {}", "get_field", a),
+            Op::SetField(a)      => write!(f, "{:<12} {}", "set_field", a),
+            Op::GetIndex         => write!(f, "get_index"),
+            Op::SetIndex         => write!(f, "set_index"),
+            Op::Binary(ref op)   => write!(f, "{}", format!("{:?}", op).to_lowercase()),
+            Op::Unary(ref op)    => write!(f, "{}", format!("{:?}", op).to_lowercase()),
+            Op::Match(a)         => write!(f, "{:<12} {}", "match", a),
+            Op::Jump(a)          => write!(f, "{:<12} {:04}", "jump", a),
+            Op::JumpIfFalse(a)   => write!(f, "{:<12} {:04}", "jump_false", a),
+            Op::JumpIfTrue(a)    => write!(f, "{:<12} {:04}", "jump_true", a),
+            Op::Next(a)          => write!(f, "{:<12} {:04}", "next", a),
+            Op::Call(a)          => write!(f, "{:<12} {}", "call", a),
+            Op::Return           => write!(f, "return"),
+            Op::Closure(a)       => write!(f, "{:<12} {}", "closure", a),
+            Op::EnterScope       => write!(f, "enter_scope"),
+            Op::ExitScope        => write!(f, "exit_scope"),
+            Op::Mark             => write!(f, "mark"),
+            Op::Unmark           => write!(f, "unmark"),
+            Op::Unwind(a)        => write!(f, "{:<12} {}", "unwind", a),
+            Op::UnwindKeep(a)    => write!(f, "{:<12} {}", "unwind_keep", a),
+        }
+    }
+}
@@ -0,0 +1,932 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+
+use super::*;
+
+// resolves every name to its slot up front, mirroring the scopes the interpreter would make
+pub struct Compiler {
+    symtab:   Rc<SymTab>,
+    chunk:    Chunk,
+    position: Option<TokenPosition>,
+    scopes:   usize,       // how deep the scopes being compiled are, within the prototype
+    regions:  Vec<Region>, // the loops and blocks with a `return` being compiled, the innermost one last
+    fields:   Rc<RefCell<HashMap<String, Rc<String>>>>, // field names, one of each for every prototype
+}
+
+// a loop or a block with a `return` in it, which marks how high the stack was when it started,
+// so leaving it from anywhere inside can drop what was left on it
+struct Region {
+    start:  Option<u32>, // where `continue` jumps back to, for loops
+    exits:  Vec<usize>,  // the jumps `break` or `return` left to be patched once it ends
+    scopes: usize,
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler {
+            symtab:   Rc::new(SymTab::new_global()),
+            chunk:    Chunk::new(),
+            position: None,
+            scopes:   0,
+            regions:  Vec::new(),
+            fields:   Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn compile(mut self, ast: &[Statement]) -> Result<Rc<Prototype>, Response> {
+        for statement in ast {
+            self.statement(statement)?
+        }
+
+        self.emit(Op::Nil);
+        self.emit(Op::Return);
+
+        Ok(Rc::new(Prototype {
+            name:  "<main>".to_owned(),
+            arity: 0,
+            chunk: self.chunk,
+        }))
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.positions.push(self.position);
+
+        self.chunk.code.len() - 1
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        self.chunk.constants.push(value);
+        self.chunk.constants.len() as u32 - 1
+    }
+
+    // points a jump emitted earlier at the next instruction
+    fn patch(&mut self, at: usize) {
+        let target = self.chunk.code.len() as u32;
+
+        self.chunk.code[at] = match self.chunk.code[at] {
+            Op::Jump(_)        => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfTrue(_)  => Op::JumpIfTrue(target),
+            Op::Next(_)        => Op::Next(target),
+            op                 => op,
+        }
+    }
+
+    fn locate(&mut self, position: TokenPosition) {
+        self.position = Some(position)
+    }
+
+    // names defined in `f` get a scope of their own. unless a fun made in it could keep them around,
+    // the scope only exists here, and its names take slots of the environment around it, free again
+    // once it's left, instead of the vm making an environment every time it's entered
+    fn scope<T>(&mut self, funs: bool, f: &mut dyn FnMut(&mut Self) -> Result<T, Response>) -> Result<T, Response> {
+        if !funs {
+            let used   = self.symtab.owner().slots.get();
+            let inner  = Rc::new(SymTab::new_shared(self.symtab.clone()));
+            let symtab = mem::replace(&mut self.symtab, inner);
+
+            let result = f(self);
+
+            self.symtab = symtab;
+            self.symtab.owner().slots.set(used);
+
+            return result
+        }
+
+        let inner  = Rc::new(SymTab::new(self.symtab.clone(), &[]));
+        let symtab = mem::replace(&mut self.symtab, inner);
+
+        self.emit(Op::EnterScope);
+        self.scopes += 1;
+
+        let result = f(self);
+
+        self.scopes -= 1;
+        self.emit(Op::ExitScope);
+
+        self.symtab = symtab;
+
+        result
+    }
+
+    // compiles a nested prototype, returning its index
+    fn prototype(&mut self, name: &str, params: &[String], body: &mut dyn FnMut(&mut Compiler) -> Result<(), Response>) -> Result<u32, Response> {
+        let mut compiler = Compiler {
+            symtab:   Rc::new(SymTab::new(self.symtab.clone(), params)),
+            chunk:    Chunk::new(),
+            position: self.position,
+            scopes:   0,
+            regions:  Vec::new(),
+            fields:   self.fields.clone(),
+        };
+
+        body(&mut compiler)?;
+        compiler.emit(Op::Return);
+
+        self.chunk.prototypes.push(Rc::new(Prototype {
+            name:  name.to_owned(),
+            arity: params.len(),
+            chunk: compiler.chunk,
+        }));
+
+        Ok(self.chunk.prototypes.len() as u32 - 1)
+    }
+
+    fn fun(&mut self, fun: &Fun) -> Result<u32, Response> {
+        let name   = function_name(&fun.name);
+        let params = fun.params.iter().map(|p| p.name.clone()).collect::<Vec<String>>();
+
+        self.locate(fun.position);
+        self.prototype(&name, &params, &mut |c| c.statements(&fun.body))
+    }
+
+    fn function_match(&mut self, function: &FunctionMatch) -> Result<u32, Response> {
+        let name = function_name(&function.name);
+
+        let arity = function.arity();
+
+        // the params take slots no identifier can name, and several are matched as one array
+        let params = (0 .. arity).map(|slot| format!(" {}", slot)).collect::<Vec<String>>();
+
+        self.locate(function.position);
+        self.prototype(&name, &params, &mut |c| {
+            for slot in 0 .. arity {
+                c.emit(Op::Get(slot as u32, 0));
+            }
+
+            if arity != 1 {
+                c.emit(Op::Array(arity as u32));
+            }
+
+            c.arms(&function.arms)
+        })
+    }
+
+    fn define(&mut self, name: &str) {
+        let index = self.symtab.add_name(name);
+        self.emit(Op::Define(index as u32));
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), Response> {
+        match *s {
+            Statement::Expression(ref e) => {
+                self.expression(e)?;
+                self.emit(Op::Pop);
+            },
+
+            Statement::Definition(Definition {ref name, ref right, ref position, ..}) => {
+                self.locate(*position);
+
+                match *right {
+                    Some(ref right) => self.expression(right)?,
+                    None            => { self.emit(Op::Nil); },
+                }
+
+                self.define(name)
+            },
+
+            // the visitor made sure the pattern always fits, leaving every binding on the stack
+            Statement::Destructure(Destructure {ref pattern, ref right, position, ..}) => {
+                self.locate(position);
+                self.expression(right)?;
+
+                self.chunk.patterns.push(pattern.clone());
+                let index = self.chunk.patterns.len() as u32 - 1;
+
+                self.emit(Op::Match(index));
+                self.emit(Op::Pop);
+
+                for name in pattern.bindings().iter().rev() {
+                    self.define(name)
+                }
+            },
+
+            Statement::Assignment(Assignment {ref left, ref right, ref position}) => {
+                self.locate(*position);
+                self.assign(left, &mut |c| c.expression(right))?
+            },
+
+            Statement::Fun(ref fun) => {
+                let define = self.function_slot(&fun.name);
+                let index  = self.fun(fun)?;
+
+                self.store_function(&fun.name, define, index)?
+            },
+
+            Statement::FunctionMatch(ref function) => {
+                let define = self.function_slot(&function.name);
+                let index  = self.function_match(function)?;
+
+                self.store_function(&function.name, define, index)?
+            },
+
+            Statement::Extern(ref statement) => match **statement {
+                Statement::Definition(Definition {ref name, ref position, ..}) => {
+                    self.locate(*position);
+
+                    match builtin(name) {
+                        Some(builtin) => {
+                            let constant = self.constant(Value::Closure(Rc::new(Closure::Builtin(builtin))));
+
+                            self.emit(Op::Constant(constant));
+                            self.define(name)
+                        },
+
+                        None => return Err(Response::error(Some(ErrorLocation::new(*position, name.len())), format!("no builtin extern named: {}", name))),
+                    }
+                },
+
+                Statement::Struct(_) => (),
+                ref statement        => self.statement(statement)?,
+            },
+
+            Statement::Data(ref data) => for variant in &data.variants {
+                self.locate(variant.position);
+
+                let constant = self.constant(Value::variant(variant));
+
+                self.emit(Op::Constant(constant));
+                self.define(&variant.name)
+            },
+
+            Statement::If(ref base) => {
+                self.if_expression(base, false)?;
+                self.emit(Op::Pop);
+            },
+
+            Statement::Unless(ref unless) => {
+                self.if_expression(&unless.base, true)?;
+                self.emit(Op::Pop);
+            },
+
+            Statement::MatchPattern(ref m) => {
+                self.match_pattern(m)?;
+                self.emit(Op::Pop);
+            },
+
+            Statement::While(While {ref condition, ref body}) => {
+                self.emit(Op::Mark);
+
+                let start = self.chunk.code.len() as u32;
+
+                self.expression(condition)?;
+                let exit = self.emit(Op::JumpIfFalse(0));
+
+                self.loop_body(start, makes_funs(body), &mut |c| {
+                    for statement in body {
+                        c.statement(statement)?
+                    }
+
+                    Ok(())
+                })?;
+
+                self.patch(exit);
+                self.emit(Op::Unmark);
+            },
+
+            // the iterable and how far into it the loop is stay on the stack for as long as it runs
+            Statement::For(For {ref index, ref name, ref iterable, ref body, position}) => {
+                self.expression(iterable)?;
+
+                let zero = self.constant(Value::Number(0.0));
+                self.emit(Op::Constant(zero));
+
+                self.locate(position);
+                self.emit(Op::Mark);
+
+                let start = self.chunk.code.len() as u32;
+                let exit  = self.emit(Op::Next(0));
+
+                self.loop_body(start, makes_funs(body), &mut |c| {
+                    c.define(name);
+
+                    match *index {
+                        Some(ref index) => c.define(index),
+                        None            => { c.emit(Op::Pop); },
+                    }
+
+                    for statement in body {
+                        c.statement(statement)?
+                    }
+
+                    Ok(())
+                })?;
+
+                self.patch(exit);
+
+                self.emit(Op::Unmark);
+                self.emit(Op::Pop);
+                self.emit(Op::Pop);
+            },
+
+            Statement::Break(position) => {
+                self.locate(position);
+
+                let region = self.leave(|region| region.start.is_some(), false).expect("break outside of a loop");
+                let jump   = self.emit(Op::Jump(0));
+
+                self.regions[region].exits.push(jump)
+            },
+
+            Statement::Continue(position) => {
+                self.locate(position);
+
+                let region = self.leave(|region| region.start.is_some(), false).expect("continue outside of a loop");
+                let start  = self.regions[region].start.expect("continue outside of a loop");
+
+                self.emit(Op::Jump(start));
+            },
+
+            // inside a block expression, a `return` jumps to the end of the block with its value
+            Statement::Return(ref e) => {
+                match *e {
+                    Some(ref e) => self.expression(e)?,
+                    None        => { self.emit(Op::Nil); },
+                }
+
+                match self.leave(|region| region.start.is_none(), true) {
+                    Some(region) => {
+                        let jump = self.emit(Op::Jump(0));
+                        self.regions[region].exits.push(jump)
+                    },
+
+                    None => { self.emit(Op::Return); },
+                }
+            },
+
+            // a struct's name holds its methods, which instances start out with
+            Statement::Struct(Struct {ref name, ref position, ..}) => {
+                self.locate(*position);
+
+                self.chunk.structs.push(FieldNames::default());
+
+                self.emit(Op::Struct(self.chunk.structs.len() as u32 - 1));
+                self.define(name)
+            },
+
+            Statement::Implementation(ref implementation) => for method in implementation.methods() {
+                self.statement(&method)?
+            },
+
+            // only the checker cares about these
+            Statement::Interface(_) |
+            Statement::Import(_)    => (),
+        }
+
+        Ok(())
+    }
+
+    // named functions get their slot before the body is compiled, so they can call themselves
+    // compiles the body of a loop in its own scope, jumping back to `start` after it
+    fn loop_body(&mut self, start: u32, funs: bool, body: &mut dyn FnMut(&mut Self) -> Result<(), Response>) -> Result<(), Response> {
+        self.region(Some(start), &mut |c| {
+            c.scope(funs, body)?;
+            c.emit(Op::Jump(start));
+
+            Ok(())
+        })
+    }
+
+    // compiles what's inside a region, its exits jumping to right after it
+    fn region(&mut self, start: Option<u32>, body: &mut dyn FnMut(&mut Self) -> Result<(), Response>) -> Result<(), Response> {
+        self.regions.push(Region {
+            start,
+            exits:  Vec::new(),
+            scopes: self.scopes,
+        });
+
+        let result = body(self);
+        let done   = self.regions.pop().expect("vm region underflow");
+
+        for jump in done.exits {
+            self.patch(jump)
+        }
+
+        result
+    }
+
+    // unwinds the stack and exits the scopes back to the innermost region that's `wanted`, keeping
+    // the value on top when asked to, and returns which region that is
+    fn leave(&mut self, wanted: fn(&Region) -> bool, keep: bool) -> Option<usize> {
+        let region = self.regions.iter().rposition(wanted)?;
+        let inner  = (self.regions.len() - region - 1) as u32;
+        let scopes = self.regions[region].scopes;
+
+        self.emit(if keep { Op::UnwindKeep(inner) } else { Op::Unwind(inner) });
+
+        for _ in scopes .. self.scopes {
+            self.emit(Op::ExitScope);
+        }
+
+        Some(region)
+    }
+
+    fn function_slot(&mut self, name: &Option<Expression>) -> Option<u32> {
+        match *name {
+            Some(Expression::Identifier(ref name, _)) => Some(self.symtab.add_name(name) as u32),
+            _                                         => None,
+        }
+    }
+
+    fn store_function(&mut self, name: &Option<Expression>, define: Option<u32>, index: u32) -> Result<(), Response> {
+        match (define, name) {
+            (Some(slot), _) => {
+                self.emit(Op::Closure(index));
+                self.emit(Op::Define(slot));
+            },
+
+            (None, Some(name)) => self.assign(name, &mut |c| {
+                c.emit(Op::Closure(index));
+                Ok(())
+            })?,
+
+            (None, None) => (),
+        }
+
+        Ok(())
+    }
+
+    // `right` pushes the value being stored
+    fn assign(&mut self, left: &Expression, right: &mut dyn FnMut(&mut Self) -> Result<(), Response>) -> Result<(), Response> {
+        match *left {
+            Expression::Identifier(ref name, position) => {
+                self.locate(position);
+
+                match self.symtab.get_name(name) {
+                    Some((index, env_index)) => {
+                        right(self)?;
+                        self.emit(Op::Set(index as u32, env_index as u32));
+                    },
+
+                    None => return Err(Response::error(Some(ErrorLocation::new(position, name.len())), format!("undefined: {}", name))),
+                }
+            },
+
+            Expression::Index(Index {ref id, ref index, field, position}) => {
+                self.expression(id)?;
+
+                if field {
+                    let name = self.field_name(index)?;
+
+                    right(self)?;
+
+                    self.locate(position);
+                    self.emit(Op::SetField(name));
+                } else {
+                    self.expression(index)?;
+                    right(self)?;
+
+                    self.locate(position);
+                    self.emit(Op::SetIndex);
+                }
+            },
+
+            // the elements are taken into slots no identifier can name, each then assigned from its own
+            Expression::Tuple(ref content, position) => {
+                let slots = (0 .. content.len()).map(|slot| format!(" {}", slot)).collect::<Vec<String>>();
+
+                self.chunk.patterns.push(Pattern::Tuple(slots.iter().map(|slot| Pattern::Binding(slot.clone(), position)).collect(), position));
+                let pattern = self.chunk.patterns.len() as u32 - 1;
+
+                // no fun can name the slots, so they can't outlive it
+                self.scope(false, &mut |c| {
+                    right(c)?;
+
+                    c.locate(position);
+                    c.emit(Op::Match(pattern));
+                    c.emit(Op::Pop);
+
+                    for slot in slots.iter().rev() {
+                        c.define(slot)
+                    }
+
+                    for (left, slot) in content.iter().zip(&slots) {
+                        let (index, env_index) = c.symtab.get_name(slot).unwrap();
+
+                        c.assign(left, &mut |c| {
+                            c.emit(Op::Get(index as u32, env_index as u32));
+                            Ok(())
+                        })?
+                    }
+
+                    Ok(())
+                })?
+            },
+
+            ref c => return Err(Response::error(self.position.map(|p| ErrorLocation::new(p, 1)), format!("can't assign to: {:?}", c))),
+        }
+
+        Ok(())
+    }
+
+    // the name of a field, which every use of it shares, so the vm can tell it apart by its address
+    fn field(&mut self, e: &Expression) -> Result<Rc<String>, Response> {
+        match *e {
+            Expression::Identifier(ref name, _) => Ok(self.fields.borrow_mut().entry(name.clone()).or_insert_with(|| Rc::new(name.clone())).clone()),
+            ref c                               => Err(Response::error(self.position.map(|p| ErrorLocation::new(p, 1)), format!("invalid field: {:?}", c))),
+        }
+    }
+
+    fn field_name(&mut self, e: &Expression) -> Result<u32, Response> {
+        let name = self.field(e)?;
+        Ok(self.constant(Value::Str(name)))
+    }
+
+    // leaves the value of the body on the stack
+    fn statements(&mut self, body: &[Statement]) -> Result<(), Response> {
+        for (i, statement) in body.iter().enumerate() {
+            if i + 1 == body.len() {
+                if let Statement::Expression(ref e) = *statement {
+                    return self.expression(e)
+                }
+            }
+
+            self.statement(statement)?
+        }
+
+        self.emit(Op::Nil);
+
+        Ok(())
+    }
+
+    fn block(&mut self, body: &[Statement]) -> Result<(), Response> {
+        self.scope(makes_funs(body), &mut |c| c.statements(body))
+    }
+
+    fn if_expression(&mut self, base: &If, negate: bool) -> Result<(), Response> {
+        self.expression(&base.condition)?;
+
+        let skip = self.emit(if negate { Op::JumpIfTrue(0) } else { Op::JumpIfFalse(0) });
+
+        self.block(&base.body)?;
+
+        let mut ends = vec![self.emit(Op::Jump(0))];
+
+        self.patch(skip);
+
+        if let Some(ref elses) = base.elses {
+            for (condition, body) in elses {
+                match *condition {
+                    Some(ref condition) => {
+                        self.expression(condition)?;
+                        let skip = self.emit(Op::JumpIfFalse(0));
+
+                        self.block(body)?;
+                        ends.push(self.emit(Op::Jump(0)));
+
+                        self.patch(skip)
+                    },
+
+                    None => {
+                        self.block(body)?;
+
+                        for end in ends {
+                            self.patch(end)
+                        }
+
+                        return Ok(())
+                    },
+                }
+            }
+        }
+
+        self.emit(Op::Nil);
+
+        for end in ends {
+            self.patch(end)
+        }
+
+        Ok(())
+    }
+
+    fn match_pattern(&mut self, m: &MatchPattern) -> Result<(), Response> {
+        self.expression(&m.matching)?;
+        self.arms(&m.arms)
+    }
+
+    // expects the subject on the stack, replacing it with the value of the taken arm
+    fn arms(&mut self, arms: &[MatchArm]) -> Result<(), Response> {
+        let mut ends = Vec::new();
+
+        for arm in arms {
+            self.chunk.patterns.push(arm.pattern());
+            let pattern = self.chunk.patterns.len() as u32 - 1;
+
+            self.locate(arm.position);
+
+            self.emit(Op::Dup);
+            self.emit(Op::Match(pattern));
+
+            let skip = self.emit(Op::JumpIfFalse(0));
+
+            let funs = arm.guard.as_ref().is_some_and(expression_makes_funs) || expression_makes_funs(&arm.body);
+
+            // the bindings are on the stack above the subject, the last one on top
+            let guard_fails = self.scope(funs, &mut |c| {
+                for name in arm.bindings().iter().rev() {
+                    c.define(name)
+                }
+
+                let guard_fails = match arm.guard {
+                    Some(ref guard) => {
+                        c.expression(guard)?;
+                        Some(c.emit(Op::JumpIfFalse(0)))
+                    },
+
+                    None => None,
+                };
+
+                c.emit(Op::Pop);
+                c.expression(&arm.body)?;
+
+                Ok(guard_fails)
+            })?;
+
+            ends.push(self.emit(Op::Jump(0)));
+
+            // leaving the scope the guard was checked in, with the subject still there for the next arm
+            if let Some(guard_fails) = guard_fails {
+                self.patch(guard_fails);
+
+                if funs {
+                    self.emit(Op::ExitScope);
+                }
+            }
+
+            self.patch(skip)
+        }
+
+        self.emit(Op::Pop);
+        self.emit(Op::Nil);
+
+        for end in ends {
+            self.patch(end)
+        }
+
+        Ok(())
+    }
+
+    fn expression(&mut self, e: &Expression) -> Result<(), Response> {
+        match *e {
+            Expression::Number(n)  => {
+                let constant = self.constant(Value::Number(n));
+                self.emit(Op::Constant(constant));
+            },
+
+            Expression::Str(ref s) => {
+                let constant = self.constant(Value::str(s));
+                self.emit(Op::Constant(constant));
+            },
+
+            Expression::Char(c) => {
+                let constant = self.constant(Value::Char(c));
+                self.emit(Op::Constant(constant));
+            },
+
+            Expression::Bool(b) => {
+                self.emit(if b { Op::True } else { Op::False });
+            },
+
+            Expression::Nil => {
+                self.emit(Op::Nil);
+            },
+
+            Expression::Identifier(ref name, position) => {
+                self.locate(position);
+
+                match self.symtab.get_name(name) {
+                    Some((index, env_index)) => { self.emit(Op::Get(index as u32, env_index as u32)); },
+                    None                     => return Err(Response::error(Some(ErrorLocation::new(position, name.len())), format!("undefined: {}", name))),
+                }
+            },
+
+            Expression::BinaryOp(BinaryOp {ref left, op, ref right, position}) => match op {
+                Operand::PipeRight | Operand::PipeLeft => {
+                    let (function, argument) = if op == Operand::PipeRight { (right, left) } else { (left, right) };
+
+                    self.expression(function)?;
+                    self.expression(argument)?;
+
+                    self.locate(position);
+                    self.emit(Op::Call(1));
+                },
+
+                // leaves the left side when it settles the answer, jumping over the right
+                Operand::And | Operand::Or => {
+                    self.expression(left)?;
+                    self.emit(Op::Dup);
+
+                    let skip = self.emit(if op == Operand::And { Op::JumpIfFalse(0) } else { Op::JumpIfTrue(0) });
+
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+
+                    self.patch(skip)
+                },
+
+                // keeps the left side unless it's nil
+                Operand::Default => {
+                    self.expression(left)?;
+                    self.emit(Op::Dup);
+                    self.emit(Op::Nil);
+                    self.emit(Op::Binary(Operand::NEqual));
+
+                    let skip = self.emit(Op::JumpIfTrue(0));
+
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+
+                    self.patch(skip)
+                },
+
+                _ => {
+                    self.expression(left)?;
+                    self.expression(right)?;
+
+                    self.locate(position);
+                    self.emit(Op::Binary(op));
+                },
+            },
+
+            Expression::UnaryOp(UnaryOp {op, ref expr, position}) => {
+                self.expression(expr)?;
+
+                self.locate(position);
+                self.emit(Op::Unary(op));
+            },
+
+            Expression::Call(Call {ref callee, ref args, position}) => {
+                self.expression(callee)?;
+
+                for arg in args {
+                    self.expression(arg)?
+                }
+
+                self.locate(position);
+                self.emit(Op::Call(args.len() as u32));
+            },
+
+            Expression::Index(Index {ref id, ref index, field, position}) => {
+                self.expression(id)?;
+
+                if field {
+                    let name = self.field_name(index)?;
+
+                    self.locate(position);
+                    self.emit(Op::GetField(name));
+                } else {
+                    self.expression(index)?;
+
+                    self.locate(position);
+                    self.emit(Op::GetIndex);
+                }
+            },
+
+            Expression::Array(ref content, _) => {
+                for e in content {
+                    self.expression(e)?
+                }
+
+                self.emit(Op::Array(content.len() as u32));
+            },
+
+            Expression::Tuple(ref content, _) => {
+                for e in content {
+                    self.expression(e)?
+                }
+
+                self.emit(Op::Tuple(content.len() as u32));
+            },
+
+            Expression::If(ref base)        => self.if_expression(base, false)?,
+            Expression::Unless(ref a)       => self.if_expression(&a.base, true)?,
+            Expression::MatchPattern(ref m) => self.match_pattern(m)?,
+
+            Expression::Block(ref body) => if contains_return(body) {
+                // a `return` has to stop here, so the block is a region its returns jump out of
+                self.emit(Op::Mark);
+                self.region(None, &mut |c| c.block(body))?;
+                self.emit(Op::Unmark);
+            } else {
+                self.block(body)?
+            },
+
+            Expression::Initialization(ref a) => {
+                let mut names = Vec::new();
+
+                for value in &a.values {
+                    names.push(self.field(&value.left)?);
+                    self.expression(&value.right)?
+                }
+
+                self.chunk.structs.push(Rc::new(names));
+                self.emit(Op::Struct(self.chunk.structs.len() as u32 - 1));
+
+                if let Expression::Identifier(ref name, _) = a.id {
+                    if let Some((index, env_index)) = self.symtab.get_name(name) {
+                        self.emit(Op::Get(index as u32, env_index as u32));
+                        self.emit(Op::Inherit);
+                    }
+                }
+            },
+
+            Expression::Fun(ref fun) => {
+                let index = self.fun(fun)?;
+                self.emit(Op::Closure(index));
+            },
+
+            Expression::FunctionMatch(ref function) => {
+                let index = self.function_match(function)?;
+                self.emit(Op::Closure(index));
+            },
+
+            Expression::Struct(_) |
+            Expression::EOF       => { self.emit(Op::Nil); },
+        }
+
+        Ok(())
+    }
+}
+
+fn function_name(name: &Option<Expression>) -> String {
+    match *name {
+        Some(Expression::Identifier(ref name, _))              => name.clone(),
+        Some(Expression::Index(Index {ref id, ref index, ..})) => match (&**id, &**index) {
+            (Expression::Identifier(a, _), Expression::Identifier(b, _)) => format!("{}.{}", a, b),
+            _                                                            => "<method>".to_owned(),
+        },
+
+        _ => "<anonymous>".to_owned(),
+    }
+}
+
+// whether a fun can be made while these run, which could keep the names they define around
+fn makes_funs(body: &[Statement]) -> bool {
+    body.iter().any(|s| match *s {
+        Statement::Fun(_)            |
+        Statement::FunctionMatch(_)  |
+        Statement::Implementation(_) => true,
+
+        Statement::Expression(ref e)                               => expression_makes_funs(e),
+        Statement::Definition(Definition {right: Some(ref e), ..}) => expression_makes_funs(e),
+        Statement::Destructure(ref d)                              => expression_makes_funs(&d.right),
+        Statement::Assignment(ref a)                               => expression_makes_funs(&a.left) || expression_makes_funs(&a.right),
+        Statement::If(ref base)                                    => if_makes_funs(base),
+        Statement::Unless(ref unless)                              => if_makes_funs(&unless.base),
+        Statement::MatchPattern(ref m)                             => match_makes_funs(m),
+        Statement::Return(Some(ref e))                             => expression_makes_funs(e),
+        Statement::While(ref w)                                    => expression_makes_funs(&w.condition) || makes_funs(&w.body),
+        Statement::For(ref f)                                      => expression_makes_funs(&f.iterable) || makes_funs(&f.body),
+
+        Statement::Definition(_) |
+        Statement::Struct(_)     |
+        Statement::Data(_)       |
+        Statement::Interface(_)  |
+        Statement::Return(None)  |
+        Statement::Import(_)     |
+        Statement::Extern(_)     |
+        Statement::Break(_)      |
+        Statement::Continue(_)   => false,
+    })
+}
+
+fn expression_makes_funs(e: &Expression) -> bool {
+    match *e {
+        Expression::Fun(_)           |
+        Expression::FunctionMatch(_) => true,
+
+        Expression::Block(ref body)            => makes_funs(body),
+        Expression::BinaryOp(ref op)           => expression_makes_funs(&op.left) || expression_makes_funs(&op.right),
+        Expression::UnaryOp(ref op)            => expression_makes_funs(&op.expr),
+        Expression::MatchPattern(ref m)        => match_makes_funs(m),
+        Expression::Call(ref call)             => expression_makes_funs(&call.callee) || call.args.iter().any(|arg| expression_makes_funs(arg)),
+        Expression::Index(ref index)           => expression_makes_funs(&index.id) || expression_makes_funs(&index.index),
+        Expression::If(ref base)               => if_makes_funs(base),
+        Expression::Unless(ref unless)         => if_makes_funs(&unless.base),
+        Expression::Initialization(ref a)      => a.values.iter().any(|value| expression_makes_funs(&value.right)),
+        Expression::Array(ref content, _)      |
+        Expression::Tuple(ref content, _)      => content.iter().any(expression_makes_funs),
+
+        Expression::Number(_)        |
+        Expression::Bool(_)          |
+        Expression::Str(_)           |
+        Expression::Char(_)          |
+        Expression::Nil              |
+        Expression::Identifier(..)   |
+        Expression::Struct(_)        |
+        Expression::EOF              => false,
+    }
+}
+
+fn if_makes_funs(base: &If) -> bool {
+    expression_makes_funs(&base.condition) || makes_funs(&base.body) || base.elses.as_ref().is_some_and(|elses| {
+        elses.iter().any(|(condition, body)| condition.as_ref().is_some_and(expression_makes_funs) || makes_funs(body))
+    })
+}
+
+fn match_makes_funs(m: &MatchPattern) -> bool {
+    expression_makes_funs(&m.matching) || m.arms.iter().any(|arm| arm.guard.as_ref().is_some_and(expression_makes_funs) || expression_makes_funs(&arm.body))
+}
@@ -0,0 +1,352 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+
+use super::*;
+
+const MAX_FRAMES: usize = 4096;
+
+struct Frame {
+    prototype: Rc<Prototype>,
+    ip:        usize,
+    env:       Rc<Environment>,
+    base:      usize, // where the callee sits on the stack
+    marks:     usize, // how many marks there were when it was called
+}
+
+pub struct Machine {
+    stack:  Vec<Value>,
+    frames: Vec<Frame>,
+    marks:  Vec<usize>, // how high the stack was as each loop or block being run started
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        Machine {
+            stack:  Vec::new(),
+            frames: Vec::new(),
+            marks:  Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self, prototype: Rc<Prototype>) -> Result<Value, Response> {
+        self.stack.clear();
+        self.marks.clear();
+
+        self.frames = vec![Frame {
+            prototype,
+            ip:    0,
+            env:   Rc::new(Environment::new_global()),
+            base:  0,
+            marks: 0,
+        }];
+
+        self.execute()
+    }
+
+    fn error(&self, message: String) -> Response {
+        let position = self.frames.last().and_then(|frame| frame.prototype.chunk.positions[frame.ip - 1]);
+        Response::error(position.map(|p| ErrorLocation::new(p, 1)), message)
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("vm stack underflow")
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("vm without frames")
+    }
+
+    fn execute(&mut self) -> Result<Value, Response> {
+        loop {
+            let op = {
+                let frame = self.frame();
+                frame.ip += 1;
+
+                frame.prototype.chunk.code[frame.ip - 1]
+            };
+
+            match op {
+                Op::Constant(c) => {
+                    let value = self.frame().prototype.chunk.constants[c as usize].clone();
+                    self.stack.push(value)
+                },
+
+                Op::Nil   => self.stack.push(Value::Nil),
+                Op::True  => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
+
+                Op::Pop => {
+                    self.pop();
+                },
+
+                Op::Dup => {
+                    let value = self.stack.last().expect("vm stack underflow").clone();
+                    self.stack.push(value)
+                },
+
+                Op::Get(index, env_index) => {
+                    let value = self.frame().env.get(index as usize, env_index as usize)?;
+                    self.stack.push(value)
+                },
+
+                Op::Set(index, env_index) => {
+                    let value = self.pop();
+                    self.frame().env.set(index as usize, env_index as usize, value)?
+                },
+
+                Op::Define(index) => {
+                    let value = self.pop();
+                    self.frame().env.define(index as usize, value)
+                },
+
+                Op::Array(len) => {
+                    let content = self.stack.split_off(self.stack.len() - len as usize);
+                    self.stack.push(Value::array(content))
+                },
+
+                Op::Tuple(len) => {
+                    let content = self.stack.split_off(self.stack.len() - len as usize);
+                    self.stack.push(Value::Tuple(Rc::new(content)))
+                },
+
+                Op::Struct(s) => {
+                    let names  = self.frame().prototype.chunk.structs[s as usize].clone();
+                    let values = self.stack.split_off(self.stack.len() - names.len());
+
+                    self.stack.push(Value::structure(Fields::new(names, values)))
+                },
+
+                // pops the struct's method table, leaving the instance below it
+                Op::Inherit => {
+                    let structure = self.pop();
+                    inherit(self.stack.last().expect("vm stack underflow"), &structure)
+                },
+
+                Op::GetField(c) => {
+                    let name  = self.field(c);
+                    let id    = self.pop();
+                    let value = get_field(&id, &name).map_err(|message| self.error(message))?;
+
+                    self.stack.push(value)
+                },
+
+                Op::SetField(c) => {
+                    let name  = self.field(c);
+                    let value = self.pop();
+                    let id    = self.pop();
+
+                    set_field(&id, &name, value).map_err(|message| self.error(message))?
+                },
+
+                Op::GetIndex => {
+                    let index = self.pop();
+                    let id    = self.pop();
+                    let value = get_index(&id, &index).map_err(|message| self.error(message))?;
+
+                    self.stack.push(value)
+                },
+
+                Op::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let id    = self.pop();
+
+                    set_index(&id, &index, value).map_err(|message| self.error(message))?
+                },
+
+                Op::Binary(op) => {
+                    let right = self.pop();
+                    let left  = self.pop();
+                    let value = binary_op(&op, &left, &right).map_err(|message| self.error(message))?;
+
+                    self.stack.push(value)
+                },
+
+                Op::Unary(op) => {
+                    let value = self.pop();
+                    let value = unary_op(&op, &value).map_err(|message| self.error(message))?;
+
+                    self.stack.push(value)
+                },
+
+                // pushes what the pattern binds, in order, and then whether it matched at all
+                Op::Match(p) => {
+                    let subject = self.pop();
+                    let pattern = &self.frame().prototype.chunk.patterns[p as usize];
+
+                    let mut bound = HashMap::new();
+
+                    if destructure(pattern, &subject, &mut bound) {
+                        for name in pattern.bindings() {
+                            self.stack.push(bound.remove(&name).unwrap())
+                        }
+
+                        self.stack.push(Value::Bool(true))
+                    } else {
+                        self.stack.push(Value::Bool(false))
+                    }
+                },
+
+                Op::Jump(target) => self.frame().ip = target as usize,
+
+                Op::JumpIfFalse(target) | Op::JumpIfTrue(target) => {
+                    let jump_on = op != Op::JumpIfFalse(target);
+
+                    match self.pop() {
+                        Value::Bool(b) => if b == jump_on {
+                            self.frame().ip = target as usize
+                        },
+
+                        c => return Err(self.error(format!("expected boolean condition, found: {}", c.type_name()))),
+                    }
+                },
+
+                // below the top is what a loop goes through, and on top how far into it it is
+                Op::Next(target) => {
+                    let top = self.stack.len() - 1;
+
+                    let i = match self.stack[top] {
+                        Value::Number(i) => i as usize,
+                        ref c            => return Err(self.error(format!("can't iterate at: {}", c.type_name()))),
+                    };
+
+                    match element(&self.stack[top - 1], i) {
+                        Ok(Some(value)) => {
+                            self.stack[top] = Value::Number(i as f64 + 1.0);
+
+                            self.stack.push(Value::Number(i as f64));
+                            self.stack.push(value)
+                        },
+
+                        Ok(None)     => self.frame().ip = target as usize,
+                        Err(message) => return Err(self.error(message)),
+                    }
+                },
+
+                Op::Call(argc) => self.call(argc as usize)?,
+
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().expect("vm without frames");
+
+                    if self.frames.is_empty() {
+                        return Ok(value)
+                    }
+
+                    self.stack.truncate(frame.base);
+                    self.marks.truncate(frame.marks);
+                    self.stack.push(value)
+                },
+
+                Op::Closure(p) => {
+                    let frame = self.frame();
+
+                    let closure = Closure::Compiled {
+                        prototype: frame.prototype.chunk.prototypes[p as usize].clone(),
+                        env:       frame.env.clone(),
+                    };
+
+                    self.stack.push(Value::Closure(Rc::new(closure)))
+                },
+
+                Op::EnterScope => {
+                    let frame = self.frame();
+                    frame.env = Rc::new(Environment::new(frame.env.clone(), Vec::new()))
+                },
+
+                Op::ExitScope => {
+                    let frame  = self.frame();
+                    let parent = frame.env.parent.clone().expect("vm scope underflow");
+
+                    frame.env = parent
+                },
+
+                Op::Mark => self.marks.push(self.stack.len()),
+
+                Op::Unmark => {
+                    self.marks.pop().expect("vm mark underflow");
+                },
+
+                // drops the marks of the regions being left, and what's on the stack above the one left to
+                Op::Unwind(inner) => self.unwind(inner as usize),
+
+                Op::UnwindKeep(inner) => {
+                    let value = self.pop();
+
+                    self.unwind(inner as usize);
+                    self.stack.push(value)
+                },
+            }
+        }
+    }
+
+    // the name of a field, which the compiler made a constant
+    fn field(&mut self, c: u32) -> Rc<String> {
+        match self.frame().prototype.chunk.constants[c as usize] {
+            Value::Str(ref name) => name.clone(),
+            ref c                => unreachable!("field named by a {}", c.type_name()),
+        }
+    }
+
+    fn unwind(&mut self, inner: usize) {
+        let marks = self.marks.len() - inner;
+        self.marks.truncate(marks);
+
+        let height = *self.marks.last().expect("vm mark underflow");
+        self.stack.truncate(height)
+    }
+
+    fn call(&mut self, argc: usize) -> Result<(), Response> {
+        let base = self.stack.len() - argc - 1;
+
+        let closure = match self.stack[base] {
+            Value::Closure(ref closure) => closure.clone(),
+            ref c                       => return Err(self.error(format!("can't call non-fun: {}", c.type_name()))),
+        };
+
+        let arity = match *closure {
+            Closure::Compiled {ref prototype, ..} => prototype.arity,
+            Closure::Builtin(ref builtin)         => builtin.arity,
+            Closure::Constructor {arity, ..}      => arity,
+            _                                     => return Err(self.error("can't call an interpreted fun from the vm".to_owned())),
+        };
+
+        if arity != argc {
+            return Err(self.error(format!("function given {} arguments, expected: {}", argc, arity)))
+        }
+
+        let args = self.stack.split_off(base + 1);
+
+        match *closure {
+            Closure::Compiled {ref prototype, ref env} => {
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err(self.error("stack overflow".to_owned()))
+                }
+
+                self.frames.push(Frame {
+                    prototype: prototype.clone(),
+                    ip:        0,
+                    env:       Rc::new(Environment::new(env.clone(), args)),
+                    base,
+                    marks:     self.marks.len(),
+                })
+            },
+
+            Closure::Builtin(ref builtin) => {
+                let value = (builtin.function)(&args)?;
+
+                self.stack.pop();
+                self.stack.push(value)
+            },
+
+            Closure::Constructor {ref tag, ..} => {
+                self.stack.pop();
+                self.stack.push(Value::Variant(tag.clone(), Rc::new(args)))
+            },
+
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+}
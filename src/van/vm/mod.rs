@@ -0,0 +1,9 @@
+pub mod chunk;
+pub mod compiler;
+pub mod machine;
+
+pub use super::*;
+
+pub use self::chunk::*;
+pub use self::compiler::*;
+pub use self::machine::*;
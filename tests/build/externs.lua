@@ -0,0 +1,53 @@
+-- writes values the way the vm does, numbers with the shortest digits that read back the same
+local function __show(v)
+    if type(v) ~= "number" then
+        return tostring(v)
+    elseif v ~= v then
+        return "nan"
+    elseif v == math.huge or v == -math.huge then
+        return v > 0 and "inf" or "-inf"
+    elseif v == 0 then
+        return "0"
+    elseif v % 1 == 0 and math.abs(v) < 1e15 then
+        return string.format("%.0f", v)
+    end
+
+    for precision = 1, 17 do
+        local written = string.format("%." .. (precision - 1) .. "e", v)
+
+        if tonumber(written) == v then
+            local sign, digits, exponent = written:match("^(-?)([%d.]+)e([-+]%d+)$")
+
+            digits   = digits:gsub("%.", "")
+            exponent = tonumber(exponent)
+
+            if exponent < 0 then
+                return sign .. "0." .. string.rep("0", -exponent - 1) .. digits
+            elseif exponent + 1 < #digits then
+                return sign .. digits:sub(1, exponent + 1) .. "." .. digits:sub(exponent + 2)
+            else
+                return sign .. digits .. string.rep("0", exponent + 1 - #digits)
+            end
+        end
+    end
+
+    return tostring(v)
+end
+
+local Range = {}
+Range.__index = Range
+
+Range["until"] = function(n)
+    return n + 1
+end
+
+local span = {start = 1, ["end"] = 2}
+local r = setmetatable({["end"] = 3, is_open = true}, Range)
+
+if r["end"] == 3 then
+    print("ends at 3")
+else
+    print("ends elsewhere")
+end
+
+print((((("" .. __show(now_(span["end"]))) .. " ") .. __show(r["end"])) .. " ") .. __show(r["until"](4)))
@@ -0,0 +1,98 @@
+-- writes values the way the vm does, numbers with the shortest digits that read back the same
+local function __show(v)
+    if type(v) ~= "number" then
+        return tostring(v)
+    elseif v ~= v then
+        return "nan"
+    elseif v == math.huge or v == -math.huge then
+        return v > 0 and "inf" or "-inf"
+    elseif v == 0 then
+        return "0"
+    elseif v % 1 == 0 and math.abs(v) < 1e15 then
+        return string.format("%.0f", v)
+    end
+
+    for precision = 1, 17 do
+        local written = string.format("%." .. (precision - 1) .. "e", v)
+
+        if tonumber(written) == v then
+            local sign, digits, exponent = written:match("^(-?)([%d.]+)e([-+]%d+)$")
+
+            digits   = digits:gsub("%.", "")
+            exponent = tonumber(exponent)
+
+            if exponent < 0 then
+                return sign .. "0." .. string.rep("0", -exponent - 1) .. digits
+            elseif exponent + 1 < #digits then
+                return sign .. digits:sub(1, exponent + 1) .. "." .. digits:sub(exponent + 2)
+            else
+                return sign .. digits .. string.rep("0", exponent + 1 - #digits)
+            end
+        end
+    end
+
+    return tostring(v)
+end
+
+local function __check(i, length)
+    if i < 0 or i % 1 ~= 0 or i >= length then
+        error("index out of bounds: " .. __show(i), 3)
+    end
+end
+
+-- indexes from 0, failing outside of the array like the vm does
+local function __at(t, i)
+    __check(i, #t)
+    return t[i + 1]
+end
+
+local Point = {}
+Point.__index = Point
+
+local function Circle(_0)
+    return {tag = "Circle", _0}
+end
+local function Rect(_0, _1)
+    return {tag = "Rect", _0, _1}
+end
+
+local function double(n)
+    return n * 2
+end
+
+local function area(_t0)
+    if _t0.tag == "Circle" then
+        local r = _t0[1]
+        return 3 * r * r
+    elseif _t0.tag == "Rect" then
+        local w = _t0[1]
+        local h = _t0[2]
+        return w * h
+    end
+end
+
+local origin = setmetatable({x = 0, y = 1}, Point)
+local xs = {1, 2, 3}
+
+local size
+if origin.x == 0 then
+    size = "none"
+else
+    size = "some"
+end
+
+local total
+do
+    local a = double(2)
+    total = a + 1
+end
+
+local _t1 = Rect(2, 3)
+if _t1.tag == "Rect" and _t1[1] == 1 then
+    local h = _t1[2]
+    print("thin")
+else
+    print("wide")
+end
+
+print((((((((((("" .. __show(double(2))) .. " ") .. __show(double(3))) .. " ") .. __show(area(Circle(1)))) .. " ") .. __show(__at(xs, 1))) .. " ") .. __show(total)) .. " ") .. __show(size))
@@ -0,0 +1,58 @@
+-- writes values the way the vm does, numbers with the shortest digits that read back the same
+local function __show(v)
+    if type(v) ~= "number" then
+        return tostring(v)
+    elseif v ~= v then
+        return "nan"
+    elseif v == math.huge or v == -math.huge then
+        return v > 0 and "inf" or "-inf"
+    elseif v == 0 then
+        return "0"
+    elseif v % 1 == 0 and math.abs(v) < 1e15 then
+        return string.format("%.0f", v)
+    end
+
+    for precision = 1, 17 do
+        local written = string.format("%." .. (precision - 1) .. "e", v)
+
+        if tonumber(written) == v then
+            local sign, digits, exponent = written:match("^(-?)([%d.]+)e([-+]%d+)$")
+
+            digits   = digits:gsub("%.", "")
+            exponent = tonumber(exponent)
+
+            if exponent < 0 then
+                return sign .. "0." .. string.rep("0", -exponent - 1) .. digits
+            elseif exponent + 1 < #digits then
+                return sign .. digits:sub(1, exponent + 1) .. "." .. digits:sub(exponent + 2)
+            else
+                return sign .. digits .. string.rep("0", exponent + 1 - #digits)
+            end
+        end
+    end
+
+    return tostring(v)
+end
+
+local function adder(n)
+    local function add(x)
+        return x + n
+    end
+    return add
+end
+
+local total = 0
+
+for i = 0, 2 do
+    total = total + i
+end
+
+print("" .. __show(adder(total)(1)))
+
+local Point = {}
+Point.__index = Point
+
+for i = 0, 1 do
+    local p = setmetatable({x = i, y = 2}, Point)
+    print("" .. __show(p.x + p.y))
+end
@@ -0,0 +1,169 @@
+// how the `van` binary behaves as a command: its exit codes, where it reads from and what it prints
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{self, Command, Output, Stdio};
+
+fn van(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_van"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run van");
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().expect("failed to wait on van")
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn program(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs").join(name).display().to_string()
+}
+
+const HELLO: &str = "extern print: fun string -> nil\n\nprint \"hello\"\n";
+
+#[test]
+fn help() {
+    for args in [&[][..], &["--help"], &["run", "-h"]] {
+        let output = van(args, "");
+
+        assert!(output.status.success(), "{:?}", args);
+        assert!(text(&output.stdout).starts_with("usage: van"), "{:?}", args)
+    }
+}
+
+#[test]
+fn bad_arguments() {
+    for (args, message) in [
+        (&["frobnicate"][..],             "unknown command: frobnicate"),
+        (&["run", "--fast"],              "unknown option: --fast"),
+        (&["build", "-o"],                "expected path after -o"),
+        (&["build", "a", "b", "-o", "c"], "-o can only be used with a single input"),
+    ] {
+        let output = van(args, "");
+        let stderr = text(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr.contains(message), "{:?}: {}", args, stderr);
+        assert!(stderr.contains("usage: van"), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args)
+    }
+}
+
+#[test]
+fn missing_file() {
+    let output = van(&["check", "no/such/file.van"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(text(&output.stderr).contains("couldn't read no/such/file.van"))
+}
+
+#[test]
+fn stdin() {
+    for args in [&["run"][..], &["run", "-"], &["run", "--walk"]] {
+        let output = van(args, HELLO);
+
+        assert!(output.status.success(), "{:?}: {}", args, text(&output.stderr));
+        assert_eq!(text(&output.stdout), "hello\n", "{:?}", args)
+    }
+}
+
+#[test]
+fn errors_fail() {
+    let output = van(&["check"], "a: number = \"text\"\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(text(&output.stderr).contains("note: found 1 error"));
+
+    let output = van(&["run"], "a := 1 +\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty())
+}
+
+// every input is processed even when one of them fails, and the whole run fails with it
+#[test]
+fn several_inputs() {
+    let numbers = program("numbers.van");
+    let output  = van(&["check", &numbers, "no/such/file.van", &numbers], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(text(&output.stderr).matches("error:").count(), 1);
+
+    let output = van(&["check", &numbers, &program("strings.van")], "");
+
+    assert!(output.status.success(), "{}", text(&output.stderr))
+}
+
+// the ast is only printed when asked for
+#[test]
+fn ast() {
+    assert!(van(&["check"], HELLO).stdout.is_empty());
+    assert!(text(&van(&["check", "--ast"], HELLO).stdout).contains("Call"));
+    assert!(text(&van(&["parse"], HELLO).stdout).contains("Call"))
+}
+
+#[test]
+fn tokens() {
+    let output = van(&["tokens"], "a := 1\n");
+    let stdout = text(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.lines().next().unwrap().starts_with("1:1\tIdentifier\t\"a\""), "{}", stdout)
+}
+
+// lua goes to stdout, or to the file given with -o, and externs are left to the host
+#[test]
+fn build() {
+    let output = van(&["build"], HELLO);
+
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "print(\"hello\")\n");
+
+    let path   = env::temp_dir().join(format!("van-cli-{}.lua", process::id()));
+    let output = van(&["build", "-o", path.to_str().unwrap()], HELLO);
+    let built  = fs::read_to_string(&path);
+
+    let _ = fs::remove_file(&path);
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(built.unwrap(), "print(\"hello\")\n")
+}
+
+// nothing is built from a program that doesn't check
+#[test]
+fn build_errors() {
+    let output = van(&["build"], "a: number = \"text\"\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty())
+}
+
+// comments are kept as tokens, for the parser to skip
+#[test]
+fn comment_tokens() {
+    let stdout = text(&van(&["tokens"], "a := 1 # note\n#[ block ]#\n").stdout);
+
+    assert!(stdout.contains("1:8\tComment\t\"# note\""), "{}", stdout);
+    assert!(stdout.contains("2:1\tComment\t\"#[ block ]#\""), "{}", stdout)
+}
+
+// an import that fails is pointed at by the whole name of the module, and the file is still checked
+#[test]
+fn import_errors() {
+    let path   = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/errors/imports.van");
+    let output = van(&["check", path.to_str().unwrap()], "");
+    let stderr = text(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("| import modules.bad\n               ^^^^^^^^^^^\n"), "{}", stderr);
+    assert!(stderr.contains("| w: string = 4\n"), "{}", stderr)
+}
@@ -0,0 +1,218 @@
+// runs the programs in tests/programs on the vm, on the tree-walking interpreter and, when there's a lua
+// around or VAN_LUA names one, as lua, and checks that each prints what its `.out` file says. the files
+// in tests/errors are checked to give exactly the diagnostics their `# error` and `# warning` comments
+// list, and the ones in tests/failures to abort with them on every backend. the programs in tests/build
+// are built to exactly the lua or bytecode beside them, so the lua backend is covered even with no lua
+// to run it, and the vm is seen to resolve every name to a slot
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output};
+use std::sync::OnceLock;
+
+fn van(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_van"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run van")
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// what a program printed, or the diagnostics it failed with
+fn outcome(output: Output) -> Result<String, String> {
+    if output.status.success() {
+        Ok(text(&output.stdout))
+    } else {
+        Err(text(&output.stderr))
+    }
+}
+
+fn files(directory: &str) -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join(directory);
+
+    let mut files: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", directory.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "van"))
+        .collect();
+
+    files.sort();
+    files
+}
+
+// the lua VAN_LUA names, which has to run, or else the first one around. with none, programs aren't run
+// as lua, which is said where cargo doesn't hide it
+fn lua() -> Option<String> {
+    static LUA: OnceLock<Option<String>> = OnceLock::new();
+
+    LUA.get_or_init(|| {
+        if let Ok(lua) = env::var("VAN_LUA") {
+            Command::new(&lua).arg("-v").output().unwrap_or_else(|e| panic!("failed to run VAN_LUA, {}: {}", lua, e));
+            return Some(lua)
+        }
+
+        let found = ["lua5.4", "lua5.3", "lua"].iter().find(|lua| Command::new(lua).arg("-v").output().is_ok());
+
+        if found.is_none() {
+            let _ = writeln!(io::stderr(), "note: no lua found, so programs are only run on the vm and the interpreter; set VAN_LUA to require one");
+        }
+
+        found.map(|lua| lua.to_string())
+    }).clone()
+}
+
+// how running `path` went on each backend
+fn run(path: &Path) -> Vec<(&'static str, Result<String, String>)> {
+    let source = path.to_str().unwrap();
+
+    let mut outcomes = vec![
+        ("vm",   outcome(van(&["run", source]))),
+        ("walk", outcome(van(&["run", "--walk", source]))),
+    ];
+
+    if let Some(lua) = lua() {
+        let name  = path.file_stem().unwrap().to_str().unwrap();
+        let built = env::temp_dir().join(format!("van-{}-{}.lua", process::id(), name));
+        let build = van(&["build", source, "-o", built.to_str().unwrap()]);
+
+        let ran = if build.status.success() {
+            outcome(Command::new(&lua).arg(&built).output().expect("failed to run lua"))
+        } else {
+            outcome(build)
+        };
+
+        let _ = fs::remove_file(&built);
+
+        outcomes.push(("lua", ran))
+    }
+
+    outcomes
+}
+
+// the diagnostics in what `check` printed, as `error 2:6: message`, or `error: message` when there's
+// nothing to point at
+fn diagnostics(stderr: &str) -> Vec<String> {
+    let lines: Vec<&str> = stderr.lines().collect();
+    let mut found        = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let (kind, message) = match line.split_once(": ") {
+            Some((kind @ "error", message)) | Some((kind @ "warning", message)) => (kind, message),
+            _                                                                  => continue,
+        };
+
+        let location = match (lines.get(i + 2), lines.get(i + 3)) {
+            (Some(source), Some(indicator)) if lines[i + 1].trim() == "|" => {
+                let line = source.split(" |").next().unwrap().trim();
+                let col  = indicator.find('^').unwrap() - source.find('|').unwrap() - 1;
+
+                format!(" {}:{}", line, col)
+            },
+
+            _ => String::new(),
+        };
+
+        found.push(format!("{}{}: {}", kind, location, message))
+    }
+
+    found.sort();
+    found
+}
+
+#[test]
+fn programs() {
+    for path in files("tests/programs") {
+        let expected = fs::read_to_string(path.with_extension("out"))
+            .unwrap_or_else(|e| panic!("failed to read what {} should print: {}", path.display(), e));
+
+        for (backend, outcome) in run(&path) {
+            assert_eq!(outcome, Ok(expected.clone()), "{} on {}", path.display(), backend)
+        }
+    }
+}
+
+#[test]
+fn builds() {
+    for path in files("tests/build") {
+        let mut compared = false;
+
+        for &(command, extension) in &[("build", "lua"), ("disasm", "disasm")] {
+            if let Ok(expected) = fs::read_to_string(path.with_extension(extension)) {
+                assert_eq!(outcome(van(&[command, path.to_str().unwrap()])), Ok(expected), "{} {}", command, path.display());
+                compared = true
+            }
+        }
+
+        assert!(compared, "{} has nothing to compare against", path.display())
+    }
+}
+
+// not every sample checks, but each backend should agree on what they do
+#[test]
+fn samples() {
+    for path in files("samples") {
+        let outcomes = run(&path);
+
+        for &(backend, ref outcome) in &outcomes[1 ..] {
+            assert_eq!(*outcome, outcomes[0].1, "{} on {} and {}", path.display(), outcomes[0].0, backend)
+        }
+    }
+}
+
+// the diagnostics the comments in `path` say it gives
+fn expected(path: &Path) -> Vec<String> {
+    let source = fs::read_to_string(path).unwrap();
+
+    let mut expected: Vec<String> = source.lines()
+        .filter_map(|line| line.trim().strip_prefix("# "))
+        .filter(|comment| comment.starts_with("error") || comment.starts_with("warning"))
+        .map(String::from)
+        .collect();
+
+    expected.sort();
+    expected
+}
+
+#[test]
+fn errors() {
+    for path in files("tests/errors") {
+        let expected = expected(&path);
+        let output = van(&["check", path.to_str().unwrap()]);
+        let failed = expected.iter().any(|diagnostic| diagnostic.starts_with("error"));
+
+        assert_eq!(diagnostics(&text(&output.stderr)), expected, "{}", path.display());
+        assert_eq!(output.status.success(), !failed, "{}", path.display())
+    }
+}
+
+// lua points at lines of its own, so it's only held to the messages, and not at all by a file saying
+// `# not on lua: why`
+#[test]
+fn failures() {
+    for path in files("tests/failures") {
+        let expected = expected(&path);
+        let aborted  = format!("error: {}: aborted at runtime", path.display());
+        let on_lua   = !fs::read_to_string(&path).unwrap().contains("# not on lua:");
+
+        for (backend, outcome) in run(&path).into_iter().filter(|&(backend, _)| backend != "lua" || on_lua) {
+            let stderr = outcome.expect_err(&format!("{} should fail on {}", path.display(), backend));
+
+            if backend == "lua" {
+                for message in expected.iter().map(|e| e.split_once(": ").unwrap().1) {
+                    assert!(stderr.contains(message), "{} on lua: {}", path.display(), stderr)
+                }
+
+                continue
+            }
+
+            let found: Vec<String> = diagnostics(&stderr).into_iter().filter(|found| *found != aborted).collect();
+
+            assert_eq!(found, expected, "{} on {}", path.display(), backend)
+        }
+    }
+}